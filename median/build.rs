@@ -114,6 +114,7 @@ fn gen_method(perms: &Vec<Vec<Arg>>) -> Result<(), Box<dyn std::error::Error>> {
             Anything(SelList<T>),
             Sel(&'a str, B<T>),
             SelVarArg(&'a str, SelList<T>),
+            SelGimmeBack(&'a str, SelGimmeBack<T>),
             #(#variants),*
         }
         }
@@ -212,6 +213,16 @@ fn gen_class(perms: &Vec<Vec<Arg>>) -> Result<(), Box<dyn std::error::Error>> {
                 )
             }
         },
+        quote! {
+            Method::SelGimmeBack(sel, f) => {
+                self.add_sel_method(
+                    sel,
+                    Some(std::mem::transmute::<crate::method::SelGimmeBack<T>, MaxMethod>(f)),
+                    &mut [max_sys::e_max_atomtypes::A_GIMMEBACK],
+                    0,
+                )
+            }
+        },
         quote! {
             Method::SelVarArg(sel, f) => {
                 self.add_sel_method(
@@ -243,7 +254,7 @@ fn gen_class(perms: &Vec<Vec<Arg>>) -> Result<(), Box<dyn std::error::Error>> {
     f.write_all(
         quote! {
         impl<T> Class<T> {
-            pub fn add_method(&mut self, m: Method<T>) -> crate::error::MaxResult<()> {
+            fn add_method_max(&mut self, m: Method<T>) -> crate::error::MaxResult<()> {
                 unsafe {
                     let r = match m {
                         #(#matches)*
@@ -4,9 +4,10 @@ use median::{
     wrapper::{MSPObjWrapped, MaxObjWrapped},
 };
 
-//create an external but don't implement ext_main (we do it explicitly below)
-median::external_no_main! {
+//create two classes in one external, ext_main registers both
+median::external! {
     #[name="median.multi"]
+    #[alias("median.multi.alias")]
     pub struct Base { }
 
     impl MaxObjWrapped<Base> for Base {
@@ -15,9 +16,7 @@ median::external_no_main! {
             Self { }
         }
     }
-}
 
-median::external_no_main! {
     #[name="median.multi.sig~"]
     pub struct Sig { }
 
@@ -37,9 +36,3 @@ median::external_no_main! {
         }
     }
 }
-
-//create an ext_main, register our objects
-median::ext_main! {
-    Base::register();
-    Sig::register();
-}
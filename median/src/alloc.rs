@@ -24,12 +24,170 @@ use std::alloc::{GlobalAlloc, Layout};
 /// ```
 pub struct MaxAllocator;
 
+//the alignment that sysmem_newptr guarantees, it uses the system malloc
+#[cfg(target_pointer_width = "64")]
+const MIN_ALIGN: usize = 16;
+#[cfg(not(target_pointer_width = "64"))]
+const MIN_ALIGN: usize = 8;
+
+impl MaxAllocator {
+    //can the layout be allocated directly with sysmem, without adjusting the pointer
+    fn direct(layout: &Layout) -> bool {
+        layout.align() <= MIN_ALIGN && layout.align() <= layout.size()
+    }
+
+    //over allocate by the alignment and store the offset to the sysmem pointer just before the
+    //pointer we return, the offset is a non zero multiple of MIN_ALIGN so there is always room
+    unsafe fn alloc_aligned(layout: Layout, zeroed: bool) -> *mut u8 {
+        let align = layout.align().max(MIN_ALIGN);
+        let size = match layout.size().checked_add(align) {
+            Some(s) => s,
+            None => return std::ptr::null_mut(),
+        };
+        let ptr: *mut u8 = if zeroed {
+            max_sys::sysmem_newptrclear(size as _) as _
+        } else {
+            max_sys::sysmem_newptr(size as _) as _
+        };
+        if ptr.is_null() {
+            return ptr;
+        }
+        let offset = align - (ptr as usize & (align - 1));
+        let aligned = ptr.add(offset);
+        (aligned as *mut usize).sub(1).write(offset);
+        aligned
+    }
+
+    unsafe fn sysmem_ptr(ptr: *mut u8) -> *mut u8 {
+        ptr.sub((ptr as *mut usize).sub(1).read())
+    }
+}
+
 unsafe impl GlobalAlloc for MaxAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        //XXX do we need to worry about alignment?
-        max_sys::sysmem_newptr(layout.size() as _) as _
+        crate::rt::check::note("allocation");
+        if Self::direct(&layout) {
+            max_sys::sysmem_newptr(layout.size() as _) as _
+        } else {
+            Self::alloc_aligned(layout, false)
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        crate::rt::check::note("allocation");
+        if Self::direct(&layout) {
+            max_sys::sysmem_newptrclear(layout.size() as _) as _
+        } else {
+            Self::alloc_aligned(layout, true)
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        crate::rt::check::note("deallocation");
+        if Self::direct(&layout) {
+            max_sys::sysmem_freeptr(ptr as _);
+        } else {
+            max_sys::sysmem_freeptr(Self::sysmem_ptr(ptr) as _);
+        }
     }
-    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
-        max_sys::sysmem_freeptr(ptr as _);
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        crate::rt::check::note("allocation");
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        if Self::direct(&layout) && Self::direct(&new_layout) {
+            max_sys::sysmem_resizeptr(ptr as _, new_size as _) as _
+        } else {
+            //the offset can change when resizing, so copy into a new aligned allocation
+            let new = self.alloc(new_layout);
+            if !new.is_null() {
+                std::ptr::copy_nonoverlapping(ptr, new, layout.size().min(new_size));
+                self.dealloc(ptr, layout);
+            }
+            new
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment() {
+        let a = MaxAllocator;
+        for align in (0..=12).map(|s| 1usize << s) {
+            for size in [1, 3, 8, 16, 33, 100, 4096] {
+                let layout = Layout::from_size_align(size, align).unwrap();
+                unsafe {
+                    let ptrs: Vec<*mut u8> = (0..4)
+                        .map(|i| {
+                            let p = a.alloc(layout);
+                            assert!(!p.is_null());
+                            assert_eq!(p as usize % align, 0, "{:?}", layout);
+                            std::ptr::write_bytes(p, i as u8, size);
+                            p
+                        })
+                        .collect();
+                    for (i, p) in ptrs.into_iter().enumerate() {
+                        assert!(std::slice::from_raw_parts(p, size)
+                            .iter()
+                            .all(|b| *b == i as u8));
+                        a.dealloc(p, layout);
+                    }
+
+                    let p = a.alloc_zeroed(layout);
+                    assert_eq!(p as usize % align, 0, "{:?}", layout);
+                    assert!(std::slice::from_raw_parts(p, size).iter().all(|b| *b == 0));
+                    a.dealloc(p, layout);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn realloc() {
+        let a = MaxAllocator;
+        for align in [1, 8, 16, 64, 4096] {
+            unsafe {
+                let layout = Layout::from_size_align(10, align).unwrap();
+                let mut p = a.alloc(layout);
+                for i in 0..10 {
+                    *p.add(i) = i as u8;
+                }
+                let mut size = 10;
+                for new_size in [1000, 20, 5000, 3] {
+                    p = a.realloc(p, Layout::from_size_align(size, align).unwrap(), new_size);
+                    assert!(!p.is_null());
+                    assert_eq!(p as usize % align, 0);
+                    let kept = size.min(new_size).min(10);
+                    assert!((0..kept).all(|i| *p.add(i) == i as u8));
+                    size = new_size;
+                }
+                a.dealloc(p, Layout::from_size_align(size, align).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn over_aligned() {
+        #[repr(align(64))]
+        struct Simd([f32; 16]);
+
+        let a = MaxAllocator;
+        let layout = Layout::new::<Simd>();
+        unsafe {
+            let p = a.alloc(layout) as *mut Simd;
+            assert_eq!(p as usize % 64, 0);
+            p.write(Simd([1.0; 16]));
+            assert_eq!((*p).0.iter().sum::<f32>(), 16.0);
+            a.dealloc(p as _, layout);
+
+            let layout = Layout::array::<Simd>(7).unwrap();
+            let p = a.alloc(layout);
+            assert_eq!(p as usize % 64, 0);
+            let p = a.realloc(p, layout, Layout::array::<Simd>(100).unwrap().size());
+            assert_eq!(p as usize % 64, 0);
+            a.dealloc(p, Layout::array::<Simd>(100).unwrap());
+        }
     }
 }
@@ -0,0 +1,318 @@
+//! Typed creation arguments.
+
+use crate::{
+    atom::{Atom, AtomType},
+    symbol::SymbolRef,
+};
+use std::convert::TryFrom;
+
+/// Derive [`CreationArgs`](trait.CreationArgs.html) for a struct with named fields.
+///
+/// # Remarks
+/// * Fields are parsed from the arguments in the order they are declared.
+/// * `Option<T>` fields are `None` if the argument is missing.
+/// * `#[arg(default)]` fields use `Default::default()` if the argument is missing, and
+/// `#[arg(default = expr)]` fields use `expr`.
+/// * Other fields are required.
+/// * A final `#[arg(rest)]` field of type `Vec<Atom>` gets the arguments after the others,
+/// without it extra arguments are ignored.
+pub use median_macros::CreationArgs;
+
+/// Typed creation arguments, usually implemented with `#[derive(CreationArgs)]`.
+///
+/// # Remarks
+/// * The arguments given to `parse` don't include `@attr value` pairs, see
+/// [`attr_args_offset`](fn.attr_args_offset.html).
+///
+/// # Examples
+/// ```ignore
+/// use median::args::CreationArgs;
+///
+/// #[derive(CreationArgs)]
+/// struct Args {
+///     size: t_atom_long,
+///     name: Option<SymbolRef>,
+///     #[arg(default = 0.5)]
+///     gain: f64,
+///     #[arg(rest)]
+///     rest: Vec<Atom>,
+/// }
+///
+/// fn new(builder: &mut dyn MaxWrappedBuilder<Self>) -> Self {
+///     //errors have already been posted to the console for the object
+///     let args: Args = builder.parse_args().unwrap_or_else(|_| Args {
+///         size: 1,
+///         name: None,
+///         gain: 0.5,
+///         rest: Vec::new(),
+///     });
+///     ...
+/// }
+/// ```
+pub trait CreationArgs: Sized {
+    fn parse(args: &[Atom]) -> Result<Self, ArgsError>;
+}
+
+/// Errors from parsing creation arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgsError {
+    /// A required argument is missing.
+    Missing { index: usize, name: &'static str },
+    /// An argument has the wrong type, or is out of range.
+    Invalid {
+        index: usize,
+        name: &'static str,
+        expected: &'static str,
+    },
+}
+
+impl std::fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { index, name } => {
+                write!(f, "missing argument {} ({})", index + 1, name)
+            }
+            Self::Invalid {
+                index,
+                name,
+                expected,
+            } => write!(
+                f,
+                "argument {} ({}) should be {}",
+                index + 1,
+                name,
+                expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+/// A value parsed from a single creation argument.
+pub trait FromArg: Sized {
+    /// What the argument should be, for error messages, for instance "an int".
+    const EXPECTED: &'static str;
+
+    /// Convert the atom, `None` if it has the wrong type or is out of range.
+    fn from_arg(atom: &Atom) -> Option<Self>;
+}
+
+//like Max, numbers convert to each other
+fn number(atom: &Atom) -> Option<Atom> {
+    match atom.get_type() {
+        Some(AtomType::Int) | Some(AtomType::Float) => Some(atom.clone()),
+        _ => None,
+    }
+}
+
+impl FromArg for f64 {
+    const EXPECTED: &'static str = "a number";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        number(atom).map(|a| a.get_float())
+    }
+}
+
+impl FromArg for f32 {
+    const EXPECTED: &'static str = "a number";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        number(atom).map(|a| a.get_float() as f32)
+    }
+}
+
+impl FromArg for max_sys::t_atom_long {
+    const EXPECTED: &'static str = "a number";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        number(atom).map(|a| a.get_int())
+    }
+}
+
+impl FromArg for i32 {
+    const EXPECTED: &'static str = "a 32 bit int";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        number(atom).and_then(|a| i32::try_from(a.get_int()).ok())
+    }
+}
+
+impl FromArg for usize {
+    const EXPECTED: &'static str = "a positive int";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        number(atom).and_then(|a| usize::try_from(a.get_int()).ok())
+    }
+}
+
+impl FromArg for bool {
+    const EXPECTED: &'static str = "a number";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        number(atom).map(|a| a.get_int() != 0)
+    }
+}
+
+impl FromArg for SymbolRef {
+    const EXPECTED: &'static str = "a symbol";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        match atom.get_type() {
+            Some(AtomType::Symbol) => Some(atom.get_symbol()),
+            _ => None,
+        }
+    }
+}
+
+impl FromArg for String {
+    const EXPECTED: &'static str = "a symbol";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        SymbolRef::from_arg(atom).and_then(|s| s.to_string().ok())
+    }
+}
+
+impl FromArg for Atom {
+    const EXPECTED: &'static str = "an atom";
+    fn from_arg(atom: &Atom) -> Option<Self> {
+        Some(atom.clone())
+    }
+}
+
+/// Get the index of the first `@attr` argument, or the length of `args` if there are none.
+pub fn attr_args_offset(args: &[Atom]) -> usize {
+    args.iter()
+        .position(|a| {
+            a.get_type() == Some(AtomType::Symbol)
+                && a.get_symbol()
+                    .to_string()
+                    .map(|s| s.starts_with('@'))
+                    .unwrap_or(false)
+        })
+        .unwrap_or(args.len())
+}
+
+//used by the CreationArgs derive
+
+#[doc(hidden)]
+pub fn optional<T: FromArg>(
+    args: &[Atom],
+    index: &mut usize,
+    name: &'static str,
+) -> Result<Option<T>, ArgsError> {
+    match args.get(*index) {
+        None => Ok(None),
+        Some(a) => {
+            let v = T::from_arg(a).ok_or(ArgsError::Invalid {
+                index: *index,
+                name,
+                expected: T::EXPECTED,
+            })?;
+            *index += 1;
+            Ok(Some(v))
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn required<T: FromArg>(
+    args: &[Atom],
+    index: &mut usize,
+    name: &'static str,
+) -> Result<T, ArgsError> {
+    optional(args, index, name)?.ok_or(ArgsError::Missing {
+        index: *index,
+        name,
+    })
+}
+
+#[doc(hidden)]
+pub fn rest(args: &[Atom], index: &mut usize) -> Vec<Atom> {
+    let rest = args.get(*index..).unwrap_or(&[]).to_vec();
+    *index = args.len();
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let args = [
+            Atom::from(2),
+            Atom::from(crate::sym!("foo")),
+            Atom::from(0.5),
+            Atom::from(crate::sym!("@size")),
+            Atom::from(3),
+        ];
+        let end = attr_args_offset(&args);
+        assert_eq!(end, 3);
+        let args = &args[..end];
+
+        let mut index = 0;
+        assert_eq!(required::<i32>(args, &mut index, "a"), Ok(2));
+        assert_eq!(
+            required::<f64>(args, &mut index, "b"),
+            Err(ArgsError::Invalid {
+                index: 1,
+                name: "b",
+                expected: "a number"
+            })
+        );
+        assert!(required::<SymbolRef>(args, &mut index, "b").is_ok());
+        assert_eq!(rest(args, &mut index).len(), 1);
+        assert_eq!(optional::<f64>(args, &mut index, "c"), Ok(None));
+        assert_eq!(
+            required::<f64>(args, &mut index, "c")
+                .unwrap_err()
+                .to_string(),
+            "missing argument 4 (c)"
+        );
+    }
+
+    #[derive(CreationArgs)]
+    struct Args {
+        size: max_sys::t_atom_long,
+        name: Option<SymbolRef>,
+        #[arg(default = 0.5)]
+        gain: f64,
+        #[arg(default)]
+        count: usize,
+        #[arg(rest)]
+        rest: Vec<Atom>,
+    }
+
+    #[test]
+    fn derive() {
+        let args = Args::parse(&[
+            Atom::from(2),
+            Atom::from(crate::sym!("foo")),
+            Atom::from(1),
+            Atom::from(3),
+            Atom::from(4),
+            Atom::from(crate::sym!("bar")),
+        ])
+        .unwrap();
+        assert_eq!(args.size, 2);
+        assert!(args.name == Some(crate::sym!("foo")));
+        assert_eq!(args.gain, 1.);
+        assert_eq!(args.count, 3);
+        assert_eq!(args.rest.len(), 2);
+
+        let args = Args::parse(&[Atom::from(2)]).unwrap();
+        assert!(args.name.is_none());
+        assert_eq!(args.gain, 0.5);
+        assert_eq!(args.count, 0);
+        assert!(args.rest.is_empty());
+
+        assert_eq!(
+            Args::parse(&[]).err(),
+            Some(ArgsError::Missing {
+                index: 0,
+                name: "size"
+            })
+        );
+        assert_eq!(
+            Args::parse(&[Atom::from(2), Atom::from(0.5)]).err(),
+            Some(ArgsError::Invalid {
+                index: 1,
+                name: "name",
+                expected: "a symbol"
+            })
+        );
+    }
+}
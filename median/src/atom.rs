@@ -2,11 +2,27 @@
 //!
 //! see [cycling 74 docs](https://cycling74.com/sdk/max-sdk-8.0.3/html/group__atom.html)
 
-use crate::symbol::SymbolRef;
+use crate::{error::MaxError, symbol::SymbolRef};
 use core::ffi::c_void;
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_long},
+};
+
+pub mod de;
+pub mod ser;
+pub mod text;
+pub mod vec;
+
+pub use self::{
+    de::from_atoms,
+    ser::to_atoms,
+    text::TextError,
+    vec::{AtomVec, Values},
+};
 
 /// The type of data that an atom stores.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AtomType {
     Int,
     Float,
@@ -15,6 +31,7 @@ pub enum AtomType {
 }
 
 /// Typed atom data.
+#[derive(Clone)]
 pub enum AtomValue {
     Int(max_sys::t_atom_long),
     Float(f64),
@@ -22,7 +39,77 @@ pub enum AtomValue {
     Object(*mut c_void),
 }
 
+/// Format atoms as text, using Max's `atom_gettext`.
+///
+/// # Remarks
+/// * See [`text::format`](text/fn.format.html) for a version that doesn't need Max.
+pub fn atoms_to_text(atoms: &[Atom]) -> Result<String, TextError> {
+    if atoms.is_empty() {
+        return Ok(String::new());
+    }
+    unsafe {
+        let mut size: c_long = 0;
+        let mut text: *mut c_char = std::ptr::null_mut();
+        MaxError::from(
+            max_sys::atom_gettext(
+                atoms.len() as _,
+                //Atom is transparent, so it can be cast to t_atom, max doesn't mutate
+                atoms.as_ptr() as *mut max_sys::t_atom,
+                &mut size,
+                &mut text,
+                max_sys::e_max_atom_gettext_flags::OBEX_UTIL_ATOM_GETTEXT_NUM_HI_RES as _,
+            ) as _,
+            (),
+        )
+        .map_err(TextError::Max)?;
+        if text.is_null() {
+            return Ok(String::new());
+        }
+        let s = CStr::from_ptr(text)
+            .to_str()
+            .map(|s| s.to_string())
+            .map_err(|_| TextError::InvalidSymbol);
+        max_sys::sysmem_freeptr(text as _);
+        s
+    }
+}
+
+/// Format a selector and atoms as text, using Max's `atom_gettext`, the reverse of
+/// [`Atom::parse_text`](struct.Atom.html#method.parse_text).
+pub fn message_to_text(selector: &SymbolRef, atoms: &[Atom]) -> Result<String, TextError> {
+    if text::implicit_selector(selector, atoms) {
+        atoms_to_text(atoms)
+    } else {
+        let mut all = vec![Atom::from(selector)];
+        all.extend(atoms.iter().map(|a| Atom { value: a.value }));
+        atoms_to_text(&all)
+    }
+}
+
+/// Errors from serializing to, or deserializing from, atoms.
+#[derive(Debug)]
+pub enum SerdeError {
+    /// A custom error from a `Serialize` or `Deserialize` implementation.
+    Message(String),
+    /// Ran out of atoms before the value was complete.
+    Eof,
+    /// The value was complete but there were atoms left over, holds the count.
+    TrailingAtoms(usize),
+    /// The atom found doesn't have the type that was expected.
+    UnexpectedType {
+        expected: AtomType,
+        found: Option<AtomType>,
+    },
+    /// An integer or length doesn't fit in the target type.
+    IntegerOverflow,
+    /// A string couldn't be converted to a symbol, or the reverse.
+    InvalidSymbol,
+    /// The type can't be represented in atoms.
+    Unsupported(&'static str),
+}
+
 #[repr(transparent)]
+#[derive(Clone)]
 pub struct Atom {
     pub(crate) value: max_sys::t_atom,
 }
@@ -95,7 +182,38 @@ impl Atom {
         }
     }
 
-    unsafe fn zeroed() -> Self {
+    /// Parse Max message text into a selector and atoms, using Max's `atom_setparse`.
+    ///
+    /// # Remarks
+    /// * If the text starts with a number, the selector is `int` or `float` for a single number and
+    /// `list` otherwise, and the atoms include the number.
+    /// * See [`text::parse_message`](text/fn.parse_message.html) for a version that doesn't need
+    /// Max.
+    pub fn parse_text(text: &str) -> Result<(SymbolRef, Vec<Atom>), TextError> {
+        let text = CString::new(text).map_err(|_| TextError::InvalidSymbol)?;
+        let atoms = unsafe {
+            let mut ac: c_long = 0;
+            let mut av: *mut max_sys::t_atom = std::ptr::null_mut();
+            MaxError::from(
+                max_sys::atom_setparse(&mut ac, &mut av, text.as_ptr()) as _,
+                (),
+            )
+            .map_err(TextError::Max)?;
+            if av.is_null() {
+                Vec::new()
+            } else {
+                let atoms = std::slice::from_raw_parts(av, ac as usize)
+                    .iter()
+                    .map(|a| Atom { value: *a })
+                    .collect();
+                max_sys::sysmem_freeptr(av as _);
+                atoms
+            }
+        };
+        text::split_selector(atoms)
+    }
+
+    pub(crate) unsafe fn zeroed() -> Self {
         Self {
             value: std::mem::MaybeUninit::<max_sys::t_atom>::zeroed().assume_init(),
         }
@@ -112,6 +230,12 @@ impl From<i64> for Atom {
     }
 }
 
+impl From<i32> for Atom {
+    fn from(v: i32) -> Self {
+        Self::from(v as max_sys::t_atom_long)
+    }
+}
+
 impl From<&i64> for Atom {
     fn from(v: &i64) -> Self {
         Self::from(*v as max_sys::t_atom_long)
@@ -150,6 +274,12 @@ impl From<f64> for Atom {
     }
 }
 
+impl From<f32> for Atom {
+    fn from(v: f32) -> Self {
+        Self::from(v as f64)
+    }
+}
+
 impl From<&f64> for Atom {
     fn from(v: &f64) -> Self {
         Self::from(*v)
@@ -182,6 +312,31 @@ impl From<&SymbolRef> for Atom {
     }
 }
 
+/// Create a symbol atom.
+///
+/// # Remarks
+/// * Like Max, the symbol ends at the first nul character, if there is one.
+impl From<&str> for Atom {
+    fn from(v: &str) -> Self {
+        let v = v.split('\0').next().unwrap_or_default();
+        Self::from(SymbolRef::from(
+            CString::new(v).expect("nul bytes have been removed"),
+        ))
+    }
+}
+
+impl From<String> for Atom {
+    fn from(v: String) -> Self {
+        Self::from(v.as_str())
+    }
+}
+
+impl From<&Atom> for Atom {
+    fn from(v: &Atom) -> Self {
+        v.clone()
+    }
+}
+
 impl From<*mut c_void> for Atom {
     fn from(v: *mut c_void) -> Self {
         unsafe {
@@ -231,6 +386,36 @@ impl Default for Atom {
     }
 }
 
+impl std::fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Message(m) => write!(f, "{}", m),
+            Self::Eof => write!(f, "unexpected end of atoms"),
+            Self::TrailingAtoms(c) => write!(f, "{} trailing atoms", c),
+            Self::UnexpectedType { expected, found } => {
+                write!(f, "expected {:?} atom, found {:?}", expected, found)
+            }
+            Self::IntegerOverflow => write!(f, "integer out of range"),
+            Self::InvalidSymbol => write!(f, "invalid symbol"),
+            Self::Unsupported(s) => write!(f, "unsupported: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for SerdeError {}
+
+impl serde::ser::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+impl serde::de::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,4 +430,16 @@ mod tests {
             std::mem::align_of::<Atom>()
         );
     }
+
+    #[test]
+    fn text() {
+        let (sel, atoms) = Atom::parse_text("set 1 2.5 \"foo bar\"").unwrap();
+        assert_eq!(sel.to_string().unwrap(), "set");
+        assert_eq!(atoms.len(), 3);
+        assert_eq!(atoms_to_text(&atoms).unwrap(), "1 2.5 \"foo bar\"");
+        assert_eq!(
+            message_to_text(&sel, &atoms).unwrap(),
+            "set 1 2.5 \"foo bar\""
+        );
+    }
 }
@@ -0,0 +1,521 @@
+//! Deserialize rust values from a flat list of atoms.
+//!
+//! See [`to_atoms`](../ser/fn.to_atoms.html) for the encoding.
+
+use super::{Atom, AtomType, SerdeError};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use std::convert::TryFrom;
+
+type Result<T> = std::result::Result<T, SerdeError>;
+
+/// Deserialize a value from a flat list of atoms.
+///
+/// # Remarks
+/// * The encoding is documented in [`to_atoms`](../ser/fn.to_atoms.html).
+/// * Floats will accept int atoms, other types must match exactly.
+/// * All of the atoms must be consumed, leftovers are reported as `SerdeError::TrailingAtoms`.
+///
+/// # Examples
+/// ```ignore
+/// #[derive(Deserialize)]
+/// struct Note {
+///     pitch: i64,
+///     velocity: f64,
+///     tags: Vec<String>,
+/// }
+///
+/// #[list]
+/// pub fn list(&self, atoms: &[Atom]) {
+///     match median::atom::from_atoms::<Note>(atoms) {
+///         Ok(note) => self.play(note),
+///         Err(e) => median::error!("bad note: {}", e),
+///     }
+/// }
+/// ```
+pub fn from_atoms<T: DeserializeOwned>(atoms: &[Atom]) -> Result<T> {
+    let mut de = Deserializer {
+        atoms,
+        pos: 0,
+        tail: true,
+    };
+    let v = T::deserialize(&mut de)?;
+    if de.pos == atoms.len() {
+        Ok(v)
+    } else {
+        Err(SerdeError::TrailingAtoms(atoms.len() - de.pos))
+    }
+}
+
+/// The deserializer used by [`from_atoms`](fn.from_atoms.html).
+pub struct Deserializer<'a> {
+    atoms: &'a [Atom],
+    pos: usize,
+    tail: bool,
+}
+
+impl<'a> Deserializer<'a> {
+    fn peek(&self) -> Result<&'a Atom> {
+        self.atoms.get(self.pos).ok_or(SerdeError::Eof)
+    }
+
+    fn expect(&mut self, t: AtomType) -> Result<&'a Atom> {
+        let a = self.peek()?;
+        match a.get_type() {
+            Some(found) if found == t => {
+                self.pos += 1;
+                Ok(a)
+            }
+            found => Err(SerdeError::UnexpectedType { expected: t, found }),
+        }
+    }
+
+    fn next_int(&mut self) -> Result<max_sys::t_atom_long> {
+        self.expect(AtomType::Int).map(|a| a.get_int())
+    }
+
+    fn next_float(&mut self) -> Result<f64> {
+        let a = self.peek()?;
+        match a.get_type() {
+            Some(AtomType::Int) | Some(AtomType::Float) => {
+                self.pos += 1;
+                Ok(a.get_float())
+            }
+            found => Err(SerdeError::UnexpectedType {
+                expected: AtomType::Float,
+                found,
+            }),
+        }
+    }
+
+    fn next_string(&mut self) -> Result<String> {
+        self.expect(AtomType::Symbol)?
+            .get_symbol()
+            .to_string()
+            .map_err(|_| SerdeError::InvalidSymbol)
+    }
+
+    //the number of items in a sequence or map, None means read until the end
+    fn len(&mut self) -> Result<Option<usize>> {
+        if self.tail {
+            Ok(None)
+        } else {
+            let len = self.next_int()?;
+            usize::try_from(len)
+                .map(Some)
+                .map_err(|_| SerdeError::IntegerOverflow)
+        }
+    }
+
+    fn items(&mut self) -> Result<Items<'_, 'a>> {
+        let len = self.len()?;
+        Ok(Items { de: self, len })
+    }
+
+    fn fields(&mut self, len: usize) -> Fields<'_, 'a> {
+        let tail = self.tail;
+        Fields {
+            de: self,
+            remaining: len,
+            tail,
+        }
+    }
+}
+
+//tuple, struct and variant fields, the last one inherits the tail position
+struct Fields<'b, 'a> {
+    de: &'b mut Deserializer<'a>,
+    remaining: usize,
+    tail: bool,
+}
+
+//sequence and map items, never in tail position
+struct Items<'b, 'a> {
+    de: &'b mut Deserializer<'a>,
+    len: Option<usize>,
+}
+
+impl<'b, 'a> Items<'b, 'a> {
+    fn has_next(&mut self) -> bool {
+        match self.len.as_mut() {
+            Some(0) => false,
+            Some(len) => {
+                *len -= 1;
+                true
+            }
+            None => self.de.pos < self.de.atoms.len(),
+        }
+    }
+}
+
+struct Enum<'b, 'a> {
+    de: &'b mut Deserializer<'a>,
+}
+
+impl<'de, 'b, 'a> de::Deserializer<'de> for &'b mut Deserializer<'a> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let a = self.peek()?;
+        match a.get_type() {
+            Some(AtomType::Int) => self.deserialize_i64(visitor),
+            Some(AtomType::Float) => self.deserialize_f64(visitor),
+            Some(AtomType::Symbol) => self.deserialize_string(visitor),
+            _ => Err(SerdeError::Unsupported("object atoms")),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.next_int()? != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(self.next_int()? as i64)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(self.next_float()?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.next_string()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut items = self.items()?;
+        let mut bytes = Vec::new();
+        while items.has_next() {
+            let b = items.de.next_int()?;
+            bytes.push(u8::try_from(b).map_err(|_| SerdeError::IntegerOverflow)?);
+        }
+        visitor.visit_byte_buf(bytes)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.next_int()? == 0 {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.items()?)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.fields(len))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(self.fields(len))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(self.items()?)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(self.fields(fields.len()))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(Enum { de: self })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+}
+
+impl<'de, 'b, 'a> de::SeqAccess<'de> for Fields<'b, 'a> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        self.de.tail = self.tail && self.remaining == 0;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, 'b, 'a> de::SeqAccess<'de> for Items<'b, 'a> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        if !self.has_next() {
+            return Ok(None);
+        }
+        self.de.tail = false;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.len
+    }
+}
+
+impl<'de, 'b, 'a> de::MapAccess<'de> for Items<'b, 'a> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if !self.has_next() {
+            return Ok(None);
+        }
+        self.de.tail = false;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        self.de.tail = false;
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.len
+    }
+}
+
+impl<'de, 'b, 'a> de::EnumAccess<'de> for Enum<'b, 'a> {
+    type Error = SerdeError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let name = self.de.next_string()?;
+        let v = seed.deserialize(name.into_deserializer())?;
+        Ok((v, self))
+    }
+}
+
+impl<'de, 'b, 'a> de::VariantAccess<'de> for Enum<'b, 'a> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self.de.fields(len))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(self.de.fields(fields.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::to_atoms;
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: i64, h: i64 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Message {
+        id: u32,
+        name: String,
+        on: bool,
+        gain: Option<f64>,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, i64>,
+        rest: Vec<i64>,
+    }
+
+    fn roundtrip<T>(v: &T) -> Vec<Atom>
+    where
+        T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let atoms = to_atoms(v).unwrap();
+        let back: T = from_atoms(&atoms).unwrap();
+        assert_eq!(v, &back);
+        atoms
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(roundtrip(&-12i8).len(), 1);
+        assert_eq!(roundtrip(&2.5f64).len(), 1);
+        assert_eq!(roundtrip(&true).len(), 1);
+        assert_eq!(roundtrip(&'x').len(), 1);
+        assert_eq!(roundtrip(&String::from("foo")).len(), 1);
+        assert_eq!(roundtrip(&()).len(), 0);
+        assert_eq!(
+            from_atoms::<f32>(&[Atom::from(3 as max_sys::t_atom_long)]).unwrap(),
+            3.0
+        );
+    }
+
+    #[test]
+    fn tail() {
+        let atoms = roundtrip(&(1i64, vec![2i64, 3, 4]));
+        assert_eq!(atoms.len(), 4);
+        assert_eq!(atoms[1].get_int(), 2);
+
+        //not in the tail, so there is a length prefix
+        let atoms = roundtrip(&(vec![2i64, 3, 4], 1i64));
+        assert_eq!(atoms.len(), 5);
+        assert_eq!(atoms[0].get_int(), 3);
+
+        let atoms = roundtrip(&Vec::<i64>::new());
+        assert_eq!(atoms.len(), 0);
+    }
+
+    #[test]
+    fn compound() {
+        let mut tags = BTreeMap::new();
+        tags.insert("a".to_string(), 1);
+        tags.insert("b".to_string(), 2);
+        let m = Message {
+            id: 7,
+            name: "foo".into(),
+            on: false,
+            gain: Some(0.5),
+            shapes: vec![Shape::Point, Shape::Circle(2.0), Shape::Rect { w: 3, h: 4 }],
+            tags,
+            rest: vec![9, 8, 7],
+        };
+        let atoms = roundtrip(&m);
+        //7 foo 0 1 0.5 3 Point Circle 2. Rect 3 4 2 a 1 b 2 9 8 7
+        assert_eq!(atoms.len(), 20);
+        assert_eq!(atoms[6].get_symbol().to_string().unwrap(), "Point");
+
+        let m = Message {
+            gain: None,
+            shapes: Vec::new(),
+            tags: BTreeMap::new(),
+            rest: Vec::new(),
+            ..m
+        };
+        roundtrip(&m);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(from_atoms::<i64>(&[]), Err(SerdeError::Eof)));
+        assert!(matches!(
+            from_atoms::<i64>(&[Atom::from(1.5f64)]),
+            Err(SerdeError::UnexpectedType { .. })
+        ));
+        assert!(matches!(
+            from_atoms::<u8>(&[Atom::from(300 as max_sys::t_atom_long)]),
+            Err(SerdeError::Message(_))
+        ));
+        assert!(matches!(
+            from_atoms::<(i64,)>(&[Atom::from(1 as max_sys::t_atom_long), Atom::from(2.0f64)]),
+            Err(SerdeError::TrailingAtoms(1))
+        ));
+        assert!(matches!(
+            from_atoms::<Shape>(&[Atom::from(1 as max_sys::t_atom_long)]),
+            Err(SerdeError::UnexpectedType { .. })
+        ));
+    }
+}
@@ -0,0 +1,413 @@
+//! Serialize rust values into a flat list of atoms.
+//!
+//! See [`to_atoms`](fn.to_atoms.html) for the encoding.
+
+use super::{Atom, SerdeError};
+use crate::symbol::SymbolRef;
+use serde::ser::{self, Serialize};
+use std::convert::TryFrom;
+
+type Result<T> = std::result::Result<T, SerdeError>;
+
+/// Serialize a value into a flat list of atoms.
+///
+/// # Encoding
+/// * `bool` is an int, `0` or `1`.
+/// * Integers are ints, floats are floats.
+/// * `char`, `str` and `String` are symbols.
+/// * `()`, unit structs and `PhantomData` produce nothing.
+/// * Newtype structs are encoded as their contents.
+/// * Tuples, tuple structs and structs are their fields, in order, without a prefix.
+/// * Sequences, bytes and maps are their length, as an int, followed by their items, maps write
+/// key then value for each entry.
+/// * A sequence, bytes or map in *tail* position omits the length prefix and consumes all the
+/// remaining atoms when deserializing. The top level value is in tail position, and so is the last
+/// field of a tuple, struct or enum variant that is in tail position.
+/// * `Option` is an int, `0` for `None` or `1` for `Some` followed by the value.
+/// * Enum variants are a symbol, the variant name, followed by the variant's contents.
+///
+/// # Examples
+/// ```ignore
+/// #[derive(Serialize)]
+/// struct Note {
+///     pitch: i64,
+///     velocity: f64,
+///     tags: Vec<String>,
+/// }
+///
+/// // [60, 0.5, foo, bar]
+/// let atoms = median::atom::to_atoms(&Note {
+///     pitch: 60,
+///     velocity: 0.5,
+///     tags: vec!["foo".into(), "bar".into()],
+/// })?;
+/// ```
+pub fn to_atoms<T: Serialize + ?Sized>(value: &T) -> Result<Vec<Atom>> {
+    let mut atoms = Vec::new();
+    value.serialize(Serializer {
+        atoms: &mut atoms,
+        tail: true,
+    })?;
+    Ok(atoms)
+}
+
+/// The serializer used by [`to_atoms`](fn.to_atoms.html).
+pub struct Serializer<'a> {
+    atoms: &'a mut Vec<Atom>,
+    tail: bool,
+}
+
+/// The state for serializing compound values.
+pub struct Compound<'a> {
+    atoms: &'a mut Vec<Atom>,
+    tail: bool,
+    //the number of fields left, used to find the tail field
+    remaining: usize,
+    //the index of the length prefix to fill in at the end and the item count
+    prefix: Option<(usize, usize)>,
+}
+
+impl<'a> Serializer<'a> {
+    fn push<A: Into<Atom>>(self, a: A) -> Result<()> {
+        self.atoms.push(a.into());
+        Ok(())
+    }
+
+    fn push_symbol(self, v: &str) -> Result<()> {
+        let s = SymbolRef::try_from(v).map_err(|_| SerdeError::InvalidSymbol)?;
+        self.push(s)
+    }
+
+    fn fields(self, len: usize) -> Compound<'a> {
+        Compound {
+            atoms: self.atoms,
+            tail: self.tail,
+            remaining: len,
+            prefix: None,
+        }
+    }
+
+    fn items(self) -> Compound<'a> {
+        let prefix = if self.tail {
+            None
+        } else {
+            //filled in when the compound ends, so we don't need to know the length up front
+            self.atoms.push(Atom::default());
+            Some((self.atoms.len() - 1, 0))
+        };
+        Compound {
+            atoms: self.atoms,
+            tail: false,
+            remaining: 0,
+            prefix,
+        }
+    }
+}
+
+impl<'a> Compound<'a> {
+    fn field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.remaining = self.remaining.saturating_sub(1);
+        value.serialize(Serializer {
+            atoms: self.atoms,
+            tail: self.tail && self.remaining == 0,
+        })
+    }
+
+    fn item<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        if let Some((_, count)) = self.prefix.as_mut() {
+            *count += 1;
+        }
+        value.serialize(Serializer {
+            atoms: self.atoms,
+            tail: false,
+        })
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Some((index, count)) = self.prefix {
+            let count =
+                max_sys::t_atom_long::try_from(count).map_err(|_| SerdeError::IntegerOverflow)?;
+            self.atoms[index].set_int(count);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.push(v as max_sys::t_atom_long)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        let v = max_sys::t_atom_long::try_from(v).map_err(|_| SerdeError::IntegerOverflow)?;
+        self.push(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        let v = i64::try_from(v).map_err(|_| SerdeError::IntegerOverflow)?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.push(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.push(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        let mut buf = [0; 4];
+        self.push_symbol(v.encode_utf8(&mut buf))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.push_symbol(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        let mut c = self.items();
+        for b in v {
+            c.item(b)?;
+        }
+        c.finish()
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.push(0 as max_sys::t_atom_long)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        self.atoms.push(Atom::from(1 as max_sys::t_atom_long));
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.push_symbol(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let tail = self.tail;
+        let atoms = self.atoms;
+        Serializer { atoms, tail }.push_symbol(variant)?;
+        value.serialize(Serializer { atoms, tail })
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(self.items())
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self.fields(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Ok(self.fields(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        let tail = self.tail;
+        let atoms = self.atoms;
+        Serializer { atoms, tail }.push_symbol(variant)?;
+        Ok(Serializer { atoms, tail }.fields(len))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(self.items())
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self.fields(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        let tail = self.tail;
+        let atoms = self.atoms;
+        Serializer { atoms, tail }.push_symbol(variant)?;
+        Ok(Serializer { atoms, tail }.fields(len))
+    }
+}
+
+impl<'a> ser::SerializeSeq for Compound<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTuple for Compound<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for Compound<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for Compound<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeMap for Compound<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.item(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        //the key already counted the entry
+        value.serialize(Serializer {
+            atoms: self.atoms,
+            tail: false,
+        })
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStruct for Compound<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for Compound<'a> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
@@ -0,0 +1,289 @@
+//! Convert atoms to and from Max message text.
+//!
+//! [`parse`](fn.parse.html) and [`format`](fn.format.html) are pure rust versions of Max's
+//! `atom_setparse` and `atom_gettext`, they don't need Max to be running so they can be used in
+//! tests. The rules:
+//!
+//! * Atoms are separated by whitespace.
+//! * Unquoted tokens that look like integers are ints, ones that look like floating point numbers
+//! are floats, everything else is a symbol.
+//! * Double quotes group text, including whitespace, into a single symbol. Inside quotes `\"` and
+//! `\\` are escapes for `"` and `\`.
+//! * Outside of quotes, a backslash escapes the next character. A token with any quoted or escaped
+//! part is always a symbol, so `"12"` is the symbol `12`.
+//! * Unescaped commas and semicolons are their own symbols, `,` and `;`.
+//!
+//! When formatting, symbols are quoted when they would otherwise parse differently, and finite
+//! floats always include a decimal point, so `format` then `parse` gives back the same atoms.
+//! Infinite and NaN floats are the exception, they are formatted as `inf`, `-inf` and `NaN`,
+//! which parse as symbols.
+
+use super::{Atom, AtomValue};
+use crate::{error::MaxError, symbol::SymbolRef};
+use std::convert::TryFrom;
+
+/// Errors from converting between text and atoms.
+#[derive(Debug)]
+pub enum TextError {
+    /// Max reported an error.
+    Max(MaxError),
+    /// There was no text to parse.
+    Empty,
+    /// A symbol couldn't be created from, or converted to, text.
+    InvalidSymbol,
+}
+
+/// Parse text into atoms.
+pub fn parse(text: &str) -> Result<Vec<Atom>, TextError> {
+    let mut atoms = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        match chars.peek() {
+            None => break,
+            Some(&c) if c == ',' || c == ';' => {
+                chars.next();
+                atoms.push(symbol(c.encode_utf8(&mut [0; 4]))?);
+                continue;
+            }
+            _ => (),
+        }
+        let mut token = String::new();
+        let mut literal = false;
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == ',' || c == ';' {
+                break;
+            }
+            chars.next();
+            match c {
+                '"' => {
+                    literal = true;
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '"' => break,
+                            '\\' => token.extend(chars.next()),
+                            _ => token.push(c),
+                        }
+                    }
+                }
+                '\\' => {
+                    literal = true;
+                    token.extend(chars.next());
+                }
+                _ => token.push(c),
+            }
+        }
+        atoms.push(if literal {
+            symbol(&token)?
+        } else {
+            token_to_atom(&token)?
+        });
+    }
+    Ok(atoms)
+}
+
+/// Parse text into a selector and atoms, like a message box would.
+///
+/// # Remarks
+/// * If the text starts with a number, the selector is `int` or `float` for a single number and
+/// `list` otherwise, and the atoms include the number.
+pub fn parse_message(text: &str) -> Result<(SymbolRef, Vec<Atom>), TextError> {
+    split_selector(parse(text)?)
+}
+
+/// Format atoms as text, separated by spaces.
+pub fn format(atoms: &[Atom]) -> String {
+    let mut text = String::new();
+    for a in atoms {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        match a.get_value() {
+            Some(AtomValue::Int(v)) => text.push_str(&v.to_string()),
+            Some(AtomValue::Float(v)) => text.push_str(&format_float(v)),
+            Some(AtomValue::Symbol(s)) => {
+                //lossy so that formatting for display can't fail
+                let s = s.to_cstring();
+                format_symbol(&mut text, &s.to_string_lossy());
+            }
+            Some(AtomValue::Object(_)) => text.push_str("<object>"),
+            None => (),
+        }
+    }
+    text
+}
+
+/// Format a selector and atoms as text, the reverse of [`parse_message`](fn.parse_message.html).
+pub fn format_message(selector: &SymbolRef, atoms: &[Atom]) -> String {
+    if implicit_selector(selector, atoms) {
+        format(atoms)
+    } else {
+        let mut all = vec![Atom::from(selector)];
+        all.extend(atoms.iter().map(|a| Atom { value: a.value }));
+        format(&all)
+    }
+}
+
+pub(crate) fn split_selector(mut atoms: Vec<Atom>) -> Result<(SymbolRef, Vec<Atom>), TextError> {
+    let name = match atoms.first().and_then(|a| a.get_value()) {
+        None => return Err(TextError::Empty),
+        Some(AtomValue::Symbol(s)) => {
+            atoms.remove(0);
+            return Ok((s, atoms));
+        }
+        Some(AtomValue::Int(_)) if atoms.len() == 1 => crate::sym!("int"),
+        Some(AtomValue::Float(_)) if atoms.len() == 1 => crate::sym!("float"),
+        Some(_) => crate::sym!("list"),
+    };
+    Ok((name, atoms))
+}
+
+//is the selector implied by the atoms, so it can be left out of the text
+pub(crate) fn implicit_selector(selector: &SymbolRef, atoms: &[Atom]) -> bool {
+    match atoms.first().and_then(|a| a.get_value()) {
+        Some(AtomValue::Int(_)) | Some(AtomValue::Float(_)) => {
+            *selector == crate::sym!("list")
+                || (atoms.len() == 1
+                    && (*selector == crate::sym!("int") || *selector == crate::sym!("float")))
+        }
+        _ => false,
+    }
+}
+
+fn symbol(s: &str) -> Result<Atom, TextError> {
+    SymbolRef::try_from(s)
+        .map(Atom::from)
+        .map_err(|_| TextError::InvalidSymbol)
+}
+
+fn looks_numeric(token: &str) -> bool {
+    token.chars().any(|c| c.is_ascii_digit())
+        && token
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E')
+}
+
+fn token_to_atom(token: &str) -> Result<Atom, TextError> {
+    if looks_numeric(token) {
+        if let Ok(v) = token.parse::<max_sys::t_atom_long>() {
+            return Ok(Atom::from(v));
+        }
+        if let Ok(v) = token.parse::<f64>() {
+            return Ok(Atom::from(v));
+        }
+    }
+    symbol(token)
+}
+
+fn format_float(v: f64) -> String {
+    let mut s = v.to_string();
+    if v.is_finite() && !s.contains('.') {
+        s.push('.');
+    }
+    s
+}
+
+fn format_symbol(text: &mut String, s: &str) {
+    let quote = s.is_empty()
+        || looks_numeric(s)
+        || s.chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\\' || c == ',' || c == ';');
+    if quote {
+        text.push('"');
+        for c in s.chars() {
+            if c == '"' || c == '\\' {
+                text.push('\\');
+            }
+            text.push(c);
+        }
+        text.push('"');
+    } else {
+        text.push_str(s);
+    }
+}
+
+impl std::fmt::Display for TextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Max(e) => write!(f, "max error {:?}", e),
+            Self::Empty => write!(f, "empty message"),
+            Self::InvalidSymbol => write!(f, "invalid symbol"),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atom::AtomType;
+
+    fn types(atoms: &[Atom]) -> Vec<Option<AtomType>> {
+        atoms.iter().map(|a| a.get_type()).collect()
+    }
+
+    #[test]
+    fn parse_tokens() {
+        let atoms = parse("set 1 2.5 \"foo bar\" -3 1. baz\\ qux \"12\"").unwrap();
+        assert_eq!(
+            types(&atoms),
+            vec![
+                Some(AtomType::Symbol),
+                Some(AtomType::Int),
+                Some(AtomType::Float),
+                Some(AtomType::Symbol),
+                Some(AtomType::Int),
+                Some(AtomType::Float),
+                Some(AtomType::Symbol),
+                Some(AtomType::Symbol),
+            ]
+        );
+        assert_eq!(atoms[3].get_symbol().to_string().unwrap(), "foo bar");
+        assert_eq!(atoms[4].get_int(), -3);
+        assert_eq!(atoms[6].get_symbol().to_string().unwrap(), "baz qux");
+        assert_eq!(atoms[7].get_symbol().to_string().unwrap(), "12");
+
+        let atoms = parse("a, b;c").unwrap();
+        assert_eq!(atoms.len(), 5);
+        assert_eq!(atoms[1].get_symbol().to_string().unwrap(), ",");
+        assert_eq!(atoms[3].get_symbol().to_string().unwrap(), ";");
+
+        assert!(parse("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn messages() {
+        let (sel, atoms) = parse_message("set 1 2.5").unwrap();
+        assert_eq!(sel.to_string().unwrap(), "set");
+        assert_eq!(atoms.len(), 2);
+        assert_eq!(format_message(&sel, &atoms), "set 1 2.5");
+
+        let (sel, atoms) = parse_message("1 2").unwrap();
+        assert_eq!(sel.to_string().unwrap(), "list");
+        assert_eq!(atoms.len(), 2);
+        assert_eq!(format_message(&sel, &atoms), "1 2");
+
+        let (sel, _) = parse_message("2.").unwrap();
+        assert_eq!(sel.to_string().unwrap(), "float");
+
+        assert!(matches!(parse_message(""), Err(TextError::Empty)));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let text = "foo \"bar baz\" \"\" \"a\\\"b\" \"1\" \",\" 3 -0.25 100.";
+        assert_eq!(format(&parse(text).unwrap()), text);
+    }
+
+    #[test]
+    fn non_finite() {
+        let text = format(&[Atom::from(f64::INFINITY), Atom::from(f64::NAN)]);
+        assert_eq!(text, "inf NaN");
+        let atoms = parse(&text).unwrap();
+        assert_eq!(atoms[0].get_symbol().to_string().unwrap(), "inf");
+        assert_eq!(atoms[1].get_symbol().to_string().unwrap(), "NaN");
+    }
+}
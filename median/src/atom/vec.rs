@@ -0,0 +1,242 @@
+//! Owned lists of atoms.
+
+use super::{Atom, AtomValue};
+use crate::slice::Slice;
+use smallvec::SmallVec;
+use std::{
+    iter::FromIterator,
+    ops::{Deref, DerefMut},
+};
+
+/// The number of atoms an `AtomVec` can hold before it allocates.
+pub const ATOM_VEC_INLINE_LEN: usize = 8;
+
+/// An owned, growable list of atoms.
+///
+/// # Remarks
+/// * Short lists are stored inline, without allocating, so building one for each outgoing message
+/// is cheap.
+/// * Derefs to `[Atom]` so it can be passed directly to outlets, `defer` and anything else that
+/// takes `&[Atom]`.
+/// * Use the [`atoms!`](../../macro.atoms.html) macro to build one from a list of values.
+#[derive(Clone, Default)]
+pub struct AtomVec {
+    inner: SmallVec<[Atom; ATOM_VEC_INLINE_LEN]>,
+}
+
+/// An iterator over the values in a list of atoms.
+///
+/// # Remarks
+/// * Atoms without a value type, like Max's comma and semicolon atoms, are skipped.
+pub struct Values<'a> {
+    inner: std::slice::Iter<'a, Atom>,
+}
+
+impl AtomVec {
+    /// Create an empty list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty list with space for at least `capacity` atoms.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: SmallVec::with_capacity(capacity),
+        }
+    }
+
+    /// Append a value to the end of the list.
+    pub fn push<A: Into<Atom>>(&mut self, value: A) {
+        self.inner.push(value.into());
+    }
+
+    /// Remove the last atom from the list and return it.
+    pub fn pop(&mut self) -> Option<Atom> {
+        self.inner.pop()
+    }
+
+    /// Insert a value at `index`, shifting later atoms to the right.
+    ///
+    /// # Panics
+    /// Will panic if `index > len`.
+    pub fn insert<A: Into<Atom>>(&mut self, index: usize, value: A) {
+        self.inner.insert(index, value.into());
+    }
+
+    /// Remove and return the atom at `index`, shifting later atoms to the left.
+    ///
+    /// # Panics
+    /// Will panic if `index >= len`.
+    pub fn remove(&mut self, index: usize) -> Atom {
+        self.inner.remove(index)
+    }
+
+    /// Shorten the list to `len` atoms.
+    pub fn truncate(&mut self, len: usize) {
+        self.inner.truncate(len);
+    }
+
+    /// Remove all the atoms.
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Get an iterator over the values of the atoms.
+    pub fn values(&self) -> Values<'_> {
+        Values::new(self)
+    }
+
+    /// Copy into a list allocated by Max, for handing ownership to Max.
+    pub fn into_slice(self) -> Slice<Atom> {
+        Slice::from(self.inner.into_iter())
+    }
+
+    /// Get the count and pointer pair that Max functions take.
+    ///
+    /// # Remarks
+    /// * The pointer is only valid as long as this list is alive and unchanged.
+    /// * Max takes `*mut` but won't mutate a list passed as arguments.
+    pub fn as_raw(&self) -> (std::os::raw::c_long, *mut max_sys::t_atom) {
+        //Atom is transparent, so it can be cast to t_atom
+        (
+            self.inner.len() as _,
+            self.inner.as_ptr() as *mut max_sys::t_atom,
+        )
+    }
+}
+
+impl<'a> Values<'a> {
+    /// Get an iterator over the values of a list of atoms.
+    pub fn new(atoms: &'a [Atom]) -> Self {
+        Self {
+            inner: atoms.iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for Values<'a> {
+    type Item = AtomValue;
+
+    fn next(&mut self) -> Option<AtomValue> {
+        self.inner.by_ref().find_map(|a| a.get_value())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl Deref for AtomVec {
+    type Target = [Atom];
+    fn deref(&self) -> &[Atom] {
+        &self.inner
+    }
+}
+
+impl DerefMut for AtomVec {
+    fn deref_mut(&mut self) -> &mut [Atom] {
+        &mut self.inner
+    }
+}
+
+impl AsRef<[Atom]> for AtomVec {
+    fn as_ref(&self) -> &[Atom] {
+        &self.inner
+    }
+}
+
+impl<A: Into<Atom>> FromIterator<A> for AtomVec {
+    fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> Self {
+        Self {
+            inner: iter.into_iter().map(|a| a.into()).collect(),
+        }
+    }
+}
+
+impl<A: Into<Atom>> Extend<A> for AtomVec {
+    fn extend<I: IntoIterator<Item = A>>(&mut self, iter: I) {
+        self.inner.extend(iter.into_iter().map(|a| a.into()));
+    }
+}
+
+impl IntoIterator for AtomVec {
+    type Item = Atom;
+    type IntoIter = smallvec::IntoIter<[Atom; ATOM_VEC_INLINE_LEN]>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a AtomVec {
+    type Item = &'a Atom;
+    type IntoIter = std::slice::Iter<'a, Atom>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter()
+    }
+}
+
+impl From<&[Atom]> for AtomVec {
+    fn from(atoms: &[Atom]) -> Self {
+        Self {
+            inner: SmallVec::from(atoms),
+        }
+    }
+}
+
+impl From<Vec<Atom>> for AtomVec {
+    fn from(atoms: Vec<Atom>) -> Self {
+        Self {
+            inner: SmallVec::from_vec(atoms),
+        }
+    }
+}
+
+impl From<Slice<Atom>> for AtomVec {
+    fn from(atoms: Slice<Atom>) -> Self {
+        Self::from(atoms.as_ref())
+    }
+}
+
+impl From<AtomVec> for Vec<Atom> {
+    fn from(atoms: AtomVec) -> Self {
+        atoms.inner.into_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let v = crate::atoms![1, 2.5, "foo", -3i64];
+        assert_eq!(v.len(), 4);
+        let values: Vec<AtomValue> = v.values().collect();
+        assert!(matches!(values[0], AtomValue::Int(1)));
+        assert!(matches!(values[1], AtomValue::Float(f) if f == 2.5));
+        assert!(matches!(&values[2], AtomValue::Symbol(s) if s.to_string().unwrap() == "foo"));
+        assert!(matches!(values[3], AtomValue::Int(-3)));
+
+        let v2: AtomVec = v.iter().collect();
+        assert_eq!(v2.len(), 4);
+        assert_eq!(v2[0].get_int(), 1);
+
+        assert!(crate::atoms![].is_empty());
+    }
+
+    #[test]
+    fn spill() {
+        let mut v: AtomVec = (0..(ATOM_VEC_INLINE_LEN as i64 * 2)).collect();
+        assert_eq!(v.len(), ATOM_VEC_INLINE_LEN * 2);
+        v.insert(0, 2.0);
+        assert_eq!(v.remove(0).get_float(), 2.0);
+        let (ac, av) = v.as_raw();
+        assert_eq!(ac as usize, v.len());
+        assert_eq!(av as *const Atom, v.as_ptr());
+        let back: Vec<Atom> = v.into();
+        assert_eq!(
+            back[ATOM_VEC_INLINE_LEN].get_int() as usize,
+            ATOM_VEC_INLINE_LEN
+        );
+    }
+}
@@ -1,9 +1,9 @@
 //! Attributes.
 
-use crate::atom::Atom;
+use crate::atom::{Atom, AtomVec};
 use crate::error::{MaxError, MaxResult};
 use crate::max::common_symbols;
-use crate::method::MaxMethod;
+use crate::method::{HandlerResult, MaxMethod};
 use crate::symbol::SymbolRef;
 
 use std::ffi::c_void;
@@ -13,7 +13,7 @@ use std::os::raw::c_long;
 pub type AttrTrampGetMethod<T> =
     extern "C" fn(s: &T, attr: c_void, ac: *mut c_long, av: *mut *mut max_sys::t_atom);
 pub type AttrTrampSetMethod<T> =
-    extern "C" fn(s: &T, attr: c_void, ac: c_long, av: *mut max_sys::t_atom);
+    extern "C" fn(s: &T, attr: c_void, ac: c_long, av: *mut max_sys::t_atom) -> max_sys::t_max_err;
 
 /// A wrapper for a max attribute. `T` refers to the object that the attribute is attributed to.
 pub struct Attr<T> {
@@ -50,7 +50,14 @@ pub extern "C" fn get_nop<T>(
 ) {
 }
 /// No-op set method for attributes.
-pub extern "C" fn set_nop<T>(_s: &T, _attr: c_void, _ac: c_long, _av: *mut max_sys::t_atom) {}
+pub extern "C" fn set_nop<T>(
+    _s: &T,
+    _attr: c_void,
+    _ac: c_long,
+    _av: *mut max_sys::t_atom,
+) -> max_sys::t_max_err {
+    max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+}
 
 impl<T> AttrBuilder<T> {
     // helper method used in public impls
@@ -436,18 +443,99 @@ where
     max_sys::e_max_errorcodes::MAX_ERR_NONE as _
 }
 
+/// handle the boiler plate of dealing with attribute atoms, for attributes with more than one
+/// atom
+///
+/// # Remarks
+/// * Unsafe because `ac` and `av` must be the pointers that Max gives an attribute getter.
+pub unsafe fn get_atoms<F>(
+    ac: *mut c_long,
+    av: *mut *mut max_sys::t_atom,
+    getter: F,
+) -> max_sys::t_max_err
+where
+    F: Fn() -> AtomVec,
+{
+    let atoms = getter();
+    //always ask for at least one atom, max may not allocate any for zero
+    let mut alloc: std::os::raw::c_char = 0;
+    let err = max_sys::atom_alloc_array(atoms.len().max(1) as _, ac, av, &mut alloc);
+    if err != max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_max_err {
+        return err;
+    }
+    if atoms.is_empty() || (*av).is_null() {
+        *ac = 0;
+        return max_sys::e_max_errorcodes::MAX_ERR_NONE as _;
+    }
+    let dst: &mut [Atom] = std::slice::from_raw_parts_mut(*av as *mut Atom, atoms.len());
+    dst.clone_from_slice(&atoms);
+    *ac = atoms.len() as _;
+    max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+}
+
+/// handle the boiler plate of dealing with attribute atoms, for attributes with more than one
+/// atom
+///
+/// # Remarks
+/// * Unsafe because `ac` and `av` must be the pointers that Max gives an attribute setter.
+/// * An `Err` from `setter` is reported without an object and rejects the value, return an
+/// already reported [`MaxErr`](../method/struct.MaxErr.html) to report against your object.
+pub unsafe fn set_atoms<F, R>(ac: c_long, av: *mut max_sys::t_atom, setter: F) -> max_sys::t_max_err
+where
+    F: Fn(&[Atom]) -> R,
+    R: HandlerResult,
+{
+    if ac > 0 && !av.is_null() {
+        //transparent so this is okay
+        setter(std::slice::from_raw_parts(av as *const Atom, ac as _))
+    } else {
+        setter(&[])
+    }
+    .report(std::ptr::null_mut())
+}
+
 /// handle the boiler plate of dealing with attribute atoms
-pub fn set<'a, T, F>(ac: c_long, av: *mut max_sys::t_atom, setter: F) -> max_sys::t_max_err
+///
+/// # Remarks
+/// * An `Err` from `setter` is reported without an object and rejects the value, return an
+/// already reported [`MaxErr`](../method/struct.MaxErr.html) to report against your object.
+pub fn set<'a, T, F, R>(ac: c_long, av: *mut max_sys::t_atom, setter: F) -> max_sys::t_max_err
 where
-    F: Fn(T),
+    F: Fn(T) -> R,
     T: From<&'a Atom>,
+    R: HandlerResult,
 {
     unsafe {
         if ac > 0 && !av.is_null() {
             //transparent so this is okay
             let a: &Atom = std::mem::transmute::<_, _>(&*av);
-            setter(a.into());
+            return setter(a.into()).report(std::ptr::null_mut());
         }
     }
     max_sys::e_max_errorcodes::MAX_ERR_NONE as _
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atoms() {
+        let mut ac: c_long = 0;
+        let mut av: *mut max_sys::t_atom = std::ptr::null_mut();
+        unsafe {
+            assert_eq!(get_atoms(&mut ac, &mut av, AtomVec::new), 0);
+            assert_eq!(ac, 0);
+            max_sys::sysmem_freeptr(av as _);
+
+            ac = 0;
+            av = std::ptr::null_mut();
+            assert_eq!(get_atoms(&mut ac, &mut av, || crate::atoms![1, 2.5]), 0);
+            assert_eq!(ac, 2);
+            let atoms = std::slice::from_raw_parts(av as *const Atom, 2);
+            assert_eq!(atoms[0].get_int(), 1);
+            assert_eq!(atoms[1].get_float(), 2.5);
+            max_sys::sysmem_freeptr(av as _);
+        }
+    }
+}
@@ -1,16 +1,9 @@
 //! Data access to MSP buffer~ object data.
 use crate::{notify::Notification, symbol::SymbolRef};
 use core::ffi::c_void;
-use std::convert::TryFrom;
 use std::marker::PhantomData;
 use std::ops::{DerefMut, Index, IndexMut};
 
-lazy_static::lazy_static! {
-    static ref GLOBAL_SYMBOL_BINDING: SymbolRef = SymbolRef::try_from("globalsymbol_binding").unwrap();
-    static ref GLOBAL_SYMBOL_UNBINDING: SymbolRef = SymbolRef::try_from("globalsymbol_unbinding").unwrap();
-    static ref GET_NAME: SymbolRef = SymbolRef::try_from("getname").unwrap();
-}
-
 #[derive(Debug, Copy, Clone)]
 pub enum TryLockError {
     BufferDoesNotExist,
@@ -90,6 +83,7 @@ impl BufferRef {
     }
 
     fn with_lock<F: Fn(&mut BufInner) -> R, R>(&self, func: F) -> R {
+        crate::rt::check::note("lock");
         let mut g = self.inner.lock();
         func(g.deref_mut())
     }
@@ -109,7 +103,8 @@ impl BufferRef {
         } else {
             let message = notification.message();
             //see if it is a binding or unbinding message
-            *message == *GLOBAL_SYMBOL_BINDING || *message == *GLOBAL_SYMBOL_UNBINDING
+            *message == crate::sym!("globalsymbol_binding")
+                || *message == crate::sym!("globalsymbol_unbinding")
         }
     }
 
@@ -124,7 +119,7 @@ impl BufferRef {
         let name: *mut max_sys::t_symbol = std::ptr::null_mut();
         max_sys::object_method(
             notification.data(),
-            GET_NAME.inner(),
+            crate::sym!("getname").inner(),
             std::mem::transmute::<_, *mut c_void>(&name),
         );
         self.with_lock(|inner| {
@@ -1,15 +1,22 @@
 //! Utilities for building objects.
 use crate::{
+    args::{attr_args_offset, ArgsError, CreationArgs},
     atom::Atom,
     buffer::{BufferRef, BufferReference},
     clock::ClockHandle,
-    inlet::{MSPInlet, MaxInlet, Proxy},
+    inlet::{
+        ColdInlet, ColdValue, ColdValues, DefaultInlet, InletHandlers, MSPInlet, MaxInlet, Proxy,
+    },
     notify::{Attachment, AttachmentError, Registration, RegistrationError, Subscription},
-    outlet::{OutAnything, OutBang, OutFloat, OutInt, OutList, Outlet},
+    outlet::{
+        MessageCount, OutAnything, OutBang, OutDictionary, OutFloat, OutInt, OutJitMatrix, OutList,
+        OutMessage, OutSymbol, Outlet, OutletConfig, OverflowPolicy, ThreadPolicy,
+    },
+    pattr::{Modified, Notifier},
     symbol::SymbolRef,
     wrapper::{
-        FloatCBHash, IntCBHash, MSPObjWrapped, MSPObjWrapper, MaxObjWrapped, MaxObjWrapper,
-        ObjWrapped, WrapperWrapped,
+        FloatCBHash, InletHandlersHash, IntCBHash, MSPObjWrapped, MSPObjWrapper, MaxObjWrapped,
+        MaxObjWrapper, ObjWrapped, WrapperWrapped,
     },
 };
 use std::{collections::HashMap, ffi::CString, marker::PhantomData, sync::Arc};
@@ -20,10 +27,14 @@ pub struct WrappedBuilder<'a, T, W> {
     sym: SymbolRef,
     args: &'a [Atom],
     inlets: Vec<MSPInlet<T>>, //just use MSP since it contains all of Max
+    default_inlet: DefaultInlet,
     buffer_refs: Vec<ManagedBufferRefInternal>,
     assist_ins: HashMap<usize, CString>,
     assist_outs: HashMap<usize, CString>,
     outlet_count: usize,
+    outlet_config: OutletConfig,
+    cold_values: ColdValues,
+    pattr: Option<Modified>,
     signal_outlets: usize,
     _phantom: PhantomData<(T, W)>,
 }
@@ -39,8 +50,22 @@ pub trait ObjBuilder<T> {
     /// Add assistance for the default inlet
     /// # Panics
     /// * Will panic if `assist` cannot be converted into a CString
+    /// * Will panic if the default inlet has been removed with
+    /// [`without_default_inlet`](#tymethod.without_default_inlet).
     fn with_default_inlet_assist(&mut self, assist: &str);
 
+    /// Don't create the default, leftmost, inlet.
+    ///
+    /// # Remarks
+    /// * The first inlet you add becomes the leftmost, with index 0, an object that doesn't add
+    /// any has no inlets at all.
+    ///
+    /// # Panics
+    /// * Will panic if inlets have already been added.
+    /// * Will panic if assistance has already been added for the default inlet.
+    /// * MSP objects will panic in finalize if they also have signal inlets.
+    fn without_default_inlet(&mut self);
+
     /// Get a clock object that executes `func` when triggered.
     fn with_clockfn(&mut self, func: fn(&T)) -> ClockHandle;
     /// Get a clock object that executes `func` when triggered.
@@ -48,6 +73,29 @@ pub trait ObjBuilder<T> {
     /// Get a managed buffer reference.
     fn with_buffer(&mut self, name: Option<SymbolRef>) -> ManagedBufferRef;
 
+    /// Make the outlets added after this [`ThreadSafeOutlet`](../outlet/struct.ThreadSafeOutlet.html)s,
+    /// that handle values sent from other threads according to `policy`.
+    fn with_thread_safe_outlets(&mut self, policy: ThreadPolicy);
+
+    /// Set what the outlets added after this do when Max reports a stack overflow, the default is
+    /// `OverflowPolicy::PostOnce`.
+    ///
+    /// # Remarks
+    /// * `OverflowPolicy::Defer` makes the outlets [`ThreadSafeOutlet`](../outlet/struct.ThreadSafeOutlet.html)s,
+    /// with `ThreadPolicy::Queue` unless `with_thread_safe_outlets` set another policy.
+    fn with_stack_overflow_policy(&mut self, policy: OverflowPolicy);
+
+    /// Count what happens to the values given to the outlets added after this, see
+    /// [`SendStats`](../outlet/trait.SendStats.html).
+    fn with_outlet_counters(&mut self);
+
+    /// Get the lock shared by the object's cold inlets.
+    fn cold_values(&self) -> ColdValues;
+
+    /// Get a [`Notifier`](../pattr/struct.Notifier.html) to tell `pattr` that the value from
+    /// [`getvalueof`](../wrapper/trait.ObjWrapped.html#method.getvalueof) has changed.
+    fn pattr_notifier(&mut self) -> Notifier;
+
     /// Add an outlet that outputs bangs.
     fn add_bang_outlet(&mut self) -> OutBang;
 
@@ -88,9 +136,49 @@ pub trait ObjBuilder<T> {
     /// * Will panic if `assist` cannot be converted into a CString
     fn add_anything_outlet_with_assist(&mut self, assist: &str) -> OutAnything;
 
+    /// Add an outlet that outputs symbols.
+    fn add_symbol_outlet(&mut self) -> OutSymbol;
+
+    /// Add an outlet that outputs symbols, provide assist.
+    /// # Panics
+    /// * Will panic if `assist` cannot be converted into a CString
+    fn add_symbol_outlet_with_assist(&mut self, assist: &str) -> OutSymbol;
+
+    /// Add an outlet that outputs `dictionary <name>`.
+    fn add_dictionary_outlet(&mut self) -> OutDictionary;
+
+    /// Add an outlet that outputs `dictionary <name>`, provide assist.
+    /// # Panics
+    /// * Will panic if `assist` cannot be converted into a CString
+    fn add_dictionary_outlet_with_assist(&mut self, assist: &str) -> OutDictionary;
+
+    /// Add an outlet that outputs `jit_matrix <name>`.
+    fn add_jit_matrix_outlet(&mut self) -> OutJitMatrix;
+
+    /// Add an outlet that outputs `jit_matrix <name>`, provide assist.
+    /// # Panics
+    /// * Will panic if `assist` cannot be converted into a CString
+    fn add_jit_matrix_outlet_with_assist(&mut self, assist: &str) -> OutJitMatrix;
+
+    /// Add the dumpout outlet, replies to attribute queries like `getfoo` are sent through it.
+    ///
+    /// # Remarks
+    /// * By convention this is the rightmost outlet, so add it last.
+    /// * Send your own replies, for instance to `dump`, with
+    /// [`MaxObj::dumpout`](../object/trait.MaxObj.html#method.dumpout).
+    fn add_dumpout_outlet(&mut self) -> OutAnything;
+
+    /// Add the dumpout outlet, provide assist.
+    /// # Panics
+    /// * Will panic if `assist` cannot be converted into a CString
+    fn add_dumpout_outlet_with_assist(&mut self, assist: &str) -> OutAnything;
+
     /// Get the arguments that were passed to this object on creation.
     fn creation_args(&self) -> &[Atom];
 
+    /// Get the creation arguments that come before any `@attr value` pairs.
+    fn positional_args(&self) -> &[Atom];
+
     /// Get the symbol that were passed used when creating this object.
     fn creation_symbol(&self) -> SymbolRef;
 
@@ -122,6 +210,12 @@ pub trait ObjBuilder<T> {
 
 pub trait MaxWrappedBuilder<T>: ObjBuilder<T> {
     /// Add an inlet, left to right, returns index.
+    ///
+    /// # Remarks
+    /// * There is no limit on the number of inlets, `Handlers` inlets get the messages sent to
+    /// them without your methods having to check the inlet number.
+    /// * `Handlers` inlets, and `Float` and `Int` inlets past the ninth, need the class to return
+    /// `true` from [`ObjWrapped::inlet_dispatch`](../wrapper/trait.ObjWrapped.html#method.inlet_dispatch).
     fn add_inlet(&mut self, inlet_type: MaxInlet<T>) -> usize;
 
     /// Add an inlet with assist, left to right, returns index.
@@ -139,9 +233,10 @@ pub trait MSPWrappedBuilder<T>: ObjBuilder<T> {
     /// * Will panic if `assist` cannot be converted into a CString
     fn add_signal_outlets_with_assist(&mut self, assist: &[&str]);
 
-    /// Add signal inlets.
+    /// Add signal inlets, the default inlet is the first of them.
     /// # Panics
     /// * Will panic if called more than once.
+    /// * Will panic if called after other inlets have been added.
     fn add_signal_inlets(&mut self, count: usize);
 
     /// Add signal inlets with assistance.
@@ -150,7 +245,19 @@ pub trait MSPWrappedBuilder<T>: ObjBuilder<T> {
     /// * Will panic if `assist` cannot be converted into a CString
     fn add_signal_inlets_with_assist(&mut self, assist: &[&str]);
 
+    /// Make the default, leftmost, inlet a signal only inlet, messages sent to it aren't handled.
+    ///
+    /// # Panics
+    /// * Will panic in finalize if there are no signal inlets.
+    fn with_signal_only_default_inlet(&mut self);
+
     /// Add an inlet, left to right, returns index.
+    ///
+    /// # Remarks
+    /// * There is no limit on the number of inlets, `Handlers` inlets get the messages sent to
+    /// them without your methods having to check the inlet number.
+    /// * `Handlers` inlets, and `Float` and `Int` inlets past the ninth, need the class to return
+    /// `true` from [`ObjWrapped::inlet_dispatch`](../wrapper/trait.ObjWrapped.html#method.inlet_dispatch).
     fn add_inlet(&mut self, inlet_type: MSPInlet<T>) -> usize;
 
     /// Add an inlet with assist, left to right, returns index.
@@ -162,6 +269,106 @@ pub trait MSPWrappedBuilder<T>: ObjBuilder<T> {
     unsafe fn msp_obj(&mut self) -> *mut max_sys::t_pxobject;
 }
 
+//generic methods would stop the builder traits being used as trait objects, so these are
+//implemented on the trait objects themselves
+macro_rules! impl_message_outlets {
+    ($b:ident) => {
+        impl<T> dyn $b<T> + '_ {
+            /// Add an outlet that outputs `selector` followed by a value converted to atoms.
+            ///
+            /// # Examples
+            /// ```ignore
+            /// let pos: OutMessage<(f64, f64)> = builder.add_message_outlet(median::sym!("pos"));
+            /// pos.send(&(0.5, 1.0))?; // pos 0.5 1.
+            /// ```
+            pub fn add_message_outlet<M: serde::Serialize>(
+                &mut self,
+                selector: SymbolRef,
+            ) -> OutMessage<M> {
+                OutMessage::new(selector, self.add_anything_outlet())
+            }
+
+            /// Add an outlet that outputs `selector` followed by a value converted to atoms,
+            /// provide assist.
+            /// # Panics
+            /// * Will panic if `assist` cannot be converted into a CString
+            pub fn add_message_outlet_with_assist<M: serde::Serialize>(
+                &mut self,
+                selector: SymbolRef,
+                assist: &str,
+            ) -> OutMessage<M> {
+                OutMessage::new(selector, self.add_anything_outlet_with_assist(assist))
+            }
+        }
+    };
+}
+
+impl_message_outlets!(MaxWrappedBuilder);
+impl_message_outlets!(MSPWrappedBuilder);
+
+macro_rules! impl_cold_inlets {
+    ($b:ident, $inlet:ident) => {
+        impl<T: 'static> dyn $b<T> + '_ {
+            /// Add a cold inlet that stores the values sent to it, starting with `default`.
+            ///
+            /// # Remarks
+            /// * The class must return `true` from
+            /// [`ObjWrapped::inlet_dispatch`](../wrapper/trait.ObjWrapped.html#method.inlet_dispatch).
+            ///
+            /// # Examples
+            /// ```ignore
+            /// let right = builder.add_cold_inlet::<f64>(0.);
+            /// ```
+            pub fn add_cold_inlet<V: ColdValue>(&mut self, default: V) -> ColdInlet<V> {
+                let cell = ColdInlet::new(default, self.cold_values());
+                self.add_inlet($inlet::Handlers(cell.handlers()));
+                cell
+            }
+
+            /// Add a cold inlet that stores the values sent to it, starting with `default`,
+            /// provide assist.
+            /// # Panics
+            /// * Will panic if `assist` cannot be converted into a CString
+            pub fn add_cold_inlet_with_assist<V: ColdValue>(
+                &mut self,
+                default: V,
+                assist: &str,
+            ) -> ColdInlet<V> {
+                let cell = ColdInlet::new(default, self.cold_values());
+                self.add_inlet_with_assist($inlet::Handlers(cell.handlers()), assist);
+                cell
+            }
+        }
+    };
+}
+
+impl_cold_inlets!(MaxWrappedBuilder, MaxInlet);
+impl_cold_inlets!(MSPWrappedBuilder, MSPInlet);
+
+macro_rules! impl_creation_args {
+    ($b:ident) => {
+        impl<T> dyn $b<T> + '_ {
+            /// Parse the positional creation arguments into `A`, errors are also posted to the
+            /// console for the object.
+            ///
+            /// # Examples
+            /// ```ignore
+            /// let args: Args = builder.parse_args().unwrap_or_default();
+            /// ```
+            pub fn parse_args<A: CreationArgs>(&mut self) -> Result<A, ArgsError> {
+                let res = A::parse(self.positional_args());
+                if let Err(e) = &res {
+                    crate::object::error(unsafe { self.max_obj() }, e.to_string());
+                }
+                res
+            }
+        }
+    };
+}
+
+impl_creation_args!(MaxWrappedBuilder);
+impl_creation_args!(MSPWrappedBuilder);
+
 impl<'a, T, W> WrappedBuilder<'a, T, W> {
     pub fn new_max(owner: *mut max_sys::t_object, sym: SymbolRef, args: &'a [Atom]) -> Self {
         Self {
@@ -170,9 +377,13 @@ impl<'a, T, W> WrappedBuilder<'a, T, W> {
             sym,
             args,
             inlets: Vec::new(),
+            default_inlet: DefaultInlet::Message,
             buffer_refs: Vec::new(),
             signal_outlets: 0,
             outlet_count: 0,
+            outlet_config: OutletConfig::default(),
+            cold_values: ColdValues::default(),
+            pattr: None,
             _phantom: PhantomData,
             assist_ins: HashMap::new(),
             assist_outs: HashMap::new(),
@@ -186,9 +397,13 @@ impl<'a, T, W> WrappedBuilder<'a, T, W> {
             sym,
             args,
             inlets: Vec::new(),
+            default_inlet: DefaultInlet::Message,
             buffer_refs: Vec::new(),
             signal_outlets: 0,
             outlet_count: 0,
+            outlet_config: OutletConfig::default(),
+            cold_values: ColdValues::default(),
+            pattr: None,
             _phantom: PhantomData,
             assist_ins: HashMap::new(),
             assist_outs: HashMap::new(),
@@ -209,58 +424,110 @@ impl<'a, T, W> WrappedBuilder<'a, T, W> {
         )
     }
 
+    //the index of the first inlet after the signal inlets, inlets are numbered by position
+    fn first_inlet_index(&self) -> usize {
+        let (signals, _) = self.signal_iolets();
+        if signals > 0 || self.default_inlet != DefaultInlet::Omitted {
+            signals.max(1)
+        } else {
+            0
+        }
+    }
+
+    fn next_inlet_index(&self) -> usize {
+        self.first_inlet_index() + self.inlets.len() - self.signal_iolets().0
+    }
+
     // pass None for Max objects
     fn finalize_inlets(
         &mut self,
         signal_inlets: Option<usize>,
-    ) -> (FloatCBHash<T>, IntCBHash<T>, Vec<Proxy>) {
+    ) -> (
+        FloatCBHash<T>,
+        IntCBHash<T>,
+        InletHandlersHash<T>,
+        Vec<Proxy>,
+    ) {
         let mut callbacks_float = HashMap::new();
         let mut callbacks_int = HashMap::new();
+        let mut handlers = HashMap::new();
         let mut proxies = Vec::new();
 
-        //handle an MSP object with 0 signal inlets
-        let mut called_dsp_setup = match signal_inlets {
-            Some(0) => unsafe {
-                max_sys::z_dsp_setup(self.msp_obj.expect("need to have msp object for dsp"), 0);
-                true
-            },
-            _ => false,
-        };
-
+        match (self.default_inlet, signal_inlets) {
+            (DefaultInlet::Omitted, Some(n)) => {
+                assert!(
+                    n == 0,
+                    "an object without a default inlet can't have signal inlets"
+                )
+            }
+            (DefaultInlet::SignalOnly, n) => assert!(
+                n.unwrap_or(0) > 0,
+                "a signal only default inlet needs signal inlets"
+            ),
+            _ => (),
+        }
+        let first = self.first_inlet_index();
+        let (signals, _) = self.signal_iolets();
         let inlets = std::mem::take(&mut self.inlets);
 
         //reverse because max allocs inlets right to left
-        for (mut index, inlet) in inlets.into_iter().enumerate().rev() {
-            index = index + 1; //TODO allow for no default inlet
+        for (i, inlet) in inlets
+            .into_iter()
+            //signal inlets are always first, z_dsp_setup creates them
+            .skip(signals)
+            .enumerate()
+            .rev()
+        {
+            let index = first + i;
             match inlet {
-                MSPInlet::Float(cb) => unsafe {
-                    assert!(index > 0 && index < 10, "index out of range");
+                //the ft1..ft9 and in1..in9 methods only cover 1 to 9, use a proxy otherwise
+                MSPInlet::Float(cb) if index > 0 && index < 10 => unsafe {
                     let _ = max_sys::floatin(self.max_obj as _, index as _);
                     let _ = callbacks_float.insert(index, cb);
                 },
-                MSPInlet::Int(cb) => unsafe {
-                    assert!(index > 0 && index < 10, "index out of range");
+                MSPInlet::Int(cb) if index > 0 && index < 10 => unsafe {
                     let _ = max_sys::intin(self.max_obj as _, index as _);
                     let _ = callbacks_int.insert(index, cb);
                 },
+                MSPInlet::Float(cb) => {
+                    proxies.push(Proxy::new(self.max_obj, index));
+                    let _ = handlers.insert(index, InletHandlers::from_float(cb));
+                }
+                MSPInlet::Int(cb) => {
+                    proxies.push(Proxy::new(self.max_obj, index));
+                    let _ = handlers.insert(index, InletHandlers::from_int(cb));
+                }
                 MSPInlet::Proxy => {
                     proxies.push(Proxy::new(self.max_obj, index));
                 }
-                MSPInlet::Signal => {
-                    if !called_dsp_setup {
-                        called_dsp_setup = true;
-                        unsafe {
-                            max_sys::z_dsp_setup(
-                                self.msp_obj.expect("need to have msp object for dsp"),
-                                signal_inlets.expect("should have signal inlets") as _,
-                            );
-                        }
-                    }
+                MSPInlet::Handlers(h) => {
+                    proxies.push(Proxy::new(self.max_obj, index));
+                    let _ = handlers.insert(index, h);
                 }
+                MSPInlet::Signal => unreachable!(),
             };
         }
 
-        (callbacks_float, callbacks_int, proxies)
+        //after the other inlets so the signal inlets end up leftmost
+        if let Some(n) = signal_inlets {
+            unsafe {
+                max_sys::z_dsp_setup(
+                    self.msp_obj.expect("need to have msp object for dsp"),
+                    n as _,
+                );
+            }
+        }
+
+        if self.default_inlet == DefaultInlet::Omitted {
+            unsafe {
+                let inlet = max_sys::inlet_nth(self.max_obj, 0);
+                if !inlet.is_null() {
+                    max_sys::inlet_delete(inlet);
+                }
+            }
+        }
+
+        (callbacks_float, callbacks_int, handlers, proxies)
     }
 
     fn add_out_assist(&mut self, i: usize, s: &str) {
@@ -284,9 +551,26 @@ where
     W: WrapperWrapped<T>,
 {
     fn with_default_inlet_assist(&mut self, assist: &str) {
+        assert!(
+            self.default_inlet != DefaultInlet::Omitted,
+            "the default inlet has been removed"
+        );
         self.add_in_assist(0, assist);
     }
 
+    fn without_default_inlet(&mut self) {
+        assert!(
+            self.inlets.is_empty(),
+            "the default inlet must be removed before adding inlets"
+        );
+        //index 0 would describe the first inlet added instead
+        assert!(
+            !self.assist_ins.contains_key(&0),
+            "the default inlet must be removed before adding its assistance"
+        );
+        self.default_inlet = DefaultInlet::Omitted;
+    }
+
     fn with_clockfn(&mut self, func: fn(&T)) -> ClockHandle {
         unsafe {
             ClockHandle::new(
@@ -317,10 +601,33 @@ where
         b
     }
 
+    fn with_thread_safe_outlets(&mut self, policy: ThreadPolicy) {
+        self.outlet_config.thread = Some(policy);
+    }
+
+    fn with_stack_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.outlet_config.overflow = policy;
+    }
+
+    fn with_outlet_counters(&mut self) {
+        self.outlet_config.counters = true;
+    }
+
+    fn cold_values(&self) -> ColdValues {
+        self.cold_values.clone()
+    }
+
+    fn pattr_notifier(&mut self) -> Notifier {
+        let owner = self.max_obj;
+        self.pattr
+            .get_or_insert_with(|| Modified::new(owner))
+            .notifier()
+    }
+
     /// Add an outlet that outputs bangs.
     fn add_bang_outlet(&mut self) -> OutBang {
         self.outlet_count += 1;
-        Outlet::append_bang(self.max_obj)
+        self.outlet_config.bang(self.max_obj)
     }
 
     fn add_bang_outlet_with_assist(&mut self, assist: &str) -> OutBang {
@@ -331,7 +638,7 @@ where
     /// Add an outlet that outputs floats.
     fn add_float_outlet(&mut self) -> OutFloat {
         self.outlet_count += 1;
-        Outlet::append_float(self.max_obj)
+        self.outlet_config.float(self.max_obj)
     }
 
     fn add_float_outlet_with_assist(&mut self, assist: &str) -> OutFloat {
@@ -342,7 +649,7 @@ where
     /// Add an outlet that outputs ints.
     fn add_int_outlet(&mut self) -> OutInt {
         self.outlet_count += 1;
-        Outlet::append_int(self.max_obj)
+        self.outlet_config.int(self.max_obj)
     }
 
     fn add_int_outlet_with_assist(&mut self, assist: &str) -> OutInt {
@@ -353,7 +660,7 @@ where
     /// Add an outlet that outputs lists.
     fn add_list_outlet(&mut self) -> OutList {
         self.outlet_count += 1;
-        Outlet::append_list(self.max_obj)
+        self.outlet_config.list(self.max_obj)
     }
 
     fn add_list_outlet_with_assist(&mut self, assist: &str) -> OutList {
@@ -364,7 +671,7 @@ where
     /// Add an outlet that outputs anything Max supports.
     fn add_anything_outlet(&mut self) -> OutAnything {
         self.outlet_count += 1;
-        Outlet::append_anything(self.max_obj)
+        self.outlet_config.anything(self.max_obj)
     }
 
     fn add_anything_outlet_with_assist(&mut self, assist: &str) -> OutAnything {
@@ -372,9 +679,52 @@ where
         self.add_anything_outlet()
     }
 
+    /// Add an outlet that outputs symbols.
+    fn add_symbol_outlet(&mut self) -> OutSymbol {
+        self.outlet_count += 1;
+        self.outlet_config.symbol(self.max_obj)
+    }
+
+    fn add_symbol_outlet_with_assist(&mut self, assist: &str) -> OutSymbol {
+        self.add_out_assist(self.outlet_count, assist);
+        self.add_symbol_outlet()
+    }
+
+    /// Add an outlet that outputs `dictionary <name>`.
+    fn add_dictionary_outlet(&mut self) -> OutDictionary {
+        OutDictionary::new(self.add_anything_outlet())
+    }
+
+    fn add_dictionary_outlet_with_assist(&mut self, assist: &str) -> OutDictionary {
+        OutDictionary::new(self.add_anything_outlet_with_assist(assist))
+    }
+
+    /// Add an outlet that outputs `jit_matrix <name>`.
+    fn add_jit_matrix_outlet(&mut self) -> OutJitMatrix {
+        OutJitMatrix::new(self.add_anything_outlet())
+    }
+
+    fn add_jit_matrix_outlet_with_assist(&mut self, assist: &str) -> OutJitMatrix {
+        OutJitMatrix::new(self.add_anything_outlet_with_assist(assist))
+    }
+
+    /// Add the dumpout outlet.
+    fn add_dumpout_outlet(&mut self) -> OutAnything {
+        self.outlet_count += 1;
+        self.outlet_config.dumpout(self.max_obj)
+    }
+
+    fn add_dumpout_outlet_with_assist(&mut self, assist: &str) -> OutAnything {
+        self.add_out_assist(self.outlet_count, assist);
+        self.add_dumpout_outlet()
+    }
+
     fn creation_args(&self) -> &[Atom] {
         self.args
     }
+    fn positional_args(&self) -> &[Atom] {
+        &self.args[..attr_args_offset(self.args)]
+    }
     fn creation_symbol(&self) -> SymbolRef {
         self.sym.clone()
     }
@@ -412,13 +762,15 @@ where
 {
     /// Add an inlet, left to right, returns index.
     fn add_inlet(&mut self, inlet_type: MaxInlet<T>) -> usize {
+        let index = self.next_inlet_index();
         //convert to MSP so we can share the builder
         self.inlets.push(match inlet_type {
             MaxInlet::Int(f) => MSPInlet::Int(f),
             MaxInlet::Float(f) => MSPInlet::Float(f),
             MaxInlet::Proxy => MSPInlet::Proxy,
+            MaxInlet::Handlers(h) => MSPInlet::Handlers(h),
         });
-        self.inlets.len() //there is a default inlet that we don't specify
+        index
     }
 
     /// Add an inlet with an assist string, left to right, returns index.
@@ -464,23 +816,40 @@ where
             }),
             "can only specify signal inlets once"
         );
+        assert!(
+            self.inlets.is_empty(),
+            "signal inlets must be added before other inlets"
+        );
         for _ in 0..count {
             self.inlets.push(MSPInlet::Signal);
         }
     }
 
     fn add_signal_inlets_with_assist(&mut self, assist: &[&str]) {
-        let start = self.inlets.len();
         self.add_signal_inlets(assist.len());
+        //signal inlets are always leftmost
         for (i, s) in assist.iter().enumerate() {
-            self.add_in_assist(i + start, *s);
+            self.add_in_assist(i, *s);
         }
     }
 
+    fn with_signal_only_default_inlet(&mut self) {
+        self.default_inlet = DefaultInlet::SignalOnly;
+    }
+
     /// Add an inlet, left to right, returns index.
     fn add_inlet(&mut self, inlet_type: MSPInlet<T>) -> usize {
+        let index = if let MSPInlet::Signal = inlet_type {
+            assert!(
+                self.inlets.iter().all(|i| matches!(i, MSPInlet::Signal)),
+                "signal inlets must be added before other inlets"
+            );
+            self.inlets.len()
+        } else {
+            self.next_inlet_index()
+        };
         self.inlets.push(inlet_type);
-        self.inlets.len() //there is a default inlet that we don't specify
+        index
     }
 
     /// Add an inlet with an assist string, left to right, returns index.
@@ -501,7 +870,11 @@ where
 pub struct MaxWrappedBuilderFinalize<T> {
     pub callbacks_float: FloatCBHash<T>,
     pub callbacks_int: IntCBHash<T>,
+    pub inlet_handlers: InletHandlersHash<T>,
     pub proxy_inlets: Vec<Proxy>,
+    pub default_inlet: DefaultInlet,
+    pub messages: Arc<MessageCount>,
+    pub pattr: Option<Modified>,
     pub buffer_refs: Vec<ManagedBufferRefInternal>,
     pub assist_ins: HashMap<usize, CString>,
     pub assist_outs: HashMap<usize, CString>,
@@ -512,22 +885,56 @@ pub struct MSPWrappedBuilderFinalize<T> {
     pub signal_outlets: usize,
     pub callbacks_float: FloatCBHash<T>,
     pub callbacks_int: IntCBHash<T>,
+    pub inlet_handlers: InletHandlersHash<T>,
     pub proxy_inlets: Vec<Proxy>,
+    pub default_inlet: DefaultInlet,
+    pub messages: Arc<MessageCount>,
+    pub pattr: Option<Modified>,
     pub buffer_refs: Vec<ManagedBufferRefInternal>,
     pub assist_ins: HashMap<usize, CString>,
     pub assist_outs: HashMap<usize, CString>,
 }
 
+//attribute changes notify pattr for classes that implement getvalueof, even if they don't ask for
+//a notifier
+fn pattr_modified<T: ObjWrapped<T>>(
+    pattr: Option<Modified>,
+    owner: *mut max_sys::t_object,
+) -> Option<Modified> {
+    match pattr {
+        None if T::hooks().getvalueof => Some(Modified::new(owner)),
+        pattr => pattr,
+    }
+}
+
+//the wrapper only routes messages by inlet for classes that ask for it
+fn check_inlet_dispatch<T: ObjWrapped<T>>(
+    handlers: &InletHandlersHash<T>,
+    default_inlet: DefaultInlet,
+) {
+    assert!(
+        T::inlet_dispatch() || (handlers.is_empty() && default_inlet != DefaultInlet::SignalOnly),
+        "{} has inlets that route their own messages but ObjWrapped::inlet_dispatch returns false",
+        T::class_name()
+    );
+}
+
 impl<'a, T> WrappedBuilder<'a, T, MaxObjWrapper<T>>
 where
     T: MaxObjWrapped<T>,
 {
     pub fn finalize(mut self) -> MaxWrappedBuilderFinalize<T> {
-        let (callbacks_float, callbacks_int, proxy_inlets) = self.finalize_inlets(None);
+        let (callbacks_float, callbacks_int, inlet_handlers, proxy_inlets) =
+            self.finalize_inlets(None);
+        check_inlet_dispatch::<T>(&inlet_handlers, self.default_inlet);
         MaxWrappedBuilderFinalize {
             callbacks_float,
             callbacks_int,
+            inlet_handlers,
             proxy_inlets,
+            default_inlet: self.default_inlet,
+            messages: self.outlet_config.messages,
+            pattr: pattr_modified::<T>(self.pattr, self.max_obj),
             buffer_refs: self.buffer_refs,
             assist_ins: self.assist_ins,
             assist_outs: self.assist_outs,
@@ -542,17 +949,53 @@ where
     //finalize and return siginal (inlets, outlets) counts
     pub fn finalize(mut self) -> MSPWrappedBuilderFinalize<T> {
         let (signal_inlets, signal_outlets) = self.signal_iolets();
-        let (callbacks_float, callbacks_int, proxy_inlets) =
+        let (callbacks_float, callbacks_int, inlet_handlers, proxy_inlets) =
             self.finalize_inlets(Some(signal_inlets));
+        check_inlet_dispatch::<T>(&inlet_handlers, self.default_inlet);
         MSPWrappedBuilderFinalize {
             signal_inlets,
             signal_outlets,
             callbacks_float,
             callbacks_int,
+            inlet_handlers,
             proxy_inlets,
+            default_inlet: self.default_inlet,
+            messages: self.outlet_config.messages,
+            pattr: pattr_modified::<T>(self.pattr, self.max_obj),
             buffer_refs: self.buffer_refs,
             assist_ins: self.assist_ins,
             assist_outs: self.assist_outs,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inlet_indices() {
+        let mut b: WrappedBuilder<(), ()> =
+            WrappedBuilder::new_max(std::ptr::null_mut(), crate::sym!("x"), &[]);
+        assert_eq!(b.next_inlet_index(), 1);
+        b.inlets.push(MSPInlet::Proxy);
+        assert_eq!(b.next_inlet_index(), 2);
+
+        let mut b: WrappedBuilder<(), ()> =
+            WrappedBuilder::new_max(std::ptr::null_mut(), crate::sym!("x"), &[]);
+        b.default_inlet = DefaultInlet::Omitted;
+        assert_eq!(b.next_inlet_index(), 0);
+        b.inlets.push(MSPInlet::Proxy);
+        assert_eq!(b.next_inlet_index(), 1);
+
+        //the default inlet is the first signal inlet
+        let mut b: WrappedBuilder<(), ()> =
+            WrappedBuilder::new_msp(std::ptr::null_mut(), crate::sym!("x"), &[]);
+        b.default_inlet = DefaultInlet::SignalOnly;
+        b.inlets.push(MSPInlet::Signal);
+        b.inlets.push(MSPInlet::Signal);
+        assert_eq!(b.next_inlet_index(), 2);
+        b.inlets.push(MSPInlet::Proxy);
+        assert_eq!(b.next_inlet_index(), 3);
+    }
+}
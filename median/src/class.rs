@@ -2,6 +2,7 @@
 
 use crate::attr::Attr;
 use crate::error::{MaxError, MaxResult};
+use crate::inlet::ClassFallbacks;
 use crate::method::*;
 use std::ffi::CString;
 use std::marker::PhantomData;
@@ -9,6 +10,8 @@ use std::os::raw::c_long;
 
 pub struct Class<T> {
     class: *mut max_sys::t_class,
+    //set for wrapped classes, which register their own inlet dispatch methods
+    fallbacks: Option<ClassFallbacks>,
     _phantom: PhantomData<T>,
 }
 
@@ -20,12 +23,11 @@ pub enum ClassType {
 
 impl Into<*mut max_sys::t_symbol> for ClassType {
     fn into(self) -> *mut max_sys::t_symbol {
-        let t = CString::new(match self {
-            ClassType::NoBox => "nobox",
-            ClassType::Box => "box",
-        })
-        .unwrap();
-        unsafe { max_sys::gensym(t.as_ptr()) }
+        let t = match self {
+            ClassType::NoBox => crate::sym!("nobox"),
+            ClassType::Box => crate::sym!("box"),
+        };
+        t.into()
     }
 }
 
@@ -43,6 +45,7 @@ impl<T> Class<T> {
     pub unsafe fn new_registered(class: *mut max_sys::t_class) -> Self {
         Self {
             class,
+            fallbacks: None,
             _phantom: PhantomData,
         }
     }
@@ -74,11 +77,76 @@ impl<T> Class<T> {
         }
     }
 
+    /// Add another name that the class can be created with.
+    ///
+    /// # Remarks
+    /// * The class must already be registered.
+    pub fn add_alias(&mut self, name: &str) -> MaxResult<()> {
+        let name = CString::new(name).expect("couldn't convert name to CString");
+        unsafe {
+            MaxError::from(
+                max_sys::class_alias(self.class, max_sys::gensym(name.as_ptr())) as _,
+                (),
+            )
+        }
+    }
+
     /// Get the inner class object.
     pub fn inner(&mut self) -> *mut max_sys::t_class {
         self.class
     }
 
+    /// Add a method to the class.
+    ///
+    /// # Remarks
+    /// * For wrapped classes, `bang`, `int`, `float`, `list`, `anything` and variable argument
+    /// selector methods handle the messages that an inlet's
+    /// [`InletHandlers`](../inlet/struct.InletHandlers.html) don't.
+    pub fn add_method(&mut self, m: Method<T>) -> MaxResult<()> {
+        let fallbacks = match self.fallbacks.as_mut() {
+            Some(f) => f,
+            None => return self.add_method_max(m),
+        };
+        unsafe {
+            match m {
+                Method::Bang(f) => {
+                    fallbacks.bang = Some(std::mem::transmute::<B<T>, MaxMethod>(f));
+                }
+                Method::Int(f) => {
+                    fallbacks.int = Some(std::mem::transmute::<I<T>, MaxMethod>(f));
+                }
+                Method::Float(f) => {
+                    fallbacks.float = Some(std::mem::transmute::<F<T>, MaxMethod>(f));
+                }
+                Method::List(f) => {
+                    fallbacks.list = Some(std::mem::transmute::<SelList<T>, MaxMethod>(f));
+                }
+                Method::Anything(f) => {
+                    fallbacks.anything = Some(std::mem::transmute::<SelList<T>, MaxMethod>(f));
+                }
+                Method::SelVarArg(sel, f) => {
+                    let sel = CString::new(sel).expect("couldn't convert selector to CString");
+                    fallbacks.selectors.insert(
+                        max_sys::gensym(sel.as_ptr()) as usize,
+                        std::mem::transmute::<SelList<T>, MaxMethod>(f),
+                    );
+                }
+                m => return self.add_method_max(m),
+            }
+        }
+        Ok(())
+    }
+
+    //route methods that inlet handlers also handle into fallbacks, the wrapper registers its
+    //dispatch methods for them instead
+    pub(crate) fn capture_fallbacks(&mut self) {
+        self.fallbacks = Some(ClassFallbacks::default());
+    }
+
+    pub(crate) fn take_fallbacks(&mut self) -> ClassFallbacks {
+        self.fallbacks.take().unwrap_or_default()
+    }
+
     pub fn add_attribute(&mut self, attr: Attr<T>) -> MaxResult<()> {
         MaxError::from(
             unsafe { max_sys::class_addattr(self.inner(), attr.into()) as _ },
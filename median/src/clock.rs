@@ -7,7 +7,7 @@ use crate::{
     wrapper::{MaxObjWrapped, MaxObjWrapper, ObjWrapped, WrapperWrapped},
 };
 
-use std::ffi::{c_void, CString};
+use std::ffi::c_void;
 
 struct ClockInner {
     target: Option<(*mut max_sys::t_object, Box<dyn Fn(*mut max_sys::t_object)>)>,
@@ -30,7 +30,10 @@ impl ClockInner {
 
     fn call(&self) {
         if let Some((target, func)) = &self.target {
-            (func)(*target)
+            //report panics against the object the clock calls into
+            crate::logging::with_object(*target, || {
+                crate::panic::catch(*target, "clock", || (func)(*target))
+            });
         }
     }
 }
@@ -124,36 +127,7 @@ impl ClockHandle {
             >(ClockInner::call_tramp)),
         );
 
-        //set the scheduler for the clock to the scheduler for the owning object
-        let sched = max_sys::scheduler_fromobject(target);
-        if !sched.is_null() {
-            let spound = CString::new("#S").unwrap();
-            max_sys::object_obex_storeflags(
-                clock,
-                max_sys::gensym(spound.as_ptr()),
-                sched as _,
-                max_sys::e_max_datastore_flags::OBJ_FLAG_DATA as _,
-            );
-        }
-
-        //set the patcher and box for the clock
-        for lookup in &["#P", "#B"] {
-            let name = CString::new(*lookup).unwrap();
-            let mut ob = std::ptr::null_mut();
-            if max_sys::object_obex_lookup(
-                target as _,
-                std::mem::transmute::<_, _>(name.as_ptr()),
-                &mut ob,
-            ) == max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_atom_long
-            {
-                let _ = max_sys::object_obex_storeflags(
-                    clock,
-                    max_sys::gensym(name.as_ptr()),
-                    ob as _,
-                    max_sys::e_max_datastore_flags::OBJ_FLAG_REF as _,
-                );
-            }
-        }
+        attach(clock, target);
 
         Self {
             _target: clock_target,
@@ -162,6 +136,35 @@ impl ClockHandle {
     }
 }
 
+//run the clock on the scheduler of the owning object, with its patcher and box
+pub(crate) unsafe fn attach(clock: *mut c_void, target: *mut max_sys::t_object) {
+    //set the scheduler for the clock to the scheduler for the owning object
+    let sched = max_sys::scheduler_fromobject(target);
+    if !sched.is_null() {
+        max_sys::object_obex_storeflags(
+            clock,
+            crate::sym!("#S").inner(),
+            sched as _,
+            max_sys::e_max_datastore_flags::OBJ_FLAG_DATA as _,
+        );
+    }
+
+    //set the patcher and box for the clock
+    for name in &[crate::sym!("#P"), crate::sym!("#B")] {
+        let mut ob = std::ptr::null_mut();
+        if max_sys::object_obex_lookup(target as _, name.inner(), &mut ob)
+            == max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_atom_long
+        {
+            let _ = max_sys::object_obex_storeflags(
+                clock,
+                name.inner(),
+                ob as _,
+                max_sys::e_max_datastore_flags::OBJ_FLAG_REF as _,
+            );
+        }
+    }
+}
+
 impl Drop for ClockHandle {
     fn drop(&mut self) {
         self.cancel();
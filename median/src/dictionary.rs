@@ -0,0 +1,645 @@
+//! Dictionaries, structured data shared by name.
+//!
+//! see [cycling 74 docs](https://cycling74.com/sdk/max-sdk-8.0.3/html/group__dictionary.html)
+
+use crate::{
+    atom::{Atom, AtomType},
+    error::{MaxError, MaxResult},
+    max::common_symbols,
+    outlet::{SendAnything, SendResult},
+    symbol::SymbolRef,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Number, Value};
+use std::{convert::TryFrom, marker::PhantomData, ops::Deref, os::raw::c_long};
+
+/// How a `Dictionary` releases its reference to the underlying `t_dictionary`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Ownership {
+    /// We created it, free with `object_free`.
+    Owned,
+    /// We retained it by name, free with `dictobj_release`.
+    Retained,
+    /// Somebody else owns it, do nothing.
+    Borrowed,
+}
+
+/// A safe wrapper for a Max `t_dictionary`.
+pub struct Dictionary {
+    inner: *mut max_sys::t_dictionary,
+    ownership: Ownership,
+}
+
+/// A dictionary that somebody else owns, valid for the lifetime `'a`.
+///
+/// # Remarks
+/// * Unlike a `Dictionary` it can't be sent to other threads, since the owner may free it.
+pub struct DictionaryRef<'a> {
+    dict: Dictionary,
+    //not Send or Sync
+    _phantom: PhantomData<(&'a Dictionary, *mut ())>,
+}
+
+/// Errors converting dictionaries to and from other representations.
+#[derive(Debug)]
+pub enum DictionaryError {
+    /// Max reported an error.
+    Max(MaxError),
+    /// A JSON value can't be represented in a dictionary, or the reverse.
+    Unsupported(String),
+    /// Serializing or deserializing with serde failed.
+    Serde(serde_json::Error),
+}
+
+impl Dictionary {
+    /// Create a new, empty, unregistered dictionary.
+    pub fn new() -> Self {
+        unsafe { Self::from_raw_owned(max_sys::dictionary_new()) }
+    }
+
+    /// Create a new, empty dictionary and register it with Max so that it can be referred to by
+    /// name.
+    ///
+    /// # Arguments
+    /// * `name` - the name to register under, if `None`, Max will generate a unique name.
+    pub fn new_registered(name: Option<SymbolRef>) -> Self {
+        let mut d = Self::new();
+        d.register(name);
+        d
+    }
+
+    /// Find a registered dictionary by name and retain it.
+    ///
+    /// # Remarks
+    /// * The dictionary is released when the returned value is dropped.
+    pub fn find_retained(name: &SymbolRef) -> Option<Self> {
+        let inner = unsafe { max_sys::dictobj_findregistered_retain(name.inner()) };
+        if inner.is_null() {
+            None
+        } else {
+            Some(Self {
+                inner,
+                ownership: Ownership::Retained,
+            })
+        }
+    }
+
+    /// Wrap a dictionary that you own, it will be freed with `object_free` when dropped.
+    pub unsafe fn from_raw_owned(inner: *mut max_sys::t_dictionary) -> Self {
+        assert!(!inner.is_null(), "dictionary pointer must not be null");
+        Self {
+            inner,
+            ownership: Ownership::Owned,
+        }
+    }
+
+    /// Wrap a dictionary that somebody else owns, nothing happens when dropped.
+    ///
+    /// # Remarks
+    /// * You must make sure that the dictionary outlives the returned value.
+    pub unsafe fn from_raw_borrowed<'a>(inner: *mut max_sys::t_dictionary) -> DictionaryRef<'a> {
+        assert!(!inner.is_null(), "dictionary pointer must not be null");
+        DictionaryRef {
+            dict: Self {
+                inner,
+                ownership: Ownership::Borrowed,
+            },
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Give up ownership and get the raw dictionary pointer.
+    pub fn into_raw(self) -> *mut max_sys::t_dictionary {
+        let inner = self.inner;
+        std::mem::forget(self);
+        inner
+    }
+
+    /// Get the raw dictionary pointer.
+    pub unsafe fn inner(&self) -> *mut max_sys::t_dictionary {
+        self.inner
+    }
+
+    /// Register this dictionary with Max, if it isn't already, and return its name.
+    ///
+    /// # Arguments
+    /// * `name` - the name to register under, if `None`, Max will generate a unique name.
+    pub fn register(&mut self, name: Option<SymbolRef>) -> SymbolRef {
+        if let Some(n) = self.name() {
+            return n;
+        }
+        let mut name: *mut max_sys::t_symbol = match name {
+            Some(n) => unsafe { n.inner() },
+            None => std::ptr::null_mut(),
+        };
+        unsafe {
+            self.inner = max_sys::dictobj_register(self.inner, &mut name);
+        }
+        name.into()
+    }
+
+    /// Get the name that this dictionary is registered under, if it is registered.
+    pub fn name(&self) -> Option<SymbolRef> {
+        let name = unsafe { max_sys::dictobj_namefromptr(self.inner) };
+        if name.is_null() {
+            None
+        } else {
+            Some(name.into())
+        }
+    }
+
+    /// Get the number of entries in the dictionary.
+    pub fn len(&self) -> usize {
+        unsafe { max_sys::dictionary_getentrycount(self.inner) as _ }
+    }
+
+    /// Does the dictionary have no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Is there an entry for `key`.
+    pub fn contains_key(&self, key: &SymbolRef) -> bool {
+        unsafe { max_sys::dictionary_hasentry(self.inner, key.inner()) != 0 }
+    }
+
+    /// Is the entry for `key` a nested dictionary.
+    pub fn is_dictionary(&self, key: &SymbolRef) -> bool {
+        unsafe { max_sys::dictionary_entryisdictionary(self.inner, key.inner()) != 0 }
+    }
+
+    /// Is the entry for `key` an array of atoms.
+    pub fn is_atom_array(&self, key: &SymbolRef) -> bool {
+        unsafe { max_sys::dictionary_entryisatomarray(self.inner, key.inner()) != 0 }
+    }
+
+    /// Get all the keys in the dictionary.
+    pub fn keys(&self) -> Vec<SymbolRef> {
+        let mut numkeys: c_long = 0;
+        let mut keys: *mut *mut max_sys::t_symbol = std::ptr::null_mut();
+        unsafe {
+            if max_sys::dictionary_getkeys(self.inner, &mut numkeys, &mut keys)
+                != max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_max_err
+                || keys.is_null()
+            {
+                return Vec::new();
+            }
+            let v = std::slice::from_raw_parts(keys, numkeys as _)
+                .iter()
+                .map(|k| SymbolRef::from(*k))
+                .collect();
+            max_sys::dictionary_freekeys(self.inner, numkeys, keys);
+            v
+        }
+    }
+
+    /// Remove the entry for `key`, freeing its value.
+    pub fn remove(&mut self, key: &SymbolRef) -> MaxResult<()> {
+        MaxError::from(
+            unsafe { max_sys::dictionary_deleteentry(self.inner, key.inner()) as _ },
+            (),
+        )
+    }
+
+    /// Remove all the entries from the dictionary.
+    pub fn clear(&mut self) -> MaxResult<()> {
+        MaxError::from(unsafe { max_sys::dictionary_clear(self.inner) as _ }, ())
+    }
+
+    /// Set an int entry.
+    pub fn set_int(&mut self, key: &SymbolRef, value: max_sys::t_atom_long) -> MaxResult<()> {
+        MaxError::from(
+            unsafe { max_sys::dictionary_appendlong(self.inner, key.inner(), value) as _ },
+            (),
+        )
+    }
+
+    /// Set a float entry.
+    pub fn set_float(&mut self, key: &SymbolRef, value: f64) -> MaxResult<()> {
+        MaxError::from(
+            unsafe { max_sys::dictionary_appendfloat(self.inner, key.inner(), value) as _ },
+            (),
+        )
+    }
+
+    /// Set a symbol entry.
+    pub fn set_symbol(&mut self, key: &SymbolRef, value: &SymbolRef) -> MaxResult<()> {
+        MaxError::from(
+            unsafe { max_sys::dictionary_appendsym(self.inner, key.inner(), value.inner()) as _ },
+            (),
+        )
+    }
+
+    /// Set an atom array entry, the atoms are copied.
+    pub fn set_atoms(&mut self, key: &SymbolRef, value: &[Atom]) -> MaxResult<()> {
+        MaxError::from(
+            unsafe {
+                max_sys::dictionary_appendatoms(
+                    self.inner,
+                    key.inner(),
+                    value.len() as _,
+                    //Atom is transparent, so it can be cast to t_atom, max doesn't mutate
+                    value.as_ptr() as *mut max_sys::t_atom,
+                ) as _
+            },
+            (),
+        )
+    }
+
+    /// Set a nested dictionary entry, this dictionary takes ownership of `value`.
+    ///
+    /// # Panics
+    /// * Will panic if `value` isn't owned, for instance if it was retained by name.
+    pub fn set_dictionary(&mut self, key: &SymbolRef, value: Dictionary) -> MaxResult<()> {
+        assert_eq!(
+            value.ownership,
+            Ownership::Owned,
+            "can only nest dictionaries that you own"
+        );
+        MaxError::from(
+            unsafe {
+                max_sys::dictionary_appenddictionary(self.inner, key.inner(), value.into_raw() as _)
+                    as _
+            },
+            (),
+        )
+    }
+
+    /// Get an int entry.
+    pub fn get_int(&self, key: &SymbolRef) -> Option<max_sys::t_atom_long> {
+        let mut v = 0;
+        unsafe {
+            MaxError::from(
+                max_sys::dictionary_getlong(self.inner, key.inner(), &mut v) as _,
+                v,
+            )
+            .ok()
+        }
+    }
+
+    /// Get a float entry.
+    pub fn get_float(&self, key: &SymbolRef) -> Option<f64> {
+        let mut v = 0f64;
+        unsafe {
+            MaxError::from(
+                max_sys::dictionary_getfloat(self.inner, key.inner(), &mut v) as _,
+                v,
+            )
+            .ok()
+        }
+    }
+
+    /// Get a symbol entry.
+    pub fn get_symbol(&self, key: &SymbolRef) -> Option<SymbolRef> {
+        let mut v: *mut max_sys::t_symbol = std::ptr::null_mut();
+        unsafe {
+            MaxError::from(
+                max_sys::dictionary_getsym(self.inner, key.inner(), &mut v) as _,
+                (),
+            )
+            .ok()
+            .map(|_| SymbolRef::from(v))
+        }
+    }
+
+    /// Get a copy of the atoms in an entry, this works for single values as well as atom arrays.
+    pub fn get_atoms(&self, key: &SymbolRef) -> Option<Vec<Atom>> {
+        let mut ac: c_long = 0;
+        let mut av: *mut max_sys::t_atom = std::ptr::null_mut();
+        unsafe {
+            if max_sys::dictionary_getatoms(self.inner, key.inner(), &mut ac, &mut av)
+                != max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_max_err
+            {
+                return None;
+            }
+            if av.is_null() || ac < 1 {
+                return Some(Vec::new());
+            }
+            //the dictionary retains ownership of the atoms, so we copy them
+            Some(
+                std::slice::from_raw_parts(av, ac as _)
+                    .iter()
+                    .map(|a| Atom { value: *a })
+                    .collect(),
+            )
+        }
+    }
+
+    /// Get a nested dictionary entry.
+    pub fn get_dictionary(&self, key: &SymbolRef) -> Option<DictionaryRef<'_>> {
+        let mut v: *mut max_sys::t_object = std::ptr::null_mut();
+        unsafe {
+            if max_sys::dictionary_getdictionary(self.inner, key.inner(), &mut v)
+                != max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_max_err
+                || v.is_null()
+            {
+                None
+            } else {
+                Some(Self::from_raw_borrowed(v as _))
+            }
+        }
+    }
+
+    /// Convert the contents of this dictionary into a JSON value.
+    ///
+    /// # Remarks
+    /// * Entries with no atoms become `null`.
+    /// * Object atoms other than dictionaries can't be represented and produce an error.
+    pub fn to_json(&self) -> Result<Value, DictionaryError> {
+        let mut map = Map::new();
+        for key in self.keys() {
+            let name = key
+                .to_string()
+                .map_err(|_| DictionaryError::Unsupported("non utf8 key".into()))?;
+            let value = if self.is_dictionary(&key) {
+                match self.get_dictionary(&key) {
+                    Some(d) => d.to_json()?,
+                    None => Value::Null,
+                }
+            } else {
+                let atoms = self.get_atoms(&key).unwrap_or_default();
+                if self.is_atom_array(&key) && !atoms.is_empty() {
+                    Value::Array(
+                        atoms
+                            .iter()
+                            .map(atom_to_json)
+                            .collect::<Result<Vec<Value>, _>>()?,
+                    )
+                } else {
+                    match atoms.first() {
+                        Some(a) => atom_to_json(a)?,
+                        None => Value::Null,
+                    }
+                }
+            };
+            map.insert(name, value);
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// Create a new, unregistered, dictionary from a JSON object.
+    ///
+    /// # Remarks
+    /// * Strings become symbols and booleans become ints.
+    /// * `null` becomes an entry with no atoms.
+    /// * Arrays may only contain numbers, strings, booleans and objects.
+    pub fn from_json(value: &Value) -> Result<Self, DictionaryError> {
+        match value {
+            Value::Object(map) => {
+                let mut d = Self::new();
+                for (k, v) in map.iter() {
+                    let key = SymbolRef::try_from(k.as_str())
+                        .map_err(|e| DictionaryError::Unsupported(e.to_string()))?;
+                    d.set_json(&key, v)?;
+                }
+                Ok(d)
+            }
+            _ => Err(DictionaryError::Unsupported(
+                "only JSON objects can be converted to dictionaries".into(),
+            )),
+        }
+    }
+
+    /// Serialize `value` into a new, unregistered, dictionary.
+    pub fn from_serde<T: Serialize>(value: &T) -> Result<Self, DictionaryError> {
+        Self::from_json(&serde_json::to_value(value).map_err(DictionaryError::Serde)?)
+    }
+
+    /// Deserialize the contents of this dictionary into a `T`.
+    pub fn to_serde<T: DeserializeOwned>(&self) -> Result<T, DictionaryError> {
+        serde_json::from_value(self.to_json()?).map_err(DictionaryError::Serde)
+    }
+
+    /// Send `dictionary <name>` out of the given outlet, registering this dictionary if needed.
+    pub fn send(&mut self, outlet: &dyn for<'a> SendAnything<'a>) -> SendResult {
+        let name = self.register(None);
+        outlet.send_anything(common_symbols().s_dictionary.into(), &[name.into()])
+    }
+
+    fn set_json(&mut self, key: &SymbolRef, value: &Value) -> Result<(), DictionaryError> {
+        match value {
+            Value::Null => self.set_atoms(key, &[]),
+            Value::Bool(b) => self.set_int(key, *b as _),
+            Value::Number(n) => match json_int(n)? {
+                Some(i) => self.set_int(key, i as _),
+                None => self.set_float(key, n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => self.set_symbol(key, &json_str_to_symbol(s)?),
+            Value::Array(a) => {
+                //nested dictionaries are freed if a later element fails
+                let mut nested = Vec::new();
+                let atoms = a
+                    .iter()
+                    .map(|v| json_to_atom(v, &mut nested))
+                    .collect::<Result<Vec<Atom>, _>>()?;
+                let res = self.set_atoms(key, &atoms);
+                if res.is_ok() {
+                    //the array owns them now
+                    for d in nested {
+                        let _ = d.into_raw();
+                    }
+                }
+                res
+            }
+            Value::Object(_) => self.set_dictionary(key, Self::from_json(value)?),
+        }
+        .map_err(DictionaryError::Max)
+    }
+}
+
+fn json_str_to_symbol(s: &str) -> Result<SymbolRef, DictionaryError> {
+    SymbolRef::try_from(s).map_err(|e| DictionaryError::Unsupported(e.to_string()))
+}
+
+//the int value of a JSON number, None for floats
+fn json_int(n: &Number) -> Result<Option<i64>, DictionaryError> {
+    match n.as_i64() {
+        Some(i) => Ok(Some(i)),
+        None if n.is_u64() => Err(DictionaryError::Unsupported(format!(
+            "{} is too large for an int",
+            n
+        ))),
+        None => Ok(None),
+    }
+}
+
+//dictionaries are added to nested, which owns them until the array that holds them is set
+fn json_to_atom(value: &Value, nested: &mut Vec<Dictionary>) -> Result<Atom, DictionaryError> {
+    match value {
+        Value::Bool(b) => Ok(Atom::from(*b as max_sys::t_atom_long)),
+        Value::Number(n) => Ok(match json_int(n)? {
+            Some(i) => Atom::from(i as max_sys::t_atom_long),
+            None => Atom::from(n.as_f64().unwrap_or_default()),
+        }),
+        Value::String(s) => Ok(Atom::from(json_str_to_symbol(s)?)),
+        Value::Object(_) => {
+            let d = Dictionary::from_json(value)?;
+            let atom = Atom::from(d.inner as *mut std::ffi::c_void);
+            nested.push(d);
+            Ok(atom)
+        }
+        Value::Null | Value::Array(_) => Err(DictionaryError::Unsupported(
+            "arrays cannot contain null or nested arrays".into(),
+        )),
+    }
+}
+
+fn atom_to_json(atom: &Atom) -> Result<Value, DictionaryError> {
+    match atom.get_type() {
+        Some(AtomType::Int) => Ok(Value::Number(Number::from(atom.get_int() as i64))),
+        Some(AtomType::Float) => Number::from_f64(atom.get_float())
+            .map(Value::Number)
+            .ok_or_else(|| DictionaryError::Unsupported("non finite float".into())),
+        Some(AtomType::Symbol) => atom
+            .get_symbol()
+            .to_string()
+            .map(Value::String)
+            .map_err(|_| DictionaryError::Unsupported("non utf8 symbol".into())),
+        Some(AtomType::Object) => {
+            let obj = atom.get_obj();
+            let class: SymbolRef = unsafe { max_sys::object_classname(obj) }.into();
+            if class == SymbolRef::from(common_symbols().s_dictionary) {
+                unsafe { Dictionary::from_raw_borrowed(obj as _) }.to_json()
+            } else {
+                Err(DictionaryError::Unsupported(format!(
+                    "cannot convert {} object",
+                    class
+                )))
+            }
+        }
+        None => Err(DictionaryError::Unsupported("unknown atom type".into())),
+    }
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Dictionary {
+    fn drop(&mut self) {
+        unsafe {
+            match self.ownership {
+                Ownership::Owned => {
+                    let _ = max_sys::object_free(self.inner as _);
+                }
+                Ownership::Retained => {
+                    let _ = max_sys::dictobj_release(self.inner);
+                }
+                Ownership::Borrowed => (),
+            }
+        }
+    }
+}
+
+impl<'a> Deref for DictionaryRef<'a> {
+    type Target = Dictionary;
+    fn deref(&self) -> &Self::Target {
+        &self.dict
+    }
+}
+
+//no DerefMut, it would let the borrowed Dictionary be swapped out and sent to another thread
+macro_rules! forward_mut {
+    ( $( $name:ident ( $( $arg:ident : $t:ty ),* ) -> $ret:ty; )+ ) => {
+        impl<'a> DictionaryRef<'a> {
+            $(
+                #[doc = concat!("See [`Dictionary::", stringify!($name), "`](struct.Dictionary.html#method.", stringify!($name), ").")]
+                pub fn $name(&mut self, $( $arg: $t ),*) -> $ret {
+                    self.dict.$name($( $arg ),*)
+                }
+            )+
+        }
+    };
+}
+
+forward_mut! {
+    remove(key: &SymbolRef) -> MaxResult<()>;
+    clear() -> MaxResult<()>;
+    set_int(key: &SymbolRef, value: max_sys::t_atom_long) -> MaxResult<()>;
+    set_float(key: &SymbolRef, value: f64) -> MaxResult<()>;
+    set_symbol(key: &SymbolRef, value: &SymbolRef) -> MaxResult<()>;
+    set_atoms(key: &SymbolRef, value: &[Atom]) -> MaxResult<()>;
+    set_dictionary(key: &SymbolRef, value: Dictionary) -> MaxResult<()>;
+}
+
+impl std::fmt::Display for DictionaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Max(e) => write!(f, "max error {:?}", e),
+            Self::Unsupported(s) => write!(f, "unsupported value: {}", s),
+            Self::Serde(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for DictionaryError {}
+
+//dictionaries do their own locking, and a Dictionary is always owned or retained, borrowed ones
+//are only handed out as a DictionaryRef
+unsafe impl Send for Dictionary {}
+unsafe impl Sync for Dictionary {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::dictionary::live;
+    use serde_json::json;
+
+    #[test]
+    fn json() {
+        let value = json!({
+            "int": 1,
+            "float": 0.5,
+            "sym": "foo",
+            "list": [1, "bar", {"nested": true}],
+            "dict": {"a": null},
+        });
+        {
+            let d = Dictionary::from_json(&value).unwrap();
+            assert!(d.is_dictionary(&crate::sym!("dict")));
+            assert!(d.is_atom_array(&crate::sym!("list")));
+            assert_eq!(d.get_int(&crate::sym!("int")), Some(1));
+            assert_eq!(
+                d.to_json().unwrap(),
+                json!({
+                    "int": 1,
+                    "float": 0.5,
+                    "sym": "foo",
+                    "list": [1, "bar", {"nested": 1}],
+                    "dict": {"a": null},
+                })
+            );
+        }
+        assert_eq!(live(), 0);
+    }
+
+    #[test]
+    fn json_errors() {
+        //the nested dictionary is created before the null fails
+        assert!(Dictionary::from_json(&json!({"list": [{"a": 1}, null]})).is_err());
+        assert_eq!(live(), 0);
+
+        assert!(Dictionary::from_json(&json!({"big": u64::MAX})).is_err());
+        assert!(Dictionary::from_json(&json!({"list": [u64::MAX]})).is_err());
+        assert!(Dictionary::from_json(&json!({"big": i64::MAX})).is_ok());
+        assert!(Dictionary::from_json(&json!([1])).is_err());
+        assert_eq!(live(), 0);
+    }
+
+    #[test]
+    fn borrowed() {
+        let mut d = Dictionary::new();
+        {
+            let mut b = unsafe { Dictionary::from_raw_borrowed(d.inner()) };
+            b.set_atoms(&crate::sym!("a"), &[Atom::from(1), Atom::from(2)])
+                .unwrap();
+        }
+        //dropping the borrow doesn't free it
+        assert_eq!(live(), 1);
+        assert_eq!(d.get_atoms(&crate::sym!("a")).map(|a| a.len()), Some(2));
+        d.clear().unwrap();
+        assert!(d.is_empty());
+    }
+}
@@ -25,3 +25,16 @@ impl MaxError {
         }
     }
 }
+
+impl std::fmt::Display for MaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Generic => write!(f, "generic max error"),
+            Self::InvalidPtr => write!(f, "invalid pointer"),
+            Self::Duplicate => write!(f, "duplicate"),
+            Self::OutOfMem => write!(f, "out of memory"),
+        }
+    }
+}
+
+impl std::error::Error for MaxError {}
@@ -1,16 +1,26 @@
 //! Inlets
-use std::ffi::c_void;
+use crate::{atom::Atom, method::MaxMethod, symbol::SymbolRef};
+use parking_lot::{Mutex, ReentrantMutex, ReentrantMutexGuard};
+use std::{collections::HashMap, ffi::c_void, sync::Arc};
 
 ///Callback method for Float inlet
 pub type FloatCB<T> = Box<dyn Fn(&T, f64)>;
 ///Callback method for Int inlet
 pub type IntCB<T> = Box<dyn Fn(&T, max_sys::t_atom_long)>;
+///Callback method for bang in an inlet
+pub type BangCB<T> = Box<dyn Fn(&T)>;
+///Callback method for list in an inlet
+pub type ListCB<T> = Box<dyn Fn(&T, &[Atom])>;
+///Callback method for anything, or a specific selector, in an inlet
+pub type AnythingCB<T> = Box<dyn Fn(&T, &SymbolRef, &[Atom])>;
 
 /// Inlets for Max objects
 pub enum MaxInlet<T> {
     Float(FloatCB<T>),
     Int(IntCB<T>),
     Proxy,
+    /// A proxy inlet with its own message handlers.
+    Handlers(InletHandlers<T>),
 }
 
 /// Inlets for MSP objects
@@ -19,6 +29,423 @@ pub enum MSPInlet<T> {
     Int(IntCB<T>),
     Proxy,
     Signal,
+    /// A proxy inlet with its own message handlers.
+    Handlers(InletHandlers<T>),
+}
+
+/// The kind of an object's default, leftmost, inlet.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DefaultInlet {
+    /// The object's own inlet, messages to it go to the class' methods.
+    Message,
+    /// A signal only inlet, messages sent to it aren't handled. MSP objects only, the class must
+    /// return `true` from
+    /// [`ObjWrapped::inlet_dispatch`](../wrapper/trait.ObjWrapped.html#method.inlet_dispatch).
+    SignalOnly,
+    /// No default inlet, the first inlet added is the leftmost.
+    Omitted,
+}
+
+/// Message handlers for a single inlet.
+///
+/// # Remarks
+/// * Messages to the inlet are routed to these handlers by inlet number, so you don't need to
+/// check [`Proxy::get_inlet`](struct.Proxy.html#method.get_inlet) in your methods.
+/// * Like Max, an int is given to the float handler if there is no int handler and the reverse,
+/// and messages without a handler go to the anything handler.
+/// * Messages that the inlet doesn't handle go to the methods registered for the class.
+/// * The class must return `true` from
+/// [`ObjWrapped::inlet_dispatch`](../wrapper/trait.ObjWrapped.html#method.inlet_dispatch).
+///
+/// # Examples
+///
+/// ```ignore
+/// builder.add_inlet(MaxInlet::Handlers(
+///     InletHandlers::new()
+///         .int(|s: &Self, v| s.set_count(v))
+///         .selector(median::sym!("reset"), |s: &Self, _sel, _atoms| s.reset())
+///         .cold(),
+/// ));
+/// ```
+pub struct InletHandlers<T> {
+    bang: Option<BangCB<T>>,
+    int: Option<IntCB<T>>,
+    float: Option<FloatCB<T>>,
+    list: Option<ListCB<T>>,
+    anything: Option<AnythingCB<T>>,
+    //keyed by symbol address
+    selectors: HashMap<usize, AnythingCB<T>>,
+    cold: bool,
+}
+
+impl<T> InletHandlers<T> {
+    /// Create an empty set of handlers.
+    pub fn new() -> Self {
+        Self {
+            bang: None,
+            int: None,
+            float: None,
+            list: None,
+            anything: None,
+            selectors: HashMap::new(),
+            cold: false,
+        }
+    }
+
+    //for typed inlets past the ones that the ft and in methods cover
+    pub(crate) fn from_float(cb: FloatCB<T>) -> Self {
+        Self {
+            float: Some(cb),
+            ..Self::new()
+        }
+    }
+
+    pub(crate) fn from_int(cb: IntCB<T>) -> Self {
+        Self {
+            int: Some(cb),
+            ..Self::new()
+        }
+    }
+
+    /// Handle `bang`.
+    pub fn bang<F: Fn(&T) + 'static>(mut self, func: F) -> Self {
+        self.bang = Some(Box::new(func));
+        self
+    }
+
+    /// Handle `int`.
+    pub fn int<F: Fn(&T, max_sys::t_atom_long) + 'static>(mut self, func: F) -> Self {
+        self.int = Some(Box::new(func));
+        self
+    }
+
+    /// Handle `float`.
+    pub fn float<F: Fn(&T, f64) + 'static>(mut self, func: F) -> Self {
+        self.float = Some(Box::new(func));
+        self
+    }
+
+    /// Handle `list`.
+    pub fn list<F: Fn(&T, &[Atom]) + 'static>(mut self, func: F) -> Self {
+        self.list = Some(Box::new(func));
+        self
+    }
+
+    /// Handle any message that doesn't have a more specific handler.
+    pub fn anything<F: Fn(&T, &SymbolRef, &[Atom]) + 'static>(mut self, func: F) -> Self {
+        self.anything = Some(Box::new(func));
+        self
+    }
+
+    /// Handle messages with the selector `sel`.
+    pub fn selector<F: Fn(&T, &SymbolRef, &[Atom]) + 'static>(
+        mut self,
+        sel: SymbolRef,
+        func: F,
+    ) -> Self {
+        self.selectors
+            .insert(unsafe { sel.inner() } as usize, Box::new(func));
+        self
+    }
+
+    /// Mark the inlet as cold, Max shows this when patch cords are connected.
+    ///
+    /// # Remarks
+    /// * This is only a hint for the patcher, it doesn't change how messages are handled.
+    pub fn cold(mut self) -> Self {
+        self.cold = true;
+        self
+    }
+
+    /// Is the inlet cold.
+    pub fn is_cold(&self) -> bool {
+        self.cold
+    }
+
+    /// Dispatch a message to these handlers, returns `false` if none of them handle it.
+    pub fn dispatch(&self, target: &T, msg: InletMessage) -> bool {
+        match msg {
+            InletMessage::Bang => {
+                if let Some(f) = &self.bang {
+                    f(target);
+                    true
+                } else {
+                    self.dispatch_anything(target, crate::sym!("bang"), &[])
+                }
+            }
+            InletMessage::Int(v) => {
+                if let Some(f) = &self.int {
+                    f(target, v);
+                    true
+                } else if let Some(f) = &self.float {
+                    f(target, v as f64);
+                    true
+                } else {
+                    self.dispatch_anything(target, crate::sym!("int"), &[Atom::from(v)])
+                }
+            }
+            InletMessage::Float(v) => {
+                if let Some(f) = &self.float {
+                    f(target, v);
+                    true
+                } else if let Some(f) = &self.int {
+                    f(target, v as max_sys::t_atom_long);
+                    true
+                } else {
+                    self.dispatch_anything(target, crate::sym!("float"), &[Atom::from(v)])
+                }
+            }
+            InletMessage::List(atoms) => {
+                if let Some(f) = &self.list {
+                    f(target, atoms);
+                    true
+                } else {
+                    self.dispatch_anything(target, crate::sym!("list"), atoms)
+                }
+            }
+            InletMessage::Anything(sel, atoms) => self.dispatch_anything(target, sel, atoms),
+        }
+    }
+
+    fn dispatch_anything(&self, target: &T, sel: SymbolRef, atoms: &[Atom]) -> bool {
+        if let Some(f) = self.selectors.get(&(unsafe { sel.inner() } as usize)) {
+            f(target, &sel, atoms);
+            true
+        } else if let Some(f) = &self.anything {
+            f(target, &sel, atoms);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<T> Default for InletHandlers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A value stored by a cold inlet, converted from the numbers and lists sent to the inlet.
+///
+/// # Remarks
+/// * Each conversion is optional, messages that don't convert leave the stored value unchanged.
+pub trait ColdValue: Clone + Send + 'static {
+    /// Convert an `int`.
+    fn from_int(_v: max_sys::t_atom_long) -> Option<Self> {
+        None
+    }
+
+    /// Convert a `float`.
+    fn from_float(_v: f64) -> Option<Self> {
+        None
+    }
+
+    /// Convert a `list`.
+    fn from_list(_list: &[Atom]) -> Option<Self> {
+        None
+    }
+}
+
+impl ColdValue for f64 {
+    fn from_int(v: max_sys::t_atom_long) -> Option<Self> {
+        Some(v as f64)
+    }
+
+    fn from_float(v: f64) -> Option<Self> {
+        Some(v)
+    }
+
+    /// Uses the first item.
+    fn from_list(list: &[Atom]) -> Option<Self> {
+        list.first().map(|a| a.get_float())
+    }
+}
+
+impl ColdValue for max_sys::t_atom_long {
+    fn from_int(v: max_sys::t_atom_long) -> Option<Self> {
+        Some(v)
+    }
+
+    fn from_float(v: f64) -> Option<Self> {
+        Some(v as max_sys::t_atom_long)
+    }
+
+    /// Uses the first item.
+    fn from_list(list: &[Atom]) -> Option<Self> {
+        list.first().map(|a| a.get_int())
+    }
+}
+
+impl ColdValue for Vec<f64> {
+    fn from_int(v: max_sys::t_atom_long) -> Option<Self> {
+        Some(vec![v as f64])
+    }
+
+    fn from_float(v: f64) -> Option<Self> {
+        Some(vec![v])
+    }
+
+    fn from_list(list: &[Atom]) -> Option<Self> {
+        Some(list.iter().map(|a| a.get_float()).collect())
+    }
+}
+
+impl ColdValue for Vec<max_sys::t_atom_long> {
+    fn from_int(v: max_sys::t_atom_long) -> Option<Self> {
+        Some(vec![v])
+    }
+
+    fn from_float(v: f64) -> Option<Self> {
+        Some(vec![v as max_sys::t_atom_long])
+    }
+
+    fn from_list(list: &[Atom]) -> Option<Self> {
+        Some(list.iter().map(|a| a.get_int()).collect())
+    }
+}
+
+/// The lock shared by all of an object's cold inlets.
+///
+/// # Remarks
+/// * Cold inlets store their value while holding it, so values read while holding it all come
+/// from the same moment, even if messages arrive from the main and scheduler threads at once.
+/// * The lock is reentrant, a message that loops back to a cold inlet from the thread holding it
+/// still stores its value.
+#[derive(Clone, Default)]
+pub struct ColdValues {
+    lock: Arc<ReentrantMutex<()>>,
+}
+
+/// Holds the values of an object's cold inlets, see [`ColdValues::hold`].
+pub struct ColdGuard<'a> {
+    _guard: ReentrantMutexGuard<'a, ()>,
+}
+
+impl ColdValues {
+    /// Hold the values of all of the object's cold inlets until the guard is dropped.
+    pub fn hold(&self) -> ColdGuard<'_> {
+        crate::rt::check::note("lock");
+        ColdGuard {
+            _guard: self.lock.lock(),
+        }
+    }
+}
+
+/// A value cell that a cold inlet updates, usually read by the handler of a hot inlet.
+///
+/// # Remarks
+/// * Create these with `add_cold_inlet` on the builder.
+/// * `int`, `float` and `list` messages are converted with [`ColdValue`], other messages are
+/// reported to the console, they never reach the class' methods.
+///
+/// # Examples
+/// ```ignore
+/// //in new
+/// let right = builder.add_cold_inlet::<f64>(0.);
+///
+/// //in the float method of the default inlet
+/// let _values = self.right.hold();
+/// self.out.send(v + self.right.get())?;
+/// ```
+pub struct ColdInlet<V> {
+    value: Arc<Mutex<V>>,
+    values: ColdValues,
+}
+
+impl<V: ColdValue> ColdInlet<V> {
+    pub(crate) fn new(default: V, values: ColdValues) -> Self {
+        Self {
+            value: Arc::new(Mutex::new(default)),
+            values,
+        }
+    }
+
+    /// Get a copy of the stored value.
+    pub fn get(&self) -> V {
+        let _values = self.values.hold();
+        self.value.lock().clone()
+    }
+
+    /// Replace the stored value, for instance from a creation argument.
+    pub fn set(&self, value: V) {
+        let _values = self.values.hold();
+        *self.value.lock() = value;
+    }
+
+    /// Hold the values of all of the object's cold inlets, so that several `get`s see the values
+    /// from the same moment.
+    pub fn hold(&self) -> ColdGuard<'_> {
+        self.values.hold()
+    }
+
+    //handlers that store the converted values, anything else is consumed so it doesn't go to the
+    //methods of the hot inlet
+    pub(crate) fn handlers<T: 'static>(&self) -> InletHandlers<T> {
+        let (int, float, list) = (self.clone(), self.clone(), self.clone());
+        InletHandlers::new()
+            .int(move |_: &T, v| int.store(V::from_int(v)))
+            .float(move |_: &T, v| float.store(V::from_float(v)))
+            .list(move |_: &T, l| list.store(V::from_list(l)))
+            .anything(|_: &T, sel, _| {
+                crate::logging::console(
+                    crate::logging::ConsoleLevel::Error,
+                    format!("doesn't understand \"{}\" in a cold inlet", sel),
+                )
+            })
+            .cold()
+    }
+
+    fn store(&self, value: Option<V>) {
+        if let Some(v) = value {
+            self.set(v);
+        }
+    }
+}
+
+impl<V> Clone for ColdInlet<V> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            values: self.values.clone(),
+        }
+    }
+}
+
+/// A message that arrived at an inlet.
+#[derive(Clone)]
+pub enum InletMessage<'a> {
+    Bang,
+    Int(max_sys::t_atom_long),
+    Float(f64),
+    List(&'a [Atom]),
+    Anything(SymbolRef, &'a [Atom]),
+}
+
+impl<'a> InletMessage<'a> {
+    /// Get the selector of the message.
+    pub fn selector(&self) -> SymbolRef {
+        match self {
+            Self::Bang => crate::sym!("bang"),
+            Self::Int(_) => crate::sym!("int"),
+            Self::Float(_) => crate::sym!("float"),
+            Self::List(_) => crate::sym!("list"),
+            Self::Anything(sel, _) => sel.clone(),
+        }
+    }
+}
+
+/// Methods that a wrapped class registered for the messages that inlet handlers also handle,
+/// called for messages that an inlet doesn't handle itself.
+#[derive(Clone, Default)]
+pub(crate) struct ClassFallbacks {
+    pub bang: Option<MaxMethod>,
+    pub int: Option<MaxMethod>,
+    pub float: Option<MaxMethod>,
+    pub list: Option<MaxMethod>,
+    pub anything: Option<MaxMethod>,
+    //A_GIMME selector methods, keyed by symbol address
+    pub selectors: HashMap<usize, MaxMethod>,
 }
 
 /// Encapsulation of a Max Proxy inlet
@@ -45,3 +472,93 @@ impl Drop for Proxy {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn dispatch() {
+        let log = RefCell::new(Vec::new());
+        let h = InletHandlers::new()
+            .float(|l: &RefCell<Vec<String>>, v| l.borrow_mut().push(format!("float {}", v)))
+            .selector(crate::sym!("set"), |l, s, a| {
+                l.borrow_mut().push(format!("{} {}", s, a.len()))
+            });
+        assert!(!h.is_cold());
+        assert!(h.dispatch(&log, InletMessage::Int(2)));
+        assert!(h.dispatch(&log, InletMessage::Float(0.5)));
+        assert!(h.dispatch(
+            &log,
+            InletMessage::Anything(crate::sym!("set"), &[Atom::from(1)])
+        ));
+        assert!(!h.dispatch(&log, InletMessage::Bang));
+        assert!(!h.dispatch(&log, InletMessage::List(&[])));
+        assert!(!h.dispatch(&log, InletMessage::Anything(crate::sym!("foo"), &[])));
+        assert_eq!(
+            log.take(),
+            vec![
+                "float 2".to_string(),
+                "float 0.5".to_string(),
+                "set 1".to_string()
+            ]
+        );
+
+        let h = InletHandlers::new()
+            .anything(|l: &RefCell<Vec<String>>, s, a| {
+                l.borrow_mut().push(format!("{} {}", s, a.len()))
+            })
+            .cold();
+        assert!(h.is_cold());
+        assert!(h.dispatch(&log, InletMessage::Bang));
+        assert!(h.dispatch(&log, InletMessage::Int(1)));
+        assert!(h.dispatch(&log, InletMessage::List(&[Atom::from(1), Atom::from(2)])));
+        assert_eq!(
+            log.take(),
+            vec![
+                "bang 0".to_string(),
+                "int 1".to_string(),
+                "list 2".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn cold() {
+        let values = ColdValues::default();
+        let right = ColdInlet::new(1f64, values.clone());
+        let h = right.handlers::<()>();
+        assert!(h.is_cold());
+        assert!(h.dispatch(&(), InletMessage::Int(2)));
+        assert_eq!(right.get(), 2.);
+        assert!(h.dispatch(&(), InletMessage::List(&[Atom::from(0.5), Atom::from(1)])));
+        assert_eq!(right.get(), 0.5);
+        //nothing to convert
+        assert!(h.dispatch(&(), InletMessage::List(&[])));
+        assert_eq!(right.get(), 0.5);
+        //reported rather than left to the class' methods
+        crate::test::post::take_posted();
+        assert!(h.dispatch(&(), InletMessage::Bang));
+        assert!(h.dispatch(&(), InletMessage::Anything(crate::sym!("set"), &[])));
+        assert_eq!(right.get(), 0.5);
+        assert_eq!(
+            crate::test::post::take_posted(),
+            vec![
+                "object error: doesn't understand \"bang\" in a cold inlet".to_string(),
+                "object error: doesn't understand \"set\" in a cold inlet".to_string()
+            ]
+        );
+
+        let list = ColdInlet::new(Vec::<max_sys::t_atom_long>::new(), values.clone());
+        let h = list.handlers::<()>();
+        {
+            //reentrant, a feedback loop on the holding thread still stores
+            let _values = values.hold();
+            assert!(h.dispatch(&(), InletMessage::List(&[Atom::from(1), Atom::from(2.5)])));
+            assert!(list.get() == vec![1, 2]);
+        }
+        assert!(h.dispatch(&(), InletMessage::Float(3.5)));
+        assert!(list.get() == vec![3]);
+    }
+}
@@ -48,20 +48,27 @@
 //! ```
 //!
 pub mod alloc;
+pub mod args;
 pub mod atom;
 pub mod attr;
 pub mod buffer;
 pub mod builder;
 pub mod class;
 pub mod clock;
+pub mod dictionary;
 pub mod error;
 pub mod file;
 pub mod inlet;
+pub mod logging;
 pub mod method;
 pub mod notify;
 pub mod num;
 pub mod object;
 pub mod outlet;
+pub mod panic;
+pub mod pattr;
+pub mod qelem;
+pub mod rt;
 pub mod slice;
 pub mod symbol;
 pub mod thread;
@@ -76,6 +83,12 @@ pub use max_sys;
 /// Wrap the given code in ext_main
 pub use median_macros::ext_main;
 /// Create an external with the wrapped contents.
+///
+/// # Remarks
+/// * The block can hold several structs, each with its own impls, the generated `ext_main`
+/// registers all of them.
+/// * `#[name="max.name"]` sets the name of the class in Max and `#[alias("other.name")]` adds
+/// more names.
 pub use median_macros::external;
 /// Create an external with the wrapped contents, don't register ext_main.
 pub use median_macros::external_no_main;
@@ -96,6 +109,86 @@ macro_rules! error {
     }}
 }
 
+/// Post a message to the Max console from the audio thread, using the same format as
+/// `std::format!`.
+///
+/// # Remarks
+/// * Doesn't allocate, lock or call into Max, the message is queued and written on the main
+/// thread, see the [`rt`](rt/index.html) module.
+/// * The message is associated with the object whose method is running, if there is one.
+/// * Repeats from the same call site before the message is written are counted, not queued.
+///
+/// # Examples
+///
+/// ```ignore
+/// fn perform(&self, ins: &[&[f64]], outs: &mut [&mut [f64]], nframes: usize) {
+///     if ins.is_empty() {
+///         median::rt_post!("no inputs, {} frames", nframes);
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! rt_post {
+    ($($arg:tt)*) => {{
+        static SITE: $crate::rt::Site = $crate::rt::Site::new();
+        SITE.post($crate::logging::ConsoleLevel::Post, ::std::format_args!($($arg)*))
+    }}
+}
+
+/// Post an error to the Max console from the audio thread, using the same format as
+/// `std::format!`.
+///
+/// See [`rt_post!`](macro.rt_post.html).
+#[macro_export]
+macro_rules! rt_error {
+    ($($arg:tt)*) => {{
+        static SITE: $crate::rt::Site = $crate::rt::Site::new();
+        SITE.post($crate::logging::ConsoleLevel::Error, ::std::format_args!($($arg)*))
+    }}
+}
+
+/// Get a [`SymbolRef`](symbol/struct.SymbolRef.html) for a string literal.
+///
+/// # Remarks
+/// * The symbol is interned with `gensym` the first time each call site runs, and is cached in a
+/// static after that, so this is cheap enough for hot paths.
+///
+/// # Examples
+///
+/// ```ignore
+/// if notification.message() == &median::sym!("buffer_modified") {
+///     //...
+/// }
+/// ```
+#[macro_export]
+macro_rules! sym {
+    ($name:expr) => {{
+        static SYMBOL: $crate::symbol::StaticSymbol =
+            $crate::symbol::StaticSymbol::new(::std::concat!($name, "\0"));
+        SYMBOL.get()
+    }};
+}
+
+/// Create an [`AtomVec`](atom/struct.AtomVec.html) from a list of values, using `Into<Atom>`.
+///
+/// # Examples
+///
+/// ```ignore
+/// let atoms = median::atoms![1, 2.5, "foo"];
+/// self.out.send(&atoms[..])?;
+/// ```
+#[macro_export]
+macro_rules! atoms {
+    () => {
+        $crate::atom::AtomVec::new()
+    };
+    ($($x:expr),+ $(,)?) => {{
+        let mut atoms = $crate::atom::AtomVec::new();
+        $(atoms.push($x);)+
+        atoms
+    }};
+}
+
 /// Post a message to the Max console, associated with the given object, using the same format as `std::format!`.
 ///
 /// # Examples
@@ -165,3 +258,7 @@ macro_rules! object_error {
 
 #[cfg(test)]
 pub mod test;
+
+//so the derives in tests can refer to this crate as ::median
+#[cfg(test)]
+extern crate self as median;
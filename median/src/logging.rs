@@ -0,0 +1,355 @@
+//! Send `log` and `tracing` output to the Max console.
+//!
+//! Call [`init_log`](fn.init_log.html), or [`init_tracing`](fn.init_tracing.html) with the
+//! `tracing` feature, from your `ext_main` to see the output of libraries that log in the Max
+//! console.
+//!
+//! * Errors go to the console as errors, warnings as warnings and everything else as posts.
+//! * Messages logged from inside one of your object's methods are associated with that object, see
+//! [`with_object`](fn.with_object.html).
+//! * Messages from the main thread are posted right away, messages from other threads are queued
+//! and posted by a qelem on the main thread, so logging never blocks the audio thread.
+
+use crate::qelem::Qelem;
+use crossbeam_queue::SegQueue;
+use std::{
+    cell::Cell,
+    ffi::{CStr, CString},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// The kinds of message that the Max console displays.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConsoleLevel {
+    Post,
+    Warn,
+    Error,
+}
+
+struct Entry {
+    level: ConsoleLevel,
+    obj: usize,
+    msg: String,
+}
+
+struct Console {
+    queue: SegQueue<Entry>,
+    qelem: Qelem,
+}
+
+lazy_static::lazy_static! {
+    static ref CONSOLE: Console = Console {
+        queue: SegQueue::new(),
+        qelem: Qelem::new(flush),
+    };
+}
+
+//so that flush doesn't create the console just to find it empty
+static CONSOLE_CREATED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static OBJECT: Cell<*mut max_sys::t_object> = const { Cell::new(std::ptr::null_mut()) };
+}
+
+struct MaxLogger;
+static LOGGER: MaxLogger = MaxLogger;
+
+/// Install a `log` logger that writes to the Max console.
+///
+/// # Arguments
+/// * `level` - the most verbose level to write to the console.
+///
+/// # Remarks
+/// * Call this from the main thread, `ext_main` is a good place.
+/// * Fails if a logger has already been installed, for instance by another external that shares
+/// the library.
+pub fn init_log(level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+    create_console();
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Install a `tracing` subscriber that writes events to the Max console.
+///
+/// # Arguments
+/// * `level` - the most verbose level to write to the console.
+///
+/// # Remarks
+/// * Call this from the main thread, `ext_main` is a good place.
+/// * Spans are accepted but not displayed, only events are written.
+#[cfg(feature = "tracing")]
+pub fn init_tracing(
+    level: tracing_core::LevelFilter,
+) -> Result<(), tracing_core::dispatcher::SetGlobalDefaultError> {
+    create_console();
+    tracing_core::dispatcher::set_global_default(tracing_core::Dispatch::new(
+        trace::MaxSubscriber::new(level),
+    ))
+}
+
+/// Run `func` with `obj` as the object that log messages are associated with.
+///
+/// # Remarks
+/// * The trampolines for wrapped objects already do this, you only need it for your own callbacks.
+/// * The previous object is restored when `func` returns or panics.
+pub fn with_object<R, F: FnOnce() -> R>(obj: *mut max_sys::t_object, func: F) -> R {
+    struct Restore(*mut max_sys::t_object);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let _ = OBJECT.try_with(|o| o.set(self.0));
+        }
+    }
+    let _restore = Restore(OBJECT.with(|o| o.replace(obj)));
+    func()
+}
+
+/// Get the object that log messages are currently associated with, null if there isn't one.
+pub fn current_object() -> *mut max_sys::t_object {
+    OBJECT.try_with(|o| o.get()).unwrap_or(std::ptr::null_mut())
+}
+
+/// Write a message to the Max console, associated with the current object.
+///
+/// # Remarks
+/// * Safe to call from any thread, messages from threads other than the main thread are queued.
+pub fn console(level: ConsoleLevel, msg: String) {
+    console_object(level, current_object(), msg);
+}
+
+/// Write a message to the Max console, associated with `obj`.
+///
+/// # Remarks
+/// * Safe to call from any thread, messages from threads other than the main thread are queued.
+/// * `obj` can be null.
+pub fn console_object(level: ConsoleLevel, obj: *mut max_sys::t_object, msg: String) {
+    if unsafe { max_sys::systhread_ismainthread() } != 0 {
+        write(level, obj, msg);
+    } else {
+        let console = create_console();
+        console.queue.push(Entry {
+            level,
+            obj: obj as usize,
+            msg,
+        });
+        console.qelem.set();
+    }
+}
+
+/// Write any queued messages to the console, called on the main thread.
+///
+/// # Remarks
+/// * Wrapped objects flush when they're freed so queued messages never refer to a freed object.
+pub fn flush() {
+    if CONSOLE_CREATED.load(Ordering::Acquire) {
+        while let Some(e) = CONSOLE.queue.pop() {
+            write(e.level, e.obj as _, e.msg);
+        }
+    }
+}
+
+fn create_console() -> &'static Console {
+    let console = &*CONSOLE;
+    CONSOLE_CREATED.store(true, Ordering::Release);
+    console
+}
+
+fn write(level: ConsoleLevel, obj: *mut max_sys::t_object, msg: String) {
+    let msg = CString::new(msg).unwrap_or_else(|e| {
+        //drop interior nuls rather than the whole message
+        let mut v = e.into_vec();
+        v.retain(|c| *c != 0);
+        CString::new(v).unwrap()
+    });
+    write_cstr(level, obj, &msg);
+}
+
+pub(crate) fn write_cstr(level: ConsoleLevel, obj: *mut max_sys::t_object, msg: &CStr) {
+    //pass the message as an argument so any % in it isn't treated as a format specifier
+    let fmt = b"%s\0".as_ptr() as *const std::os::raw::c_char;
+    unsafe {
+        match level {
+            ConsoleLevel::Post => max_sys::object_post(obj, fmt, msg.as_ptr()),
+            ConsoleLevel::Warn => max_sys::object_warn(obj, fmt, msg.as_ptr()),
+            ConsoleLevel::Error => max_sys::object_error(obj, fmt, msg.as_ptr()),
+        }
+    }
+}
+
+impl From<log::Level> for ConsoleLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => Self::Error,
+            log::Level::Warn => Self::Warn,
+            _ => Self::Post,
+        }
+    }
+}
+
+impl log::Log for MaxLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            console(
+                record.level().into(),
+                format!("{}: {}", record.target(), record.args()),
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(feature = "tracing")]
+mod trace {
+    use super::{console, ConsoleLevel};
+    use std::{
+        fmt::Write,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+    use tracing_core::{
+        field::{Field, Visit},
+        span, Event, Level, LevelFilter, Metadata, Subscriber,
+    };
+
+    pub struct MaxSubscriber {
+        level: LevelFilter,
+        next_id: AtomicU64,
+    }
+
+    #[derive(Default)]
+    struct Fields {
+        message: String,
+        rest: String,
+    }
+
+    impl MaxSubscriber {
+        pub fn new(level: LevelFilter) -> Self {
+            Self {
+                level,
+                //zero isn't a valid span id
+                next_id: AtomicU64::new(1),
+            }
+        }
+    }
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                let _ = write!(self.message, "{:?}", value);
+            } else {
+                let _ = write!(self.rest, " {}={:?}", field.name(), value);
+            }
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "message" {
+                self.message.push_str(value);
+            } else {
+                let _ = write!(self.rest, " {}={}", field.name(), value);
+            }
+        }
+    }
+
+    impl Subscriber for MaxSubscriber {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            self.level >= *metadata.level()
+        }
+
+        fn max_level_hint(&self) -> Option<LevelFilter> {
+            Some(self.level)
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed))
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let level = match *event.metadata().level() {
+                Level::ERROR => ConsoleLevel::Error,
+                Level::WARN => ConsoleLevel::Warn,
+                _ => ConsoleLevel::Post,
+            };
+            console(
+                level,
+                format!(
+                    "{}: {}{}",
+                    event.metadata().target(),
+                    fields.message,
+                    fields.rest
+                ),
+            );
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_context() {
+        let a = 1usize as *mut max_sys::t_object;
+        let b = 2usize as *mut max_sys::t_object;
+        assert!(current_object().is_null());
+        with_object(a, || {
+            assert_eq!(current_object(), a);
+            let _ = std::panic::catch_unwind(|| with_object(b, || panic!("restore")));
+            assert_eq!(current_object(), a);
+        });
+        assert!(current_object().is_null());
+    }
+
+    #[test]
+    fn queued() {
+        use crate::test::post::{set_main_thread, take_posted};
+        take_posted();
+        set_main_thread(false);
+        console_object(
+            ConsoleLevel::Warn,
+            std::ptr::null_mut(),
+            "100% queued".into(),
+        );
+        set_main_thread(true);
+        assert!(take_posted().is_empty());
+        flush();
+        assert_eq!(take_posted(), vec!["object warn: 100% queued".to_string()]);
+    }
+
+    #[test]
+    fn logger() {
+        use crate::test::post::take_posted;
+        init_log(log::LevelFilter::Info).unwrap();
+        take_posted();
+        log::info!("info {}", 1);
+        log::debug!("debug");
+        log::error!("error");
+        assert_eq!(
+            take_posted(),
+            vec![
+                "object post: median::logging::tests: info 1".to_string(),
+                "object error: median::logging::tests: error".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(ConsoleLevel::from(log::Level::Error), ConsoleLevel::Error);
+        assert_eq!(ConsoleLevel::from(log::Level::Warn), ConsoleLevel::Warn);
+        assert_eq!(ConsoleLevel::from(log::Level::Trace), ConsoleLevel::Post);
+    }
+}
@@ -12,6 +12,7 @@ pub fn common_symbols() -> &'static max_sys::_common_symbols_table {
 
 /// Post a message to the Max console.
 pub fn post<T: Into<Vec<u8>>>(msg: T) {
+    crate::rt::check::note("post");
     unsafe {
         match CString::new(msg) {
             Ok(p) => max_sys::post(p.as_ptr()),
@@ -23,6 +24,7 @@ pub fn post<T: Into<Vec<u8>>>(msg: T) {
 
 /// Post an error to the Max console.
 pub fn error<T: Into<Vec<u8>>>(msg: T) {
+    crate::rt::check::note("post");
     unsafe {
         match CString::new(msg) {
             Ok(p) => max_sys::error(p.as_ptr()),
@@ -1,6 +1,6 @@
 //! Method encapsulation and type aliases
 use crate::{atom::Atom, symbol::SymbolRef};
-use std::{ffi::c_void, os::raw::c_long};
+use std::{ffi::c_void, fmt::Display, os::raw::c_long};
 
 pub type MaxNew = unsafe extern "C" fn(
     sym: *mut max_sys::t_symbol,
@@ -19,15 +19,121 @@ pub type MaxMethod = unsafe extern "C" fn(arg1: *mut c_void, ...) -> *mut c_void
 pub type B<T> = unsafe extern "C" fn(&T);
 pub type SelList<T> =
     unsafe extern "C" fn(&T, *mut max_sys::t_symbol, c_long, *const max_sys::t_atom);
+pub type SelGimmeBack<T> = unsafe extern "C" fn(
+    &T,
+    *mut max_sys::t_symbol,
+    c_long,
+    *const max_sys::t_atom,
+    *mut max_sys::t_atom,
+) -> max_sys::t_max_err;
+
+/// The return value of a message handler or attribute setter.
+///
+/// # Remarks
+/// * Handlers can return `()` or `Result<(), E>` where `E: Display`, the generated trampolines
+/// post an `Err` to the Max console for the object.
+/// * Attribute setters that return `Err` reject the value, Max gets `MAX_ERR_GENERIC`.
+pub trait HandlerResult {
+    /// Report any error against `obj` and get the Max error code for the result.
+    fn report(self, obj: *mut max_sys::t_object) -> max_sys::t_max_err;
+}
+
+impl HandlerResult for () {
+    fn report(self, _obj: *mut max_sys::t_object) -> max_sys::t_max_err {
+        max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+    }
+}
+
+impl<E: Display> HandlerResult for Result<(), E> {
+    fn report(self, obj: *mut max_sys::t_object) -> max_sys::t_max_err {
+        match self {
+            Ok(()) => max_sys::e_max_errorcodes::MAX_ERR_NONE as _,
+            Err(e) => {
+                crate::object::error(obj, e.to_string());
+                max_sys::e_max_errorcodes::MAX_ERR_GENERIC as _
+            }
+        }
+    }
+}
+
+/// A Max error code that has already been reported, returned from a handler to pass it through
+/// to Max as is.
+///
+/// # Remarks
+/// * A newtype so that handlers returning plain integers aren't mistaken for error codes.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MaxErr(pub max_sys::t_max_err);
+
+impl HandlerResult for MaxErr {
+    fn report(self, _obj: *mut max_sys::t_object) -> max_sys::t_max_err {
+        self.0
+    }
+}
+
+/// The return value of a method that can be called with `object_method_typed`, see
+/// [`Method::SelGimmeBack`](enum.Method.html#variant.SelGimmeBack).
+///
+/// # Remarks
+/// * An `Atom` is given back to the caller, `()` gives nothing back.
+/// * An `Err` is posted to the Max console for the object and the caller gets `MAX_ERR_GENERIC`.
+pub trait TypedResult {
+    /// Write the value to `rv`, report any error against `obj` and get the Max error code.
+    fn write(
+        self,
+        obj: *mut max_sys::t_object,
+        rv: Option<&mut max_sys::t_atom>,
+    ) -> max_sys::t_max_err;
+}
+
+impl TypedResult for () {
+    fn write(
+        self,
+        _obj: *mut max_sys::t_object,
+        _rv: Option<&mut max_sys::t_atom>,
+    ) -> max_sys::t_max_err {
+        max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+    }
+}
+
+impl TypedResult for Atom {
+    fn write(
+        self,
+        _obj: *mut max_sys::t_object,
+        rv: Option<&mut max_sys::t_atom>,
+    ) -> max_sys::t_max_err {
+        if let Some(rv) = rv {
+            *rv = self.value;
+        }
+        max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+    }
+}
+
+impl<T: TypedResult, E: Display> TypedResult for Result<T, E> {
+    fn write(
+        self,
+        obj: *mut max_sys::t_object,
+        rv: Option<&mut max_sys::t_atom>,
+    ) -> max_sys::t_max_err {
+        match self {
+            Ok(v) => v.write(obj, rv),
+            Err(e) => {
+                crate::object::error(obj, e.to_string());
+                max_sys::e_max_errorcodes::MAX_ERR_GENERIC as _
+            }
+        }
+    }
+}
 
 /// helper method to convert between max and median calls selector list method calls
-pub fn sel_list<F>(
+pub fn sel_list<F, R>(
     sym: *mut max_sys::t_symbol,
     ac: ::std::os::raw::c_long,
     av: *const ::max_sys::t_atom,
     f: F,
-) where
-    F: Fn(SymbolRef, &[Atom]),
+) -> R
+where
+    F: Fn(SymbolRef, &[Atom]) -> R,
 {
     let sym = SymbolRef::from(sym);
     let atoms = unsafe {
@@ -36,7 +142,67 @@ pub fn sel_list<F>(
             ac as _,
         )
     };
-    f(sym, atoms);
+    f(sym, atoms)
+}
+
+/// helper method to convert between max and median calls for `A_GIMMEBACK` methods
+///
+/// # Remarks
+/// * Unsafe because `av` must point to `ac` atoms and `rv` must be null or valid, as Max passes
+/// them to an `A_GIMMEBACK` method.
+pub unsafe fn sel_list_back<F, R>(
+    obj: *mut max_sys::t_object,
+    sym: *mut max_sys::t_symbol,
+    ac: ::std::os::raw::c_long,
+    av: *const ::max_sys::t_atom,
+    rv: *mut ::max_sys::t_atom,
+    f: F,
+) -> max_sys::t_max_err
+where
+    F: Fn(SymbolRef, &[Atom]) -> R,
+    R: TypedResult,
+{
+    //callers that don't want a value can pass null
+    sel_list(sym, ac, av, f).write(obj, rv.as_mut())
 }
 
 include!(concat!(env!("OUT_DIR"), "/method-gen.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MaxError;
+
+    #[test]
+    fn handler_result() {
+        let obj = std::ptr::null_mut();
+        let none = max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_max_err;
+        let generic = max_sys::e_max_errorcodes::MAX_ERR_GENERIC as max_sys::t_max_err;
+        assert_eq!(().report(obj), none);
+        assert_eq!(Ok::<(), String>(()).report(obj), none);
+        assert_eq!(Err::<(), _>(MaxError::OutOfMem).report(obj), generic);
+        assert_eq!(MaxErr(generic).report(obj), generic);
+    }
+
+    #[test]
+    fn typed_result() {
+        let obj = std::ptr::null_mut();
+        let none = max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_max_err;
+        let generic = max_sys::e_max_errorcodes::MAX_ERR_GENERIC as max_sys::t_max_err;
+        let mut rv = Atom::from(0i64);
+        assert_eq!(Atom::from(2.5f64).write(obj, Some(&mut rv.value)), none);
+        assert_eq!(rv.get_float(), 2.5);
+        assert_eq!(
+            Ok::<_, String>(Atom::from(7i64)).write(obj, Some(&mut rv.value)),
+            none
+        );
+        assert_eq!(rv.get_int(), 7);
+        assert_eq!(
+            Err::<Atom, _>("no").write(obj, Some(&mut rv.value)),
+            generic
+        );
+        assert_eq!(rv.get_int(), 7);
+        assert_eq!(().write(obj, None), none);
+        assert_eq!(Atom::from(1i64).write(obj, None), none);
+    }
+}
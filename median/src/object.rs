@@ -1,6 +1,8 @@
 //! Object traits.
 
 use crate::{
+    atom::Atom,
+    class::ClassType,
     error::{MaxError, MaxResult},
     notify::{Attachment, AttachmentError, Registration, RegistrationError, Subscription},
     symbol::SymbolRef,
@@ -58,6 +60,27 @@ macro_rules! impl_obj_methods {
             )
         }
 
+        /// Call a method of this object by name, with `object_method_typed`.
+        ///
+        /// # Arguments
+        /// * `sel` - the name of the method.
+        /// * `args` - the arguments to pass.
+        ///
+        /// # Remarks
+        /// * Works for typed and `A_GIMME` methods, only `A_GIMMEBACK` methods give a value back.
+        fn method_typed(&self, sel: SymbolRef, args: &[Atom]) -> MaxResult<Option<Atom>> {
+            crate::object::method_typed($o(self), sel, args)
+        }
+
+        /// Send a message through this object's dumpout outlet, for instance a reply to `dump`.
+        ///
+        /// # Remarks
+        /// * Does nothing if the object doesn't have a dumpout outlet, see
+        /// [`add_dumpout_outlet`](../builder/trait.ObjBuilder.html#tymethod.add_dumpout_outlet).
+        fn dumpout(&self, sel: SymbolRef, args: &[Atom]) {
+            crate::object::dumpout($o(self), sel, args)
+        }
+
         /// Indicate that an attribute has had a change (outside of its setter).
         ///
         /// # Arguments
@@ -109,6 +132,7 @@ use std::ops::{Deref, DerefMut};
 
 /// Post a message to the Max console, associated with the given object.
 pub fn post<T: Into<Vec<u8>>>(obj: *mut max_sys::t_object, msg: T) {
+    crate::rt::check::note("post");
     unsafe {
         match CString::new(msg) {
             Ok(p) => max_sys::object_post(obj, p.as_ptr()),
@@ -120,6 +144,7 @@ pub fn post<T: Into<Vec<u8>>>(obj: *mut max_sys::t_object, msg: T) {
 
 /// Post an error to the Max console, associated with the given object
 pub fn error<T: Into<Vec<u8>>>(obj: *mut max_sys::t_object, msg: T) {
+    crate::rt::check::note("post");
     unsafe {
         match CString::new(msg) {
             Ok(p) => max_sys::object_error(obj, p.as_ptr()),
@@ -132,6 +157,105 @@ pub fn error<T: Into<Vec<u8>>>(obj: *mut max_sys::t_object, msg: T) {
     }
 }
 
+/// Call a method of `obj` by name, with `object_method_typed`.
+///
+/// See [`MaxObj::method_typed`](trait.MaxObj.html#method.method_typed).
+pub fn method_typed(
+    obj: *mut max_sys::t_object,
+    sel: SymbolRef,
+    args: &[Atom],
+) -> MaxResult<Option<Atom>> {
+    unsafe {
+        //a method that doesn't set the return value leaves it as A_NOTHING
+        let mut rv = Atom::zeroed();
+        MaxError::from(
+            max_sys::object_method_typed(
+                obj as _,
+                sel.inner(),
+                args.len() as _,
+                args.as_ptr() as *mut max_sys::t_atom, //max doesn't mutate
+                &mut rv.value,
+            ) as _,
+            (),
+        )?;
+        Ok(rv.get_type().map(|_| rv))
+    }
+}
+
+/// Send a message through the dumpout outlet of `obj`.
+///
+/// See [`MaxObj::dumpout`](trait.MaxObj.html#method.dumpout).
+pub fn dumpout(obj: *mut max_sys::t_object, sel: SymbolRef, args: &[Atom]) {
+    unsafe {
+        max_sys::object_obex_dumpout(
+            obj as _,
+            sel.inner(),
+            args.len() as _,
+            args.as_ptr() as *mut max_sys::t_atom, //max doesn't mutate
+        );
+    }
+}
+
+/// A Max object whose class isn't known to Rust, for instance one created by name with
+/// [`new_typed`](#method.new_typed).
+///
+/// # Remarks
+/// * This is how you use a `ClassType::NoBox` helper class that is registered by another
+/// external, possibly in another library. The class must have been registered before you create
+/// an instance, for instance by loading the external that defines it.
+/// * Talk to the instance with [`method_typed`](trait.MaxObj.html#method.method_typed), the
+/// helper class can add methods that give back a value with
+/// [`Method::SelGimmeBack`](../method/enum.Method.html#variant.SelGimmeBack) or the `#[gimmeback]`
+/// attribute in `external!`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use median::{atom::Atom, class::ClassType, object::{MaxObj, Object}};
+///
+/// let hub = Object::new_typed(ClassType::NoBox, median::sym!("midi.hub"), &[])
+///     .expect("midi.hub isn't registered");
+/// let count = hub.method_typed(median::sym!("count"), &[Atom::from(1i64)]);
+/// ```
+#[repr(C)]
+pub struct Object {
+    s_obj: max_sys::t_object,
+}
+
+unsafe impl MaxObj for Object {}
+
+impl Object {
+    /// Create an instance of a registered class by name, with `object_new_typed`.
+    ///
+    /// # Arguments
+    /// * `class_type` - the namespace of the class.
+    /// * `name` - the name of the class.
+    /// * `args` - the creation arguments.
+    ///
+    /// # Remarks
+    /// * Returns `None` if the class isn't registered or creation fails.
+    /// * The instance is freed when the returned box is dropped.
+    pub fn new_typed(
+        class_type: ClassType,
+        name: SymbolRef,
+        args: &[Atom],
+    ) -> Option<ObjBox<Self>> {
+        unsafe {
+            let o = max_sys::object_new_typed(
+                class_type.into(),
+                name.inner(),
+                args.len() as _,
+                args.as_ptr() as *mut max_sys::t_atom, //max doesn't mutate
+            );
+            if o.is_null() {
+                None
+            } else {
+                Some(ObjBox::from_raw(o as *mut Self))
+            }
+        }
+    }
+}
+
 /// A smart pointer for an object that max allocated
 pub struct ObjBox<T: MaxObj> {
     pub value: Option<Box<T>>, //option box so that we can drop if the value still exists
@@ -1,24 +1,130 @@
 //! Routines for creating and sending data through outlets.
 
-use crate::atom::Atom;
+use crate::atom::{Atom, SerdeError};
+use crate::dictionary::Dictionary;
 use crate::max::common_symbols;
+use crate::method::MaxMethod;
 use crate::symbol::SymbolRef;
-use std::ffi::c_void;
+use crate::thread::SysThread;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    ffi::c_void,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 /// Result type alias from sending data through an outlet.
-pub type SendResult = Result<(), SendError>;
+pub type SendResult = Result<Sent, SendError>;
 pub type OutBang = Box<dyn SendValue<()> + Sync>;
 pub type OutInt = Box<dyn SendValue<max_sys::t_atom_long> + Sync>;
 pub type OutFloat = Box<dyn SendValue<f64> + Sync>;
 pub type OutList = Box<dyn for<'a> SendValue<&'a [Atom]> + Sync + Send>;
 pub type OutAnything = Box<dyn for<'a> SendAnything<'a> + Sync + Send>;
+pub type OutSymbol = Box<dyn SendValue<SymbolRef> + Sync + Send>;
 
+/// How a value given to an outlet was handled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Sent {
+    /// Sent before `send` returned.
+    Now,
+    /// Queued, it will be sent from the scheduler thread.
+    Later,
+}
+
+/// Why a value given to an outlet was not sent.
+#[derive(Debug)]
 pub enum SendError {
+    /// Max refused to send because its stack is too deep, usually because of a feedback loop.
     StackOverflow,
+    /// The outlet is disabled until the object gets its next message, after a stack overflow with
+    /// [`OverflowPolicy::Disable`].
+    Disabled,
+    /// Sent from a thread that can't call into Max, by a [`ThreadSafeOutlet`] that drops those
+    /// values.
+    WrongThread,
+    /// An [`OutMessage`] value couldn't be converted to atoms.
+    Serialize(SerdeError),
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StackOverflow => write!(f, "stack overflow"),
+            Self::Disabled => write!(f, "outlet disabled after a stack overflow"),
+            Self::WrongThread => write!(f, "sent from a thread that can't call into Max"),
+            Self::Serialize(e) => write!(f, "couldn't convert the value to atoms: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// What a [`ThreadSafeOutlet`] does with values sent from threads other than the main or scheduler
+/// thread.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ThreadPolicy {
+    /// Discard the value, `send` returns `SendError::WrongThread`.
+    Drop,
+    /// Queue every value and send them in order from the scheduler thread.
+    Queue,
+    /// Keep only the latest value and send it from the scheduler thread.
+    Coalesce,
+}
+
+/// What an outlet does when Max reports a stack overflow.
+///
+/// # Remarks
+/// `send` returns `SendError::StackOverflow` for the value that overflowed in every case but
+/// `Defer`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Post an error to the console, only for the first overflow of the outlet.
+    #[default]
+    PostOnce,
+    /// Discard everything sent through the outlet until the object gets its next message, `send`
+    /// returns `SendError::Disabled`.
+    Disable,
+    /// Queue the value and send it from the scheduler thread, `send` returns `Sent::Later`.
+    Defer,
+}
+
+/// Counts of what happened to the values given to an outlet.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OutletStats {
+    /// Values sent, including queued values once they went out.
+    pub sent: usize,
+    /// Values that Max refused because of a stack overflow.
+    pub overflowed: usize,
+    /// Values discarded by a disabled outlet, a [`ThreadPolicy`] or a newer coalesced value.
+    pub dropped: usize,
+    /// Values queued to be sent from the scheduler thread.
+    pub queued: usize,
+}
+
+/// Diagnostics for an outlet.
+pub trait SendStats {
+    /// Get the counts for this outlet, `None` unless the object enabled them with
+    /// [`ObjBuilder::with_outlet_counters`](../builder/trait.ObjBuilder.html#tymethod.with_outlet_counters).
+    fn stats(&self) -> Option<OutletStats> {
+        None
+    }
 }
 
 /// Send data through an outlet.
-pub trait SendValue<T> {
+pub trait SendValue<T>: SendStats {
     fn send(&self, value: T) -> SendResult;
 }
 
@@ -29,12 +135,161 @@ pub trait SendAnything<'a>:
     fn send_anything(&self, selector: SymbolRef, value: &'a [Atom]) -> SendResult;
 }
 
+/// Per object count of the messages it got, used to re-enable outlets after
+/// [`OverflowPolicy::Disable`].
+///
+/// # Remarks
+/// * Only messages sent to the object's inlets and methods are counted, not calls like `assist`
+/// or `notify`.
+/// * A message that arrives on the same thread while the object is handling another, for instance
+/// through a feedback loop, is counted as part of that one.
+#[derive(Default)]
+pub struct MessageCount {
+    count: AtomicUsize,
+}
+
+thread_local! {
+    //the addresses of the message counts of the objects handling a message on this thread
+    static HANDLING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) struct MessageGuard<'a>(&'a MessageCount);
+
+impl MessageCount {
+    //call for every message the object gets, it is being handled until the guard drops
+    pub(crate) fn enter(&self) -> MessageGuard<'_> {
+        let key = self.key();
+        let nested = HANDLING.with(|h| {
+            let mut h = h.borrow_mut();
+            let nested = h.contains(&key);
+            h.push(key);
+            nested
+        });
+        if !nested {
+            self.count.fetch_add(1, Ordering::AcqRel);
+        }
+        MessageGuard(self)
+    }
+
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    fn current(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for MessageGuard<'_> {
+    fn drop(&mut self) {
+        let key = self.0.key();
+        HANDLING.with(|h| {
+            let mut h = h.borrow_mut();
+            if let Some(i) = h.iter().rposition(|k| *k == key) {
+                h.remove(i);
+            }
+        });
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    sent: AtomicUsize,
+    overflowed: AtomicUsize,
+    dropped: AtomicUsize,
+    queued: AtomicUsize,
+}
+
+impl Counters {
+    fn stats(&self) -> OutletStats {
+        OutletStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            overflowed: self.overflowed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+        }
+    }
+}
+
+//count into one of the counters, if the outlet has them
+fn count<F: FnOnce(&Counters) -> &AtomicUsize>(counters: &Option<Box<Counters>>, field: F) {
+    if let Some(c) = counters {
+        field(c).fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// How the builder creates an object's outlets.
+#[derive(Clone, Default)]
+pub(crate) struct OutletConfig {
+    pub thread: Option<ThreadPolicy>,
+    pub overflow: OverflowPolicy,
+    pub counters: bool,
+    pub messages: Arc<MessageCount>,
+}
+
+macro_rules! config_append {
+    ($name:ident, $out:ty, $sym:expr) => {
+        pub fn $name(&self, owner: *mut max_sys::t_object) -> $out {
+            let outlet = self.outlet(owner, $sym);
+            match self.thread_policy() {
+                Some(policy) => Box::new(ThreadSafeOutlet::new(outlet, policy)),
+                None => Box::new(outlet),
+            }
+        }
+    };
+}
+
+impl OutletConfig {
+    fn outlet(&self, owner: *mut max_sys::t_object, type_sym: *mut max_sys::t_symbol) -> Outlet {
+        Outlet::new(
+            unsafe { max_sys::outlet_append(owner, std::ptr::null_mut(), type_sym) },
+            owner,
+            self,
+        )
+    }
+
+    //deferred values are queued, so those outlets are always thread safe
+    fn thread_policy(&self) -> Option<ThreadPolicy> {
+        match (self.thread, self.overflow) {
+            (None, OverflowPolicy::Defer) => Some(ThreadPolicy::Queue),
+            (policy, _) => policy,
+        }
+    }
+
+    config_append!(anything, OutAnything, std::ptr::null_mut());
+    config_append!(bang, OutBang, common_symbols().s_bang);
+    config_append!(int, OutInt, common_symbols().s_long);
+    config_append!(float, OutFloat, common_symbols().s_float);
+    config_append!(list, OutList, common_symbols().s_list);
+    config_append!(symbol, OutSymbol, std::ptr::null_mut());
+
+    pub fn dumpout(&self, owner: *mut max_sys::t_object) -> OutAnything {
+        let outlet = self.outlet(owner, std::ptr::null_mut());
+        outlet.store_dumpout(owner);
+        match self.thread_policy() {
+            Some(policy) => Box::new(ThreadSafeOutlet::new(outlet, policy)),
+            None => Box::new(outlet),
+        }
+    }
+}
+
 /// A safe wrapper for a Max outlet.
 ///
 /// # Remarks
-/// This type is marked as Send and Sync but technically it can only be
+/// * This type is marked as Send and Sync but technically it can only be used from the main or
+/// scheduler thread, use a [`ThreadSafeOutlet`] to send from other threads.
+/// * Outlets created with the `append_*` functions use [`OverflowPolicy::PostOnce`] and have no
+/// counters, the builder's outlets use the object's settings.
 pub struct Outlet {
     inner: *mut c_void,
+    owner: *mut max_sys::t_object,
+    overflow: OverflowPolicy,
+    messages: Arc<MessageCount>,
+    //set once an overflow has been posted
+    posted: AtomicBool,
+    //the message count when the outlet was disabled plus one, 0 while enabled
+    disabled: AtomicUsize,
+    counters: Option<Box<Counters>>,
 }
 
 /// Technically outlets are only Sync in the scheduler or main Max thread.
@@ -42,36 +297,62 @@ unsafe impl Send for Outlet {}
 unsafe impl Sync for Outlet {}
 
 impl Outlet {
-    /// Create an outlet that can send anything Max allows.
-    fn append(owner: *mut max_sys::t_object, type_sym: *mut max_sys::t_symbol) -> Box<Self> {
-        Box::new(Self {
-            inner: unsafe { max_sys::outlet_append(owner, std::ptr::null_mut(), type_sym) },
-        })
+    fn new(inner: *mut c_void, owner: *mut max_sys::t_object, config: &OutletConfig) -> Self {
+        Self {
+            inner,
+            owner,
+            overflow: config.overflow,
+            messages: config.messages.clone(),
+            posted: AtomicBool::new(false),
+            disabled: AtomicUsize::new(0),
+            counters: if config.counters {
+                Some(Box::default())
+            } else {
+                None
+            },
+        }
     }
 
     /// Create an outlet that can send anything Max allows.
     pub fn append_anything(owner: *mut max_sys::t_object) -> OutAnything {
-        Self::append(owner, std::ptr::null_mut())
+        Box::new(OutletConfig::default().outlet(owner, std::ptr::null_mut()))
     }
 
     /// Create an outlet that will only send bangs.
     pub fn append_bang(owner: *mut max_sys::t_object) -> OutBang {
-        Self::append(owner, common_symbols().s_bang)
+        Box::new(OutletConfig::default().outlet(owner, common_symbols().s_bang))
     }
 
     /// Create an outlet that will only send ints.
     pub fn append_int(owner: *mut max_sys::t_object) -> OutInt {
-        Self::append(owner, common_symbols().s_long)
+        Box::new(OutletConfig::default().outlet(owner, common_symbols().s_long))
     }
 
     /// Create an outlet that will only send floats.
     pub fn append_float(owner: *mut max_sys::t_object) -> OutFloat {
-        Self::append(owner, common_symbols().s_float)
+        Box::new(OutletConfig::default().outlet(owner, common_symbols().s_float))
     }
 
     /// Create an outlet that will only send floats.
     pub fn append_list(owner: *mut max_sys::t_object) -> OutList {
-        Self::append(owner, common_symbols().s_list)
+        Box::new(OutletConfig::default().outlet(owner, common_symbols().s_list))
+    }
+
+    /// Create an outlet that sends symbols.
+    pub fn append_symbol(owner: *mut max_sys::t_object) -> OutSymbol {
+        Box::new(OutletConfig::default().outlet(owner, std::ptr::null_mut()))
+    }
+
+    /// Create the object's dumpout outlet, Max sends attribute query replies through it.
+    pub fn append_dumpout(owner: *mut max_sys::t_object) -> OutAnything {
+        OutletConfig::default().dumpout(owner)
+    }
+
+    fn store_dumpout(&self, owner: *mut max_sys::t_object) {
+        unsafe {
+            let _ =
+                max_sys::object_obex_store(owner as _, common_symbols().s_dumpout, self.inner as _);
+        }
     }
 
     /// Add a signal output.
@@ -80,42 +361,79 @@ impl Outlet {
             let _ = max_sys::outlet_append(owner, std::ptr::null_mut(), common_symbols().s_signal);
         }
     }
-}
 
-/// wrap the result, all the outlet methods return 1 for success, null for stack overflow
-fn res_wrap<F: FnOnce() -> *mut c_void>(func: F) -> SendResult {
-    if func().is_null() {
+    /// Get what this outlet does when Max reports a stack overflow.
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow
+    }
+
+    //all the outlet methods return 1 for success, null for stack overflow
+    fn result<F: FnOnce() -> *mut c_void>(&self, func: F) -> SendResult {
+        let disabled = self.disabled.load(Ordering::Acquire);
+        if disabled != 0 {
+            if disabled == self.messages.current() + 1 {
+                count(&self.counters, |c| &c.dropped);
+                return Err(SendError::Disabled);
+            }
+            //the object has had a new message since
+            self.disabled.store(0, Ordering::Release);
+        }
+        if !func().is_null() {
+            count(&self.counters, |c| &c.sent);
+            return Ok(Sent::Now);
+        }
+        count(&self.counters, |c| &c.overflowed);
+        match self.overflow {
+            OverflowPolicy::PostOnce => {
+                if !self.posted.swap(true, Ordering::AcqRel) {
+                    crate::object::error(
+                        self.owner,
+                        "stack overflow in outlet, the value was not sent",
+                    );
+                }
+            }
+            OverflowPolicy::Disable => {
+                self.disabled
+                    .store(self.messages.current() + 1, Ordering::Release);
+            }
+            //the ThreadSafeOutlet that wraps this queues the value
+            OverflowPolicy::Defer => (),
+        }
         Err(SendError::StackOverflow)
-    } else {
-        Ok(())
+    }
+}
+
+impl SendStats for Outlet {
+    fn stats(&self) -> Option<OutletStats> {
+        self.counters.as_ref().map(|c| c.stats())
     }
 }
 
 impl SendValue<()> for Outlet {
     /// Send a bang.
     fn send(&self, _v: ()) -> SendResult {
-        res_wrap(|| unsafe { max_sys::outlet_bang(self.inner) })
+        self.result(|| unsafe { max_sys::outlet_bang(self.inner) })
     }
 }
 
 impl SendValue<f64> for Outlet {
     /// Send a float.
     fn send(&self, v: f64) -> SendResult {
-        res_wrap(|| unsafe { max_sys::outlet_float(self.inner, v) })
+        self.result(|| unsafe { max_sys::outlet_float(self.inner, v) })
     }
 }
 
 impl SendValue<max_sys::t_atom_long> for Outlet {
     /// Send an int.
     fn send(&self, v: max_sys::t_atom_long) -> SendResult {
-        res_wrap(|| unsafe { max_sys::outlet_int(self.inner, v) })
+        self.result(|| unsafe { max_sys::outlet_int(self.inner, v) })
     }
 }
 
 impl SendValue<&[Atom]> for Outlet {
     /// Send a list.
     fn send(&self, list: &[Atom]) -> SendResult {
-        res_wrap(|| unsafe {
+        self.result(|| unsafe {
             max_sys::outlet_list(
                 self.inner,
                 std::ptr::null_mut(),
@@ -127,10 +445,17 @@ impl SendValue<&[Atom]> for Outlet {
     }
 }
 
+impl SendValue<SymbolRef> for Outlet {
+    /// Send a symbol, as `symbol <v>`.
+    fn send(&self, v: SymbolRef) -> SendResult {
+        self.send_anything(common_symbols().s_symbol.into(), &[Atom::from(v)])
+    }
+}
+
 impl<'a> SendAnything<'a> for Outlet {
     /// Send a selector message.
     fn send_anything(&self, selector: SymbolRef, list: &'a [Atom]) -> SendResult {
-        res_wrap(|| unsafe {
+        self.result(|| unsafe {
             max_sys::outlet_anything(
                 self.inner,
                 selector.inner(),
@@ -141,3 +466,593 @@ impl<'a> SendAnything<'a> for Outlet {
         })
     }
 }
+
+/// An outlet that can be sent to from any thread.
+///
+/// # Remarks
+/// * Values sent from the main or scheduler thread are sent right away, values from other threads
+/// are handled according to the [`ThreadPolicy`], queued values are sent by a clock on the
+/// scheduler thread of the owning object, so they keep their timing like values sent from a
+/// `ClockHandle`.
+/// * Values are always sent in the order they were given to the outlet, a value sent from the main
+/// thread waits behind any that are queued.
+/// * Queueing takes a lock, and a list or message is copied, so prefer `Coalesce` or `Drop` when
+/// sending from `perform`.
+/// * With [`OverflowPolicy::Defer`], a value that overflows the stack is queued too.
+pub struct ThreadSafeOutlet {
+    shared: Arc<ThreadSafeShared>,
+    clock: FlushClock,
+}
+
+struct ThreadSafeShared {
+    outlet: Outlet,
+    policy: ThreadPolicy,
+    state: Mutex<ThreadSafeState>,
+}
+
+struct ThreadSafeState {
+    queue: VecDeque<Message>,
+    //a queued message is being sent, so new ones have to wait behind it
+    sending: bool,
+}
+
+//atoms hold numbers, symbols or object pointers, which are only dereferenced when they are sent
+unsafe impl Send for ThreadSafeState {}
+
+//an owned copy of a value, to be sent later
+enum Message {
+    Bang,
+    Int(max_sys::t_atom_long),
+    Float(f64),
+    List(Vec<Atom>),
+    Anything(SymbolRef, Vec<Atom>),
+}
+
+impl ThreadSafeOutlet {
+    fn append(
+        owner: *mut max_sys::t_object,
+        type_sym: *mut max_sys::t_symbol,
+        policy: ThreadPolicy,
+    ) -> Box<Self> {
+        Box::new(Self::new(
+            OutletConfig::default().outlet(owner, type_sym),
+            policy,
+        ))
+    }
+
+    fn new(outlet: Outlet, policy: ThreadPolicy) -> Self {
+        let shared = Arc::new(ThreadSafeShared {
+            outlet,
+            policy,
+            state: Mutex::new(ThreadSafeState {
+                queue: VecDeque::new(),
+                sending: false,
+            }),
+        });
+        Self {
+            clock: FlushClock::new(shared.clone()),
+            shared,
+        }
+    }
+
+    /// Create an outlet that can send anything Max allows.
+    pub fn append_anything(owner: *mut max_sys::t_object, policy: ThreadPolicy) -> OutAnything {
+        Self::append(owner, std::ptr::null_mut(), policy)
+    }
+
+    /// Create an outlet that will only send bangs.
+    pub fn append_bang(owner: *mut max_sys::t_object, policy: ThreadPolicy) -> OutBang {
+        Self::append(owner, common_symbols().s_bang, policy)
+    }
+
+    /// Create an outlet that will only send ints.
+    pub fn append_int(owner: *mut max_sys::t_object, policy: ThreadPolicy) -> OutInt {
+        Self::append(owner, common_symbols().s_long, policy)
+    }
+
+    /// Create an outlet that will only send floats.
+    pub fn append_float(owner: *mut max_sys::t_object, policy: ThreadPolicy) -> OutFloat {
+        Self::append(owner, common_symbols().s_float, policy)
+    }
+
+    /// Create an outlet that will only send lists.
+    pub fn append_list(owner: *mut max_sys::t_object, policy: ThreadPolicy) -> OutList {
+        Self::append(owner, common_symbols().s_list, policy)
+    }
+
+    /// Create an outlet that sends symbols.
+    pub fn append_symbol(owner: *mut max_sys::t_object, policy: ThreadPolicy) -> OutSymbol {
+        Self::append(owner, std::ptr::null_mut(), policy)
+    }
+
+    /// Create the object's dumpout outlet, Max sends attribute query replies through it.
+    pub fn append_dumpout(owner: *mut max_sys::t_object, policy: ThreadPolicy) -> OutAnything {
+        let outlet = Self::append(owner, std::ptr::null_mut(), policy);
+        outlet.shared.outlet.store_dumpout(owner);
+        outlet
+    }
+
+    /// Get the policy for values sent from other threads.
+    pub fn policy(&self) -> ThreadPolicy {
+        self.shared.policy
+    }
+
+    //send right away if we can, otherwise queue an owned copy of the value
+    fn send_or_queue<S, M>(&self, send: S, message: M) -> SendResult
+    where
+        S: FnOnce(&Outlet) -> SendResult,
+        M: FnOnce() -> Message,
+    {
+        let outlet = &self.shared.outlet;
+        if SysThread::is_main() || SysThread::is_scheduler() {
+            let idle = {
+                let state = self.shared.state.lock();
+                state.queue.is_empty() && !state.sending
+            };
+            if idle {
+                return match send(outlet) {
+                    Err(SendError::StackOverflow) if outlet.overflow == OverflowPolicy::Defer => {
+                        self.queue(message())
+                    }
+                    res => res,
+                };
+            }
+        } else if self.shared.policy == ThreadPolicy::Drop {
+            count(&outlet.counters, |c| &c.dropped);
+            return Err(SendError::WrongThread);
+        } else {
+            crate::rt::check::note("lock");
+        }
+        self.queue(message())
+    }
+
+    fn queue(&self, message: Message) -> SendResult {
+        let counters = &self.shared.outlet.counters;
+        {
+            let mut state = self.shared.state.lock();
+            if self.shared.policy == ThreadPolicy::Coalesce {
+                if let Some(c) = counters {
+                    c.dropped.fetch_add(state.queue.len(), Ordering::Relaxed);
+                }
+                state.queue.clear();
+            }
+            state.queue.push_back(message);
+        }
+        count(counters, |c| &c.queued);
+        self.clock.set();
+        Ok(Sent::Later)
+    }
+}
+
+//sends the queued messages on the scheduler thread, clock_delay can be called from any thread
+struct FlushClock {
+    inner: *mut max_sys::t_clock,
+    //the clock holds a pointer to it
+    _shared: Arc<ThreadSafeShared>,
+}
+
+unsafe impl Send for FlushClock {}
+unsafe impl Sync for FlushClock {}
+
+impl FlushClock {
+    fn new(shared: Arc<ThreadSafeShared>) -> Self {
+        unsafe {
+            let inner = max_sys::clock_new(
+                Arc::as_ptr(&shared) as _,
+                Some(std::mem::transmute::<
+                    extern "C" fn(&ThreadSafeShared),
+                    MaxMethod,
+                >(Self::flush_tramp)),
+            );
+            crate::clock::attach(inner, shared.outlet.owner);
+            Self {
+                inner,
+                _shared: shared,
+            }
+        }
+    }
+
+    //setting a set clock just reschedules it, so queued messages are flushed by one call
+    fn set(&self) {
+        unsafe {
+            max_sys::clock_delay(self.inner, 0);
+        }
+    }
+
+    extern "C" fn flush_tramp(shared: &ThreadSafeShared) {
+        let owner = shared.outlet.owner;
+        crate::logging::with_object(owner, || {
+            crate::panic::catch(owner, "outlet", || shared.flush())
+        });
+    }
+}
+
+impl Drop for FlushClock {
+    fn drop(&mut self) {
+        unsafe {
+            max_sys::clock_unset(self.inner);
+            max_sys::object_free(self.inner);
+        }
+    }
+}
+
+impl ThreadSafeShared {
+    //send the queued messages, on the main or scheduler thread
+    fn flush(&self) {
+        //only send what was queued when the flush started. values sent while we're sending are
+        //queued behind these and set the clock again, so feedback in the patch is sent a tick at
+        //a time instead of spinning here
+        let messages = {
+            let mut state = self.state.lock();
+            state.sending = true;
+            std::mem::take(&mut state.queue)
+        };
+        for message in messages {
+            //the sender has already returned, so there is nobody to give an error to, the outlet
+            //has posted or counted it
+            let _ = match message {
+                Message::Bang => self.outlet.send(()),
+                Message::Int(v) => self.outlet.send(v),
+                Message::Float(v) => self.outlet.send(v),
+                Message::List(l) => self.outlet.send(l.as_slice()),
+                Message::Anything(sel, l) => self.outlet.send_anything(sel, l.as_slice()),
+            };
+        }
+        self.state.lock().sending = false;
+    }
+}
+
+impl SendStats for ThreadSafeOutlet {
+    fn stats(&self) -> Option<OutletStats> {
+        self.shared.outlet.stats()
+    }
+}
+
+impl SendValue<()> for ThreadSafeOutlet {
+    /// Send a bang.
+    fn send(&self, _v: ()) -> SendResult {
+        self.send_or_queue(|o| o.send(()), || Message::Bang)
+    }
+}
+
+impl SendValue<f64> for ThreadSafeOutlet {
+    /// Send a float.
+    fn send(&self, v: f64) -> SendResult {
+        self.send_or_queue(|o| o.send(v), || Message::Float(v))
+    }
+}
+
+impl SendValue<max_sys::t_atom_long> for ThreadSafeOutlet {
+    /// Send an int.
+    fn send(&self, v: max_sys::t_atom_long) -> SendResult {
+        self.send_or_queue(|o| o.send(v), || Message::Int(v))
+    }
+}
+
+impl SendValue<&[Atom]> for ThreadSafeOutlet {
+    /// Send a list.
+    fn send(&self, list: &[Atom]) -> SendResult {
+        self.send_or_queue(|o| o.send(list), || Message::List(list.to_vec()))
+    }
+}
+
+impl SendValue<SymbolRef> for ThreadSafeOutlet {
+    /// Send a symbol, as `symbol <v>`.
+    fn send(&self, v: SymbolRef) -> SendResult {
+        self.send_anything(common_symbols().s_symbol.into(), &[Atom::from(v)])
+    }
+}
+
+impl<'a> SendAnything<'a> for ThreadSafeOutlet {
+    /// Send a selector message.
+    fn send_anything(&self, selector: SymbolRef, list: &'a [Atom]) -> SendResult {
+        let sel = selector.clone();
+        self.send_or_queue(
+            |o| o.send_anything(selector, list),
+            || Message::Anything(sel, list.to_vec()),
+        )
+    }
+}
+
+/// An outlet that sends a fixed selector followed by a value converted to atoms, for instance
+/// `pos x y` from an `OutMessage<(f64, f64)>`.
+///
+/// # Remarks
+/// * The value is converted with [`to_atoms`](../atom/fn.to_atoms.html).
+pub struct OutMessage<T> {
+    outlet: OutAnything,
+    selector: SymbolRef,
+    _phantom: PhantomData<fn(&T)>,
+}
+
+impl<T: Serialize> OutMessage<T> {
+    /// Send messages with `selector` through `outlet`.
+    pub fn new(selector: SymbolRef, outlet: OutAnything) -> Self {
+        Self {
+            outlet,
+            selector,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the selector that messages are sent with.
+    pub fn selector(&self) -> SymbolRef {
+        self.selector.clone()
+    }
+}
+
+impl<T> SendStats for OutMessage<T> {
+    fn stats(&self) -> Option<OutletStats> {
+        self.outlet.stats()
+    }
+}
+
+impl<T: Serialize> SendValue<&T> for OutMessage<T> {
+    /// Send the selector and the value.
+    fn send(&self, value: &T) -> SendResult {
+        let atoms = crate::atom::to_atoms(value).map_err(SendError::Serialize)?;
+        self.outlet.send_anything(self.selector.clone(), &atoms)
+    }
+}
+
+/// An outlet that sends `dictionary <name>`.
+pub struct OutDictionary {
+    outlet: OutAnything,
+}
+
+impl OutDictionary {
+    /// Send dictionaries through `outlet`.
+    pub fn new(outlet: OutAnything) -> Self {
+        Self { outlet }
+    }
+
+    /// Send `dictionary <name>` for a dictionary that is already registered.
+    pub fn send_name(&self, name: SymbolRef) -> SendResult {
+        self.outlet
+            .send_anything(common_symbols().s_dictionary.into(), &[name.into()])
+    }
+}
+
+impl SendStats for OutDictionary {
+    fn stats(&self) -> Option<OutletStats> {
+        self.outlet.stats()
+    }
+}
+
+impl SendValue<&mut Dictionary> for OutDictionary {
+    /// Send a dictionary, registering it if needed.
+    fn send(&self, dict: &mut Dictionary) -> SendResult {
+        dict.send(&*self.outlet)
+    }
+}
+
+/// An outlet that sends `jit_matrix <name>`.
+pub struct OutJitMatrix {
+    outlet: OutAnything,
+}
+
+impl OutJitMatrix {
+    /// Send matrices through `outlet`.
+    pub fn new(outlet: OutAnything) -> Self {
+        Self { outlet }
+    }
+}
+
+impl SendStats for OutJitMatrix {
+    fn stats(&self) -> Option<OutletStats> {
+        self.outlet.stats()
+    }
+}
+
+impl SendValue<SymbolRef> for OutJitMatrix {
+    /// Send `jit_matrix <name>` for the matrix registered with `name`.
+    fn send(&self, name: SymbolRef) -> SendResult {
+        self.outlet
+            .send_anything(common_symbols().s_jit_matrix.into(), &[name.into()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{
+        clock::{clock_unset, is_set},
+        outlet::{set_on_send, set_overflow, take_sent},
+        post::{set_main_thread, take_posted},
+    };
+
+    fn raw(config: &OutletConfig) -> Outlet {
+        Outlet::new(1usize as _, std::ptr::null_mut(), config)
+    }
+
+    fn outlet(policy: ThreadPolicy) -> ThreadSafeOutlet {
+        ThreadSafeOutlet::new(raw(&OutletConfig::default()), policy)
+    }
+
+    #[test]
+    fn thread_safe() {
+        take_sent();
+        let o = outlet(ThreadPolicy::Queue);
+        assert!(o.send(1 as max_sys::t_atom_long).is_ok());
+        set_main_thread(false);
+        assert!(o.send(2 as max_sys::t_atom_long).is_ok());
+        assert!(o
+            .send_anything(crate::sym!("foo"), &[Atom::from(1)])
+            .is_ok());
+        set_main_thread(true);
+        //waits behind the queued values
+        assert!(o.send(0.5).is_ok());
+        assert_eq!(take_sent(), vec!["int 1".to_string()]);
+        //the clock sends them
+        assert!(unsafe { is_set(o.clock.inner) });
+        o.shared.flush();
+        assert_eq!(
+            take_sent(),
+            vec![
+                "int 2".to_string(),
+                "foo 1".to_string(),
+                "float 0.5".to_string()
+            ]
+        );
+        assert!(o.send(()).is_ok());
+        assert_eq!(take_sent(), vec!["bang".to_string()]);
+
+        let o = outlet(ThreadPolicy::Coalesce);
+        set_main_thread(false);
+        for i in 0..3 {
+            assert!(o.send(i as max_sys::t_atom_long).is_ok());
+        }
+        set_main_thread(true);
+        o.shared.flush();
+        assert_eq!(take_sent(), vec!["int 2".to_string()]);
+
+        let o = outlet(ThreadPolicy::Drop);
+        set_main_thread(false);
+        assert!(matches!(
+            o.send(1 as max_sys::t_atom_long),
+            Err(SendError::WrongThread)
+        ));
+        set_main_thread(true);
+        o.shared.flush();
+        assert!(take_sent().is_empty());
+    }
+
+    #[test]
+    fn feedback() {
+        take_sent();
+        let o = std::rc::Rc::new(outlet(ThreadPolicy::Queue));
+        set_main_thread(false);
+        assert!(o.send(1 as max_sys::t_atom_long).is_ok());
+        assert!(o.send(2 as max_sys::t_atom_long).is_ok());
+        set_main_thread(true);
+
+        //the patch sends back into the outlet, forever
+        let looped = o.clone();
+        set_on_send(Some(Box::new(move || {
+            assert!(matches!(looped.send(()), Ok(Sent::Later)));
+        })));
+        unsafe { clock_unset(o.clock.inner as _) };
+        //only the values queued before the flush are sent, the rest wait for the next tick
+        o.shared.flush();
+        assert_eq!(take_sent(), vec!["int 1".to_string(), "int 2".to_string()]);
+        assert!(unsafe { is_set(o.clock.inner) });
+        o.shared.flush();
+        assert_eq!(take_sent(), vec!["bang".to_string(), "bang".to_string()]);
+        set_on_send(None);
+    }
+
+    #[test]
+    fn message_count() {
+        let messages = MessageCount::default();
+        let message = messages.enter();
+        assert_eq!(messages.current(), 1);
+        {
+            let _nested = messages.enter();
+            assert_eq!(messages.current(), 1);
+        }
+        //a message on another thread isn't part of this one
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let _message = messages.enter();
+            });
+        });
+        assert_eq!(messages.current(), 2);
+        drop(message);
+        let _message = messages.enter();
+        assert_eq!(messages.current(), 3);
+    }
+
+    #[test]
+    fn typed() {
+        take_sent();
+        let out = || -> OutAnything { Box::new(raw(&OutletConfig::default())) };
+        let pos: OutMessage<(f64, f64)> = OutMessage::new(crate::sym!("pos"), out());
+        assert!(pos.send(&(0.5, 1.0)).is_ok());
+        assert!(OutJitMatrix::new(out()).send(crate::sym!("u123")).is_ok());
+        assert!(OutDictionary::new(out())
+            .send_name(crate::sym!("d"))
+            .is_ok());
+        let sym: OutSymbol = Box::new(raw(&OutletConfig::default()));
+        assert!(sym.send(crate::sym!("foo")).is_ok());
+        assert!(outlet(ThreadPolicy::Queue).send(crate::sym!("bar")).is_ok());
+        assert_eq!(
+            take_sent(),
+            vec![
+                "pos 2".to_string(),
+                "jit_matrix 1".to_string(),
+                "dictionary 1".to_string(),
+                "symbol 1".to_string(),
+                "symbol 1".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        take_sent();
+        take_posted();
+        let config = OutletConfig {
+            counters: true,
+            ..Default::default()
+        };
+        let o = raw(&config);
+        set_overflow(true);
+        assert!(matches!(o.send(()), Err(SendError::StackOverflow)));
+        assert!(matches!(o.send(()), Err(SendError::StackOverflow)));
+        set_overflow(false);
+        assert!(matches!(o.send(()), Ok(Sent::Now)));
+        assert_eq!(take_posted().len(), 1);
+        assert_eq!(
+            o.stats(),
+            Some(OutletStats {
+                sent: 1,
+                overflowed: 2,
+                ..Default::default()
+            })
+        );
+
+        let config = OutletConfig {
+            overflow: OverflowPolicy::Disable,
+            ..Default::default()
+        };
+        let o = raw(&config);
+        let message = config.messages.enter();
+        set_overflow(true);
+        assert!(matches!(o.send(()), Err(SendError::StackOverflow)));
+        set_overflow(false);
+        assert!(matches!(o.send(()), Err(SendError::Disabled)));
+        //still the same message
+        {
+            let _nested = config.messages.enter();
+            assert!(matches!(o.send(()), Err(SendError::Disabled)));
+        }
+        drop(message);
+        let _message = config.messages.enter();
+        assert!(matches!(o.send(()), Ok(Sent::Now)));
+        assert!(o.stats().is_none());
+        assert!(take_posted().is_empty());
+
+        let config = OutletConfig {
+            overflow: OverflowPolicy::Defer,
+            counters: true,
+            ..Default::default()
+        };
+        let o = ThreadSafeOutlet::new(raw(&config), config.thread_policy().unwrap());
+        take_sent();
+        set_overflow(true);
+        assert!(matches!(o.send(1 as max_sys::t_atom_long), Ok(Sent::Later)));
+        set_overflow(false);
+        assert!(take_sent().is_empty());
+        o.shared.flush();
+        assert_eq!(take_sent(), vec!["int 1".to_string()]);
+        assert_eq!(
+            o.stats(),
+            Some(OutletStats {
+                sent: 1,
+                overflowed: 1,
+                queued: 1,
+                ..Default::default()
+            })
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Containing panics so that they don't unwind into Max.
+//!
+//! Every trampoline that Max calls into catches panics from your code, reports them with
+//! `object_error` for the owning object and then applies the object's
+//! [`PanicPolicy`](enum.PanicPolicy.html).
+
+use std::{
+    any::Any,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// What to do with an object after one of its methods panics.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Report the panic and keep going, later calls are handled as usual.
+    #[default]
+    Continue,
+    /// Report the panic and ignore all later calls, MSP objects output silence.
+    Disable,
+    /// Report the panic and stop calling `perform`, MSP objects output silence but keep handling
+    /// messages. Max objects treat this like `Continue`.
+    Silence,
+}
+
+/// Per object panic state.
+#[derive(Default)]
+pub struct PanicState {
+    disabled: AtomicBool,
+    silenced: AtomicBool,
+    perform_reported: AtomicBool,
+}
+
+impl PanicState {
+    /// Has a panic disabled the object.
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::Relaxed)
+    }
+
+    /// Has a panic silenced the object's signal outputs.
+    pub fn is_silenced(&self) -> bool {
+        self.disabled.load(Ordering::Relaxed) || self.silenced.load(Ordering::Relaxed)
+    }
+
+    /// Call `func`, catching and reporting any panic and applying `policy` if one happens.
+    ///
+    /// # Arguments
+    /// * `obj` - the object to report the panic for.
+    /// * `policy` - the policy to apply if `func` panics.
+    /// * `what` - the name of the method being called, used in the report.
+    /// * `func` - the code to run.
+    ///
+    /// # Remarks
+    /// * Returns `None` without calling `func` if the object has been disabled.
+    pub fn call<R, F: FnOnce() -> R>(
+        &self,
+        obj: *mut max_sys::t_object,
+        policy: PanicPolicy,
+        what: &str,
+        func: F,
+    ) -> Option<R> {
+        if self.is_disabled() {
+            return None;
+        }
+        match catch_unwind(AssertUnwindSafe(func)) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                report(obj, what, e.as_ref());
+                self.apply(obj, policy);
+                None
+            }
+        }
+    }
+
+    /// Call a `perform` method, catching any panic.
+    ///
+    /// # Remarks
+    /// * Only the first panic from `perform` is reported, to avoid flooding the console from the
+    /// audio thread.
+    /// * Returns `false` if `func` wasn't called or panicked, the caller should output silence.
+    pub fn call_perform<F: FnOnce()>(
+        &self,
+        obj: *mut max_sys::t_object,
+        policy: PanicPolicy,
+        func: F,
+    ) -> bool {
+        if self.is_silenced() {
+            return false;
+        }
+        match catch_unwind(AssertUnwindSafe(func)) {
+            Ok(()) => true,
+            Err(e) => {
+                if !self.perform_reported.swap(true, Ordering::Relaxed) {
+                    report(obj, "perform", e.as_ref());
+                    self.apply(obj, policy);
+                } else if policy != PanicPolicy::Continue {
+                    self.apply(obj, policy);
+                }
+                false
+            }
+        }
+    }
+
+    fn apply(&self, obj: *mut max_sys::t_object, policy: PanicPolicy) {
+        match policy {
+            PanicPolicy::Continue => (),
+            PanicPolicy::Disable => {
+                if !self.disabled.swap(true, Ordering::Relaxed) {
+                    crate::object::error(obj, "object disabled after panic");
+                }
+            }
+            PanicPolicy::Silence => {
+                self.silenced.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Call `func`, catching and reporting any panic, for code that isn't associated with a wrapped
+/// object's state.
+///
+/// # Arguments
+/// * `obj` - the object to report the panic for, if null the report isn't associated with an
+/// object.
+/// * `what` - a name for the code being called, used in the report.
+/// * `func` - the code to run.
+pub fn catch<R, F: FnOnce() -> R>(obj: *mut max_sys::t_object, what: &str, func: F) -> Option<R> {
+    match catch_unwind(AssertUnwindSafe(func)) {
+        Ok(r) => Some(r),
+        Err(e) => {
+            report(obj, what, e.as_ref());
+            None
+        }
+    }
+}
+
+/// Get the message from a panic payload.
+pub fn message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Report a panic to the Max console.
+pub fn report(obj: *mut max_sys::t_object, what: &str, payload: &(dyn Any + Send)) {
+    let msg = format!("panic in {}: {}", what, message(payload));
+    if obj.is_null() {
+        crate::error(msg);
+    } else {
+        crate::object::error(obj, msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_message() {
+        let e = catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(message(e.as_ref()), "static");
+        let e = catch_unwind(|| panic!("formatted {}", 2)).unwrap_err();
+        assert_eq!(message(e.as_ref()), "formatted 2");
+        let e = catch_unwind(|| std::panic::panic_any(2)).unwrap_err();
+        assert_eq!(message(e.as_ref()), "unknown panic");
+
+        let state = PanicState::default();
+        assert_eq!(
+            state.call(std::ptr::null_mut(), PanicPolicy::Disable, "test", || 2),
+            Some(2)
+        );
+        assert!(!state.is_disabled());
+        assert!(state.call_perform(std::ptr::null_mut(), PanicPolicy::Silence, || ()));
+        assert!(!state.is_silenced());
+    }
+
+    #[test]
+    fn policy() {
+        let obj = std::ptr::null_mut();
+        let state = PanicState::default();
+        assert_eq!(
+            state.call(obj, PanicPolicy::Continue, "test", || panic!("continue")),
+            None::<()>
+        );
+        assert!(!state.is_disabled());
+        assert!(!state.call_perform(obj, PanicPolicy::Silence, || panic!("silence")));
+        assert!(state.is_silenced());
+        assert!(!state.is_disabled());
+        //messages are still handled, perform isn't
+        assert_eq!(state.call(obj, PanicPolicy::Silence, "test", || 2), Some(2));
+        assert!(!state.call_perform(obj, PanicPolicy::Silence, || ()));
+
+        let state = PanicState::default();
+        assert_eq!(
+            state.call(obj, PanicPolicy::Disable, "test", || panic!("disable")),
+            None::<()>
+        );
+        assert!(state.is_disabled());
+        assert!(state.is_silenced());
+        assert_eq!(state.call(obj, PanicPolicy::Disable, "test", || 2), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! Support for `pattr` and `preset`.
+//!
+//! # Remarks
+//! * A wrapped object takes part in `pattr` and `pattrstorage` by implementing
+//! [`getvalueof`](../wrapper/trait.ObjWrapped.html#method.getvalueof) and
+//! [`setvalueof`](../wrapper/trait.ObjWrapped.html#method.setvalueof), and in `preset` by also
+//! implementing [`preset`](../wrapper/trait.ObjWrapped.html#method.preset).
+//! * Recalled values are always given to `setvalueof` on the main thread.
+//! * Attribute changes notify `pattr` automatically, use a [`Notifier`](struct.Notifier.html) to
+//! notify it when other state changes.
+
+use crate::qelem::Qelem;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Weak,
+};
+
+/// Broadcast `modified` from `obj` with `object_notify`, so any attached `pattr` fetches its value.
+///
+/// # Remarks
+/// * Should be called on the main thread, see [`Notifier`](struct.Notifier.html) for other threads.
+pub fn notify_modified(obj: *mut max_sys::t_object) {
+    unsafe {
+        max_sys::object_notify(
+            obj as _,
+            crate::sym!("modified").inner(),
+            std::ptr::null_mut(),
+        );
+    }
+}
+
+//the object pointer is only used on the main thread, by the qelem, and only while the object is
+//alive. a notifier can keep the qelem around after the object is freed, so the flag is cleared
+//on the main thread when the object is freed
+struct Owner {
+    obj: *mut max_sys::t_object,
+    alive: AtomicBool,
+}
+
+unsafe impl Send for Owner {}
+unsafe impl Sync for Owner {}
+
+impl Owner {
+    fn notify(&self) {
+        if self.alive.load(Ordering::Acquire) {
+            notify_modified(self.obj);
+        }
+    }
+}
+
+/// The `modified` notification for a wrapped object, owned by the wrapper.
+pub struct Modified {
+    qelem: Arc<Qelem>,
+    owner: Arc<Owner>,
+}
+
+impl Modified {
+    pub(crate) fn new(obj: *mut max_sys::t_object) -> Self {
+        let owner = Arc::new(Owner {
+            obj,
+            alive: AtomicBool::new(true),
+        });
+        let o = owner.clone();
+        Self {
+            qelem: Arc::new(Qelem::new(move || o.notify())),
+            owner,
+        }
+    }
+
+    /// Notify `pattr` on the main thread.
+    pub fn notify(&self) {
+        self.qelem.set();
+    }
+
+    pub(crate) fn notifier(&self) -> Notifier {
+        Notifier {
+            qelem: Arc::downgrade(&self.qelem),
+        }
+    }
+
+    //stop notifying, called on the main thread when the object is freed
+    pub(crate) fn free(&self) {
+        self.owner.alive.store(false, Ordering::Release);
+        self.qelem.unset();
+    }
+}
+
+impl Drop for Modified {
+    fn drop(&mut self) {
+        self.free();
+    }
+}
+
+/// Tells `pattr` that the value of an object has changed, from any thread.
+///
+/// # Remarks
+/// * The notification is sent on the main thread, several notifications before it is sent are
+/// coalesced into one.
+/// * Does nothing once the object has been freed.
+///
+/// # Examples
+/// ```ignore
+/// fn new(builder: &mut dyn MaxWrappedBuilder<Self>) -> Self {
+///     Self {
+///         notifier: builder.pattr_notifier(),
+///         ...
+///     }
+/// }
+///
+/// fn set_steps(&self, steps: Vec<t_atom_long>) {
+///     *self.steps.lock() = steps;
+///     self.notifier.notify();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Notifier {
+    qelem: Weak<Qelem>,
+}
+
+impl Notifier {
+    /// Notify `pattr` that the value has changed.
+    pub fn notify(&self) {
+        if let Some(q) = self.qelem.upgrade() {
+            q.set();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifier() {
+        let modified = Modified::new(std::ptr::null_mut());
+        let notifier = modified.notifier();
+        notifier.clone().notify();
+        notifier.notify();
+        unsafe { crate::test::qelem::run() };
+        //coalesced into one notification
+        assert_eq!(
+            crate::test::object::take_notified(),
+            vec!["modified".to_string()]
+        );
+
+        //a notifier on another thread can hold the qelem while the object is freed
+        let held = notifier.qelem.upgrade().unwrap();
+        drop(modified);
+        held.set();
+        unsafe { crate::test::qelem::run() };
+        drop(held);
+        //the qelem is gone, so this is a no op
+        notifier.notify();
+        unsafe { crate::test::qelem::run() };
+        assert!(crate::test::object::take_notified().is_empty());
+    }
+}
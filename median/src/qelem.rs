@@ -0,0 +1,75 @@
+//! Qelems - run code later on Max's main thread.
+
+use std::ffi::c_void;
+
+type QelemFn = Box<dyn Fn() + Send + Sync>;
+
+/// A queue element, calls a function on the main thread after it is set.
+///
+/// # Remarks
+/// * Setting an already set qelem doesn't queue a second call, so a qelem is a cheap way to
+/// coalesce many requests from other threads into a single call on the main thread.
+pub struct Qelem {
+    inner: *mut max_sys::t_qelem,
+    //boxed twice so the qelem can hold a thin pointer to it
+    _func: Box<QelemFn>,
+}
+
+unsafe impl Send for Qelem {}
+unsafe impl Sync for Qelem {}
+
+impl Qelem {
+    /// Create a new qelem that calls `func` on the main thread when it is set.
+    ///
+    /// # Remarks
+    /// * A panic in `func` is reported to the Max console.
+    pub fn new<F>(func: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let func: Box<QelemFn> = Box::new(Box::new(func));
+        let inner = unsafe {
+            max_sys::qelem_new(
+                &*func as *const QelemFn as *mut c_void,
+                Some(std::mem::transmute::<
+                    extern "C" fn(&QelemFn),
+                    unsafe extern "C" fn(*mut c_void) -> *mut c_void,
+                >(Self::call_tramp)),
+            )
+        };
+        Self { inner, _func: func }
+    }
+
+    /// Schedule the function to be called on the main thread, at low priority.
+    pub fn set(&self) {
+        unsafe {
+            max_sys::qelem_set(self.inner);
+        }
+    }
+
+    /// Schedule the function to be called on the main thread, ahead of other low priority events.
+    pub fn front(&self) {
+        unsafe {
+            max_sys::qelem_front(self.inner);
+        }
+    }
+
+    /// Cancel a scheduled call, if it hasn't happened yet.
+    pub fn unset(&self) {
+        unsafe {
+            max_sys::qelem_unset(self.inner);
+        }
+    }
+
+    extern "C" fn call_tramp(func: &QelemFn) {
+        crate::panic::catch(std::ptr::null_mut(), "qelem", func);
+    }
+}
+
+impl Drop for Qelem {
+    fn drop(&mut self) {
+        unsafe {
+            max_sys::qelem_free(self.inner);
+        }
+    }
+}
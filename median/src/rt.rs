@@ -0,0 +1,252 @@
+//! Real-time safe posting to the Max console.
+//!
+//! [`rt_post!`](../macro.rt_post.html) and [`rt_error!`](../macro.rt_error.html) can be used from
+//! `perform` and other audio thread code. They format into a fixed size buffer on the stack and
+//! push it onto a preallocated lock-free queue, a qelem then writes the queue to the console on the
+//! main thread.
+//!
+//! * Messages longer than [`RT_MESSAGE_LEN`](constant.RT_MESSAGE_LEN.html) bytes are truncated.
+//! * Each call site has at most one message waiting to be written, repeats before it is written
+//! are counted and the count is added to the message.
+//! * If the queue is full the message is dropped, and the number of dropped messages is posted
+//! with the next flush.
+
+pub mod check;
+
+use crate::{logging::ConsoleLevel, qelem::Qelem};
+use crossbeam_queue::ArrayQueue;
+use std::{
+    ffi::CStr,
+    fmt::{self, Write},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// The maximum length, in bytes, of a real-time message.
+pub const RT_MESSAGE_LEN: usize = 256;
+
+/// The number of real-time messages that can be waiting to be written.
+pub const RT_QUEUE_LEN: usize = 64;
+
+/// A fixed capacity, nul terminated, message buffer.
+pub struct RtMessage {
+    len: usize,
+    buf: [u8; RT_MESSAGE_LEN + 1],
+}
+
+/// The state for one `rt_post!` call site.
+pub struct Site {
+    pending: AtomicBool,
+    suppressed: AtomicUsize,
+}
+
+struct Entry {
+    level: ConsoleLevel,
+    obj: usize,
+    site: &'static Site,
+    msg: RtMessage,
+}
+
+struct RtConsole {
+    queue: ArrayQueue<Entry>,
+    dropped: AtomicUsize,
+    qelem: Qelem,
+}
+
+lazy_static::lazy_static! {
+    static ref RT_CONSOLE: RtConsole = RtConsole {
+        queue: ArrayQueue::new(RT_QUEUE_LEN),
+        dropped: AtomicUsize::new(0),
+        qelem: Qelem::new(flush),
+    };
+}
+
+//so that flush doesn't create the console just to find it empty
+static RT_CONSOLE_CREATED: AtomicBool = AtomicBool::new(false);
+
+/// Create the queue and qelem used by `rt_post!`.
+///
+/// # Remarks
+/// * Registering a wrapped class calls this, so you only need it if you post from code that runs
+/// before that.
+/// * Otherwise the first `rt_post!` creates them, which allocates.
+pub fn init() {
+    console();
+}
+
+/// Write any queued real-time messages to the console, called on the main thread.
+pub fn flush() {
+    if !RT_CONSOLE_CREATED.load(Ordering::Acquire) {
+        return;
+    }
+    let console = &*RT_CONSOLE;
+    while let Some(mut e) = console.queue.pop() {
+        //allow the site to post again
+        e.site.pending.store(false, Ordering::Release);
+        let suppressed = e.site.suppressed.swap(0, Ordering::Relaxed);
+        if suppressed > 0 {
+            let _ = write!(e.msg, " ({} repeats suppressed)", suppressed);
+        }
+        crate::logging::write_cstr(e.level, e.obj as _, e.msg.as_cstr());
+    }
+    let dropped = console.dropped.swap(0, Ordering::Relaxed);
+    if dropped > 0 {
+        crate::logging::console_object(
+            ConsoleLevel::Warn,
+            std::ptr::null_mut(),
+            format!("rt_post: {} messages dropped, queue full", dropped),
+        );
+    }
+}
+
+fn console() -> &'static RtConsole {
+    let console = &*RT_CONSOLE;
+    RT_CONSOLE_CREATED.store(true, Ordering::Release);
+    console
+}
+
+impl RtMessage {
+    /// Create an empty message.
+    pub const fn new() -> Self {
+        Self {
+            len: 0,
+            buf: [0; RT_MESSAGE_LEN + 1],
+        }
+    }
+
+    /// Get the message as a string.
+    pub fn as_str(&self) -> &str {
+        //only whole utf8 characters are ever written
+        unsafe { std::str::from_utf8_unchecked(&self.buf[..self.len]) }
+    }
+
+    /// Get the message as a C string.
+    pub fn as_cstr(&self) -> &CStr {
+        //the byte after the message is always nul, and nuls are never written
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.buf[..=self.len]) }
+    }
+}
+
+impl Default for RtMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for RtMessage {
+    //truncates rather than failing, so a long message still gets posted
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars().filter(|c| *c != '\0') {
+            let l = c.len_utf8();
+            if self.len + l > RT_MESSAGE_LEN {
+                break;
+            }
+            c.encode_utf8(&mut self.buf[self.len..self.len + l]);
+            self.len += l;
+        }
+        self.buf[self.len] = 0;
+        Ok(())
+    }
+}
+
+impl Site {
+    pub const fn new() -> Self {
+        Self {
+            pending: AtomicBool::new(false),
+            suppressed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Queue a message from this call site, associated with the current object.
+    ///
+    /// # Remarks
+    /// * Doesn't allocate or lock, except for the first post if [`init`](fn.init.html) hasn't
+    /// been called.
+    pub fn post(&'static self, level: ConsoleLevel, args: fmt::Arguments) {
+        self.post_object(level, crate::logging::current_object(), args);
+    }
+
+    /// Queue a message from this call site, associated with `obj`.
+    pub fn post_object(
+        &'static self,
+        level: ConsoleLevel,
+        obj: *mut max_sys::t_object,
+        args: fmt::Arguments,
+    ) {
+        if self.pending.swap(true, Ordering::AcqRel) {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let mut msg = RtMessage::new();
+        let _ = msg.write_fmt(args);
+        let console = console();
+        let entry = Entry {
+            level,
+            obj: obj as usize,
+            site: self,
+            msg,
+        };
+        if console.queue.push(entry).is_err() {
+            self.pending.store(false, Ordering::Release);
+            console.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        console.qelem.set();
+    }
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message() {
+        let mut m = RtMessage::new();
+        write!(m, "foo {} {}", 1, 2.5).unwrap();
+        assert_eq!(m.as_str(), "foo 1 2.5");
+        assert_eq!(m.as_cstr().to_str().unwrap(), "foo 1 2.5");
+
+        //truncates on a character boundary
+        let mut m = RtMessage::new();
+        let long = "é".repeat(RT_MESSAGE_LEN);
+        write!(m, "a{}", long).unwrap();
+        assert_eq!(m.as_str().len(), RT_MESSAGE_LEN - 1);
+        assert_eq!(m.as_cstr().to_bytes().len(), RT_MESSAGE_LEN - 1);
+
+        let mut m = RtMessage::new();
+        m.write_str("a\0b").unwrap();
+        assert_eq!(m.as_str(), "ab");
+    }
+
+    #[test]
+    fn rate_limit() {
+        use crate::test::post::take_posted;
+        static SITE: Site = Site::new();
+        take_posted();
+        for i in 0..3 {
+            SITE.post_object(
+                ConsoleLevel::Post,
+                std::ptr::null_mut(),
+                format_args!("rt {}", i),
+            );
+        }
+        flush();
+        SITE.post_object(
+            ConsoleLevel::Error,
+            std::ptr::null_mut(),
+            format_args!("rt again"),
+        );
+        flush();
+        assert_eq!(
+            take_posted(),
+            vec![
+                "object post: rt 0 (2 repeats suppressed)".to_string(),
+                "object error: rt again".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Real-time safety checks for `perform`.
+//!
+//! With the `rt-check` feature, in debug builds, the thread is marked as real-time while a wrapped
+//! object's `perform` runs and these are reported as violations:
+//!
+//! * Heap allocation through [`MaxAllocator`](../../alloc/struct.MaxAllocator.html) or
+//! [`RtCheckAllocator`](struct.RtCheckAllocator.html).
+//! * Taking a lock in median, for instance in `BufferRef`.
+//! * Posting to the console with `post`, `error` or their `object_` versions, use
+//! [`rt_post!`](../../macro.rt_post.html) instead.
+//!
+//! The first violation for each object is reported to the Max console with a backtrace. Without
+//! the feature, or in release builds, the checks compile to nothing.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    sync::atomic::AtomicBool,
+};
+
+/// Wrap another global allocator so allocations in `perform` are reported.
+///
+/// # Remarks
+/// * [`MaxAllocator`](../../alloc/struct.MaxAllocator.html) already checks, this is for other
+/// allocators.
+///
+/// # Examples
+///
+/// ```no_run
+/// use median::rt::check::RtCheckAllocator;
+///
+/// #[global_allocator]
+/// static GLOBAL: RtCheckAllocator<std::alloc::System> = RtCheckAllocator(std::alloc::System);
+/// ```
+pub struct RtCheckAllocator<A>(pub A);
+
+#[cfg(all(feature = "rt-check", debug_assertions))]
+mod imp {
+    use std::{
+        backtrace::Backtrace,
+        cell::{Cell, RefCell},
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    thread_local! {
+        //checking is on for this thread
+        static CHECKING: Cell<bool> = const { Cell::new(false) };
+        //set while recording, so allocations made by the recording aren't checked
+        static RECORDING: Cell<bool> = const { Cell::new(false) };
+        static VIOLATION: RefCell<Option<(&'static str, Backtrace)>> = const { RefCell::new(None) };
+    }
+
+    pub fn note(what: &'static str) {
+        let check = CHECKING.try_with(|c| c.get()).unwrap_or(false)
+            && !RECORDING.try_with(|r| r.get()).unwrap_or(true);
+        if check {
+            RECORDING.with(|r| r.set(true));
+            //only keep the first
+            CHECKING.with(|c| c.set(false));
+            let bt = Backtrace::force_capture();
+            let _ = VIOLATION.try_with(|v| *v.borrow_mut() = Some((what, bt)));
+            RECORDING.with(|r| r.set(false));
+        }
+    }
+
+    //restores checking when dropped, even if perform panics
+    struct Checking(bool);
+
+    impl Checking {
+        fn start() -> Self {
+            Self(CHECKING.with(|c| c.replace(true)))
+        }
+    }
+
+    impl Drop for Checking {
+        fn drop(&mut self) {
+            let _ = CHECKING.try_with(|c| c.set(self.0));
+        }
+    }
+
+    pub fn run<R, F: FnOnce() -> R>(
+        obj: *mut max_sys::t_object,
+        reported: &AtomicBool,
+        func: F,
+    ) -> R {
+        if reported.load(Ordering::Relaxed) {
+            return func();
+        }
+        //a panic in an earlier perform can leave a violation behind
+        VIOLATION.with(|v| v.borrow_mut().take());
+        let r = {
+            let _checking = Checking::start();
+            func()
+        };
+        if let Some((what, bt)) = VIOLATION.with(|v| v.borrow_mut().take()) {
+            if !reported.swap(true, Ordering::Relaxed) {
+                crate::logging::console_object(
+                    crate::logging::ConsoleLevel::Error,
+                    obj,
+                    format!("real-time violation in perform: {}\n{}", what, bt),
+                );
+            }
+        }
+        r
+    }
+}
+
+#[cfg(not(all(feature = "rt-check", debug_assertions)))]
+mod imp {
+    use std::sync::atomic::AtomicBool;
+
+    #[inline(always)]
+    pub fn note(_what: &'static str) {}
+
+    #[inline(always)]
+    pub fn run<R, F: FnOnce() -> R>(
+        _obj: *mut max_sys::t_object,
+        _reported: &AtomicBool,
+        func: F,
+    ) -> R {
+        func()
+    }
+}
+
+/// Record a real-time violation, if the current thread is being checked.
+///
+/// # Arguments
+/// * `what` - a description of the violation, for instance `"allocation"`.
+///
+/// # Remarks
+/// * Use this to add checks to your own code that shouldn't run on the audio thread.
+#[inline(always)]
+pub fn note(what: &'static str) {
+    imp::note(what)
+}
+
+/// Run `func` with real-time checking on for the current thread.
+///
+/// # Arguments
+/// * `obj` - the object to report violations for.
+/// * `reported` - set once a violation has been reported, so each object only reports once.
+/// * `func` - the code to check.
+#[inline(always)]
+pub fn run<R, F: FnOnce() -> R>(obj: *mut max_sys::t_object, reported: &AtomicBool, func: F) -> R {
+    imp::run(obj, reported, func)
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for RtCheckAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        note("allocation");
+        self.0.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        note("deallocation");
+        self.0.dealloc(ptr, layout)
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        note("allocation");
+        self.0.alloc_zeroed(layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        note("reallocation");
+        self.0.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(all(test, feature = "rt-check", debug_assertions))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn report_once() {
+        use crate::test::post::take_posted;
+        take_posted();
+        let reported = AtomicBool::new(false);
+        run(std::ptr::null_mut(), &reported, || ());
+        assert!(!reported.load(Ordering::Relaxed));
+        run(std::ptr::null_mut(), &reported, || note("lock"));
+        assert!(reported.load(Ordering::Relaxed));
+        run(std::ptr::null_mut(), &reported, || note("lock"));
+        note("outside");
+        let posted = take_posted();
+        assert_eq!(posted.len(), 1);
+        assert!(posted[0].starts_with("object error: real-time violation in perform: lock"));
+    }
+
+    #[test]
+    fn allocation() {
+        use crate::test::post::take_posted;
+        take_posted();
+        let reported = AtomicBool::new(false);
+        let a = RtCheckAllocator(std::alloc::System);
+        let layout = Layout::new::<u64>();
+        run(std::ptr::null_mut(), &reported, || unsafe {
+            a.dealloc(a.alloc(layout), layout);
+        });
+        let posted = take_posted();
+        assert_eq!(posted.len(), 1);
+        assert!(posted[0].starts_with("object error: real-time violation in perform: allocation"));
+    }
+
+    #[test]
+    fn panic() {
+        use crate::test::post::take_posted;
+        take_posted();
+        let reported = AtomicBool::new(false);
+        let r = std::panic::catch_unwind(|| {
+            run(std::ptr::null_mut(), &reported, || {
+                note("lock");
+                panic!("in perform");
+            })
+        });
+        assert!(r.is_err());
+        //checking is off again and the violation isn't blamed on the next object
+        note("outside");
+        let other = AtomicBool::new(false);
+        run(std::ptr::null_mut(), &other, || ());
+        assert!(!other.load(Ordering::Relaxed));
+        assert!(take_posted().is_empty());
+    }
+}
@@ -6,8 +6,18 @@ use std::{
     ffi::{CStr, CString},
     fmt::{Display, Formatter},
     hash::{Hash, Hasher},
+    sync::atomic::{AtomicPtr, Ordering},
 };
 
+/// A symbol that is interned the first time it is used and cached after that.
+///
+/// # Remarks
+/// * Usually created with the [`sym!`](../macro.sym.html) macro.
+pub struct StaticSymbol {
+    name: &'static str,
+    value: AtomicPtr<max_sys::t_symbol>,
+}
+
 #[repr(transparent)]
 pub struct SymbolRef {
     pub(crate) value: UnsafeCell<*mut max_sys::t_symbol>,
@@ -42,6 +52,14 @@ impl SymbolRef {
         unsafe { CStr::from_ptr(self.inner_ref().s_name).into() }
     }
 
+    /// Get the symbol's name.
+    ///
+    /// # Remarks
+    /// * Max never frees symbols, so the name lives forever.
+    pub fn as_cstr(&self) -> &'static CStr {
+        unsafe { CStr::from_ptr(self.inner_ref().s_name) }
+    }
+
     /// Try to convert to a rust String.
     pub fn to_string(&self) -> Result<String, std::str::Utf8Error> {
         self.to_cstring().to_str().map(|s| s.to_string())
@@ -53,6 +71,32 @@ impl SymbolRef {
     }
 }
 
+impl StaticSymbol {
+    /// Create a static symbol, `name` must be nul terminated.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            value: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// Get the symbol, interning it if this is the first use.
+    ///
+    /// # Panics
+    /// Will panic if the name isn't nul terminated or has interior nuls.
+    pub fn get(&self) -> SymbolRef {
+        let mut s = self.value.load(Ordering::Acquire);
+        if s.is_null() {
+            let name = CStr::from_bytes_with_nul(self.name.as_bytes())
+                .expect("static symbol names must be nul terminated, without interior nuls");
+            //gensym always gives the same symbol for a name, so racing here is harmless
+            s = unsafe { max_sys::gensym(name.as_ptr()) };
+            self.value.store(s, Ordering::Release);
+        }
+        SymbolRef::new(s)
+    }
+}
+
 impl Hash for SymbolRef {
     fn hash<H: Hasher>(&self, state: &mut H) {
         unsafe {
@@ -162,4 +206,17 @@ mod tests {
             std::mem::align_of::<SymbolRef>()
         );
     }
+
+    #[test]
+    fn static_symbol() {
+        fn foo() -> SymbolRef {
+            crate::sym!("foo")
+        }
+        let a = foo();
+        assert_eq!(a.to_string().unwrap(), "foo");
+        assert!(a == foo());
+        assert!(a == SymbolRef::try_from("foo").unwrap());
+        assert!(a != crate::sym!("bar"));
+        assert!(crate::sym!(concat!("f", "oo")) == a);
+    }
 }
@@ -1,2 +1,12 @@
 pub mod atom;
+pub mod buffer;
+pub mod class;
+pub mod clock;
+pub mod dictionary;
+pub mod inlet;
+pub mod object;
+pub mod outlet;
+pub mod post;
+pub mod qelem;
+pub mod symbol;
 pub mod sysmem;
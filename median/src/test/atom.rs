@@ -18,3 +18,120 @@ pub unsafe extern "C" fn atom_setlong(
     (*a).a_w.w_long = b;
     max_sys::e_max_errorcodes::MAX_ERR_NONE as _
 }
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_setsym(
+    a: *mut max_sys::t_atom,
+    b: *mut max_sys::t_symbol,
+) -> max_sys::t_max_err {
+    (*a).a_type = max_sys::e_max_atomtypes::A_SYM as _;
+    (*a).a_w.w_sym = b;
+    max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_gettype(a: *const max_sys::t_atom) -> std::os::raw::c_long {
+    (*a).a_type as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_getlong(a: *const max_sys::t_atom) -> max_sys::t_atom_long {
+    match (*a).a_type as max_sys::e_max_atomtypes::Type {
+        max_sys::e_max_atomtypes::A_LONG => (*a).a_w.w_long,
+        max_sys::e_max_atomtypes::A_FLOAT => (*a).a_w.w_float as _,
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_getfloat(a: *const max_sys::t_atom) -> max_sys::t_atom_float {
+    match (*a).a_type as max_sys::e_max_atomtypes::Type {
+        max_sys::e_max_atomtypes::A_LONG => (*a).a_w.w_long as _,
+        max_sys::e_max_atomtypes::A_FLOAT => (*a).a_w.w_float,
+        _ => 0.0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_getsym(a: *const max_sys::t_atom) -> *mut max_sys::t_symbol {
+    match (*a).a_type as max_sys::e_max_atomtypes::Type {
+        max_sys::e_max_atomtypes::A_SYM => (*a).a_w.w_sym,
+        _ => super::symbol::gensym(b"\0".as_ptr() as _),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_setparse(
+    ac: *mut std::os::raw::c_long,
+    av: *mut *mut max_sys::t_atom,
+    parsestr: *const std::os::raw::c_char,
+) -> max_sys::t_max_err {
+    let text = std::ffi::CStr::from_ptr(parsestr).to_string_lossy();
+    match crate::atom::text::parse(&text) {
+        Ok(atoms) => {
+            let size = std::mem::size_of::<max_sys::t_atom>() * atoms.len();
+            let ptr = super::sysmem::sysmem_newptr(size as _) as *mut max_sys::t_atom;
+            for (i, a) in atoms.iter().enumerate() {
+                *ptr.add(i) = a.value;
+            }
+            *ac = atoms.len() as _;
+            *av = ptr;
+            max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+        }
+        Err(_) => max_sys::e_max_errorcodes::MAX_ERR_GENERIC as _,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_gettext(
+    ac: std::os::raw::c_long,
+    av: *mut max_sys::t_atom,
+    textsize: *mut std::os::raw::c_long,
+    text: *mut *mut std::os::raw::c_char,
+    _flags: std::os::raw::c_long,
+) -> max_sys::t_max_err {
+    let atoms = std::slice::from_raw_parts(av as *const crate::atom::Atom, ac as _);
+    let s = crate::atom::text::format(atoms);
+    let ptr = super::sysmem::sysmem_newptr((s.len() + 1) as _);
+    std::ptr::copy_nonoverlapping(s.as_ptr() as *const std::os::raw::c_char, ptr, s.len());
+    *ptr.add(s.len()) = 0;
+    *textsize = (s.len() + 1) as _;
+    *text = ptr;
+    max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_setobj(
+    a: *mut max_sys::t_atom,
+    b: *mut std::ffi::c_void,
+) -> max_sys::t_max_err {
+    (*a).a_type = max_sys::e_max_atomtypes::A_OBJ as _;
+    (*a).a_w.w_obj = b as _;
+    max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atom_getobj(a: *const max_sys::t_atom) -> *mut std::ffi::c_void {
+    match (*a).a_type as max_sys::e_max_atomtypes::Type {
+        max_sys::e_max_atomtypes::A_OBJ => (*a).a_w.w_obj as _,
+        _ => std::ptr::null_mut(),
+    }
+}
+
+//like max, reuses the caller's atoms if there are enough and allocates otherwise
+#[no_mangle]
+pub unsafe extern "C" fn atom_alloc_array(
+    minsize: std::os::raw::c_long,
+    ac: *mut std::os::raw::c_long,
+    av: *mut *mut max_sys::t_atom,
+    alloc: *mut std::os::raw::c_char,
+) -> max_sys::t_max_err {
+    *alloc = 0;
+    if *ac < minsize || (*av).is_null() {
+        let size = std::mem::size_of::<max_sys::t_atom>() * minsize as usize;
+        *av = super::sysmem::sysmem_newptrclear(size as _) as _;
+        *ac = minsize;
+        *alloc = 1;
+    }
+    max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+}
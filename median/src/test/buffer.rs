@@ -0,0 +1,75 @@
+use std::ffi::c_void;
+
+//tests have no buffers, references never find one
+#[no_mangle]
+pub unsafe extern "C" fn buffer_ref_new(
+    _self: *mut max_sys::t_object,
+    _name: *mut max_sys::t_symbol,
+) -> *mut max_sys::t_buffer_ref {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_ref_set(
+    _x: *mut max_sys::t_buffer_ref,
+    _name: *mut max_sys::t_symbol,
+) {
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_ref_exists(_x: *mut max_sys::t_buffer_ref) -> max_sys::t_atom_long {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_ref_getobject(
+    _x: *mut max_sys::t_buffer_ref,
+) -> *mut max_sys::t_buffer_obj {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_ref_notify(
+    _x: *mut max_sys::t_buffer_ref,
+    _s: *mut max_sys::t_symbol,
+    _msg: *mut max_sys::t_symbol,
+    _sender: *mut c_void,
+    _data: *mut c_void,
+) -> max_sys::t_max_err {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_getchannelcount(
+    _buffer_object: *mut max_sys::t_buffer_obj,
+) -> max_sys::t_atom_long {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_getframecount(
+    _buffer_object: *mut max_sys::t_buffer_obj,
+) -> max_sys::t_atom_long {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_getsamplerate(
+    _buffer_object: *mut max_sys::t_buffer_obj,
+) -> max_sys::t_atom_float {
+    0.
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_getmillisamplerate(
+    _buffer_object: *mut max_sys::t_buffer_obj,
+) -> max_sys::t_atom_float {
+    0.
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buffer_locksamples(
+    _buffer_object: *mut max_sys::t_buffer_obj,
+) -> *mut f32 {
+    std::ptr::null_mut()
+}
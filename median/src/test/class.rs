@@ -0,0 +1,75 @@
+use std::{
+    ffi::c_void,
+    os::raw::{c_char, c_long, c_short},
+};
+
+//tests build wrappers by hand, these are only linked in through the class setup and builder
+#[no_mangle]
+pub unsafe extern "C" fn class_new(
+    _name: *const c_char,
+    _mnew: max_sys::method,
+    _mfree: max_sys::method,
+    _size: c_long,
+    _mmenu: max_sys::method,
+    _type: c_short,
+) -> *mut max_sys::t_class {
+    std::ptr::null_mut()
+}
+
+//the variadic type arguments are ignored
+#[no_mangle]
+pub unsafe extern "C" fn class_addmethod(
+    _c: *mut max_sys::t_class,
+    _m: max_sys::method,
+    _name: *const c_char,
+) -> max_sys::t_max_err {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn class_method(
+    _x: *mut max_sys::t_class,
+    _methodname: *mut max_sys::t_symbol,
+) -> max_sys::method {
+    None
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn class_alias(
+    _c: *mut max_sys::t_class,
+    _aliasname: *mut max_sys::t_symbol,
+) -> max_sys::t_max_err {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn class_findbyname(
+    _name_space: *mut max_sys::t_symbol,
+    _classname: *mut max_sys::t_symbol,
+) -> *mut max_sys::t_class {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn class_register(
+    _name_space: *mut max_sys::t_symbol,
+    _c: *mut max_sys::t_class,
+) -> max_sys::t_max_err {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_alloc(_c: *mut max_sys::t_class) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn attr_args_process(
+    _x: *mut c_void,
+    _ac: c_short,
+    _av: *mut max_sys::t_atom,
+) {
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn z_dsp_setup(_x: *mut max_sys::t_pxobject, _nsignals: c_long) {}
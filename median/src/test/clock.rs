@@ -0,0 +1,58 @@
+use std::{
+    ffi::c_void,
+    os::raw::c_long,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+//tests don't run a scheduler, they flush by hand
+struct Clock {
+    set: AtomicBool,
+}
+
+static LIVE: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Free `x` if it is a mock clock.
+pub unsafe fn free(x: *mut c_void) -> bool {
+    let mut live = LIVE.lock().unwrap();
+    match live.iter().position(|c| *c == x as usize) {
+        Some(i) => {
+            live.swap_remove(i);
+            let _ = Box::from_raw(x as *mut Clock);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Is the clock `x` waiting to go off.
+pub unsafe fn is_set(x: *mut c_void) -> bool {
+    (*(x as *const Clock)).set.load(Ordering::Relaxed)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn clock_new(_obj: *mut c_void, _func: max_sys::method) -> *mut c_void {
+    let x = Box::into_raw(Box::new(Clock {
+        set: AtomicBool::new(false),
+    }));
+    LIVE.lock().unwrap().push(x as usize);
+    x as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn clock_delay(x: *mut c_void, _n: c_long) {
+    (*(x as *const Clock)).set.store(true, Ordering::Relaxed);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn clock_unset(x: *mut c_void) {
+    (*(x as *const Clock)).set.store(false, Ordering::Relaxed);
+}
+
+//tests have a single scheduler
+#[no_mangle]
+pub unsafe extern "C" fn scheduler_fromobject(_obj: *mut max_sys::t_object) -> *mut c_void {
+    std::ptr::null_mut()
+}
@@ -0,0 +1,348 @@
+use std::{cell::RefCell, collections::HashSet, ffi::c_void, os::raw::c_long};
+
+type Key = *mut max_sys::t_symbol;
+
+//an in memory stand in for max's dictionaries, entries keep their insertion order
+struct Dict {
+    entries: Vec<(Key, Entry)>,
+}
+
+enum Entry {
+    Atoms {
+        atoms: Vec<max_sys::t_atom>,
+        array: bool,
+    },
+    Dict(*mut c_void),
+}
+
+thread_local! {
+    static LIVE: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+const NONE: max_sys::t_max_err = max_sys::e_max_errorcodes::MAX_ERR_NONE as _;
+const GENERIC: max_sys::t_max_err = max_sys::e_max_errorcodes::MAX_ERR_GENERIC as _;
+
+/// The number of mock dictionaries that haven't been freed, on this thread.
+pub fn live() -> usize {
+    LIVE.with(|l| l.borrow().len())
+}
+
+fn is_dict(x: *mut c_void) -> bool {
+    LIVE.with(|l| l.borrow().contains(&(x as usize)))
+}
+
+/// Free `x` if it is a mock dictionary, along with the dictionaries it owns.
+pub unsafe fn free(x: *mut c_void) -> bool {
+    if !LIVE.with(|l| l.borrow_mut().remove(&(x as usize))) {
+        return false;
+    }
+    let d = Box::from_raw(x as *mut Dict);
+    for (_, e) in d.entries {
+        free_entry(e);
+    }
+    true
+}
+
+//arrays own the dictionaries in them
+unsafe fn free_entry(e: Entry) {
+    match e {
+        Entry::Dict(d) => {
+            free(d);
+        }
+        Entry::Atoms { atoms, .. } => {
+            for a in atoms {
+                if a.a_type as max_sys::e_max_atomtypes::Type == max_sys::e_max_atomtypes::A_OBJ {
+                    free(a.a_w.w_obj as _);
+                }
+            }
+        }
+    }
+}
+
+unsafe fn dict<'a>(d: *const max_sys::t_dictionary) -> &'a mut Dict {
+    &mut *(d as *mut Dict)
+}
+
+unsafe fn entry<'a>(d: *const max_sys::t_dictionary, key: Key) -> Option<&'a Entry> {
+    dict(d)
+        .entries
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, e)| e)
+}
+
+unsafe fn append(d: *mut max_sys::t_dictionary, key: Key, e: Entry) -> max_sys::t_max_err {
+    let d = dict(d);
+    match d.entries.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, old)) => free_entry(std::mem::replace(old, e)),
+        None => d.entries.push((key, e)),
+    }
+    NONE
+}
+
+unsafe fn append_atom(
+    d: *mut max_sys::t_dictionary,
+    key: Key,
+    a: max_sys::t_atom,
+) -> max_sys::t_max_err {
+    append(
+        d,
+        key,
+        Entry::Atoms {
+            atoms: vec![a],
+            array: false,
+        },
+    )
+}
+
+unsafe fn single<'a>(d: *const max_sys::t_dictionary, key: Key) -> Option<&'a max_sys::t_atom> {
+    match entry(d, key) {
+        Some(Entry::Atoms {
+            atoms,
+            array: false,
+        }) => atoms.first(),
+        _ => None,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_new() -> *mut max_sys::t_dictionary {
+    let d = Box::into_raw(Box::new(Dict {
+        entries: Vec::new(),
+    }));
+    LIVE.with(|l| l.borrow_mut().insert(d as usize));
+    d as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictobj_namefromptr(_d: *mut max_sys::t_dictionary) -> Key {
+    std::ptr::null_mut()
+}
+
+//tests don't register dictionaries, so none are retained
+#[no_mangle]
+pub unsafe extern "C" fn dictobj_release(_d: *mut max_sys::t_dictionary) -> max_sys::t_max_err {
+    NONE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_classname(x: *mut c_void) -> Key {
+    if is_dict(x) {
+        super::symbol::gensym(b"dictionary\0".as_ptr() as _)
+    } else {
+        super::symbol::gensym(b"object\0".as_ptr() as _)
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_getentrycount(
+    d: *const max_sys::t_dictionary,
+) -> max_sys::t_atom_long {
+    dict(d).entries.len() as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_hasentry(d: *const max_sys::t_dictionary, key: Key) -> c_long {
+    entry(d, key).is_some() as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_entryisdictionary(
+    d: *const max_sys::t_dictionary,
+    key: Key,
+) -> c_long {
+    matches!(entry(d, key), Some(Entry::Dict(_))) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_entryisatomarray(
+    d: *const max_sys::t_dictionary,
+    key: Key,
+) -> c_long {
+    matches!(entry(d, key), Some(Entry::Atoms { array: true, .. })) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_getkeys(
+    d: *const max_sys::t_dictionary,
+    numkeys: *mut c_long,
+    keys: *mut *mut Key,
+) -> max_sys::t_max_err {
+    let entries = &dict(d).entries;
+    let ptr = super::sysmem::sysmem_newptr((std::mem::size_of::<Key>() * entries.len().max(1)) as _)
+        as *mut Key;
+    for (i, (k, _)) in entries.iter().enumerate() {
+        *ptr.add(i) = *k;
+    }
+    *numkeys = entries.len() as _;
+    *keys = ptr;
+    NONE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_freekeys(
+    _d: *mut max_sys::t_dictionary,
+    _numkeys: c_long,
+    keys: *mut Key,
+) {
+    super::sysmem::sysmem_freeptr(keys as _);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_deleteentry(
+    d: *mut max_sys::t_dictionary,
+    key: Key,
+) -> max_sys::t_max_err {
+    let d = dict(d);
+    match d.entries.iter().position(|(k, _)| *k == key) {
+        Some(i) => {
+            free_entry(d.entries.remove(i).1);
+            NONE
+        }
+        None => GENERIC,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_clear(d: *mut max_sys::t_dictionary) -> max_sys::t_max_err {
+    for (_, e) in std::mem::take(&mut dict(d).entries) {
+        free_entry(e);
+    }
+    NONE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_appendlong(
+    d: *mut max_sys::t_dictionary,
+    key: Key,
+    value: max_sys::t_atom_long,
+) -> max_sys::t_max_err {
+    let mut a = std::mem::zeroed();
+    super::atom::atom_setlong(&mut a, value);
+    append_atom(d, key, a)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_appendfloat(
+    d: *mut max_sys::t_dictionary,
+    key: Key,
+    value: f64,
+) -> max_sys::t_max_err {
+    let mut a = std::mem::zeroed();
+    super::atom::atom_setfloat(&mut a, value);
+    append_atom(d, key, a)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_appendsym(
+    d: *mut max_sys::t_dictionary,
+    key: Key,
+    value: Key,
+) -> max_sys::t_max_err {
+    let mut a = std::mem::zeroed();
+    super::atom::atom_setsym(&mut a, value);
+    append_atom(d, key, a)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_appendatoms(
+    d: *mut max_sys::t_dictionary,
+    key: Key,
+    argc: c_long,
+    argv: *mut max_sys::t_atom,
+) -> max_sys::t_max_err {
+    let atoms = if argv.is_null() || argc < 1 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(argv, argc as _).to_vec()
+    };
+    //like max, a single atom isn't stored as an array
+    let array = atoms.len() != 1;
+    append(d, key, Entry::Atoms { atoms, array })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_appenddictionary(
+    d: *mut max_sys::t_dictionary,
+    key: Key,
+    value: *mut max_sys::t_object,
+) -> max_sys::t_max_err {
+    append(d, key, Entry::Dict(value as _))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_getlong(
+    d: *const max_sys::t_dictionary,
+    key: Key,
+    value: *mut max_sys::t_atom_long,
+) -> max_sys::t_max_err {
+    match single(d, key) {
+        Some(a) => {
+            *value = super::atom::atom_getlong(a);
+            NONE
+        }
+        None => GENERIC,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_getfloat(
+    d: *const max_sys::t_dictionary,
+    key: Key,
+    value: *mut f64,
+) -> max_sys::t_max_err {
+    match single(d, key) {
+        Some(a) => {
+            *value = super::atom::atom_getfloat(a);
+            NONE
+        }
+        None => GENERIC,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_getsym(
+    d: *const max_sys::t_dictionary,
+    key: Key,
+    value: *mut Key,
+) -> max_sys::t_max_err {
+    match single(d, key) {
+        Some(a) => {
+            *value = super::atom::atom_getsym(a);
+            NONE
+        }
+        None => GENERIC,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_getatoms(
+    d: *const max_sys::t_dictionary,
+    key: Key,
+    argc: *mut c_long,
+    argv: *mut *mut max_sys::t_atom,
+) -> max_sys::t_max_err {
+    match entry(d, key) {
+        Some(Entry::Atoms { atoms, .. }) => {
+            *argc = atoms.len() as _;
+            *argv = atoms.as_ptr() as _;
+            NONE
+        }
+        _ => GENERIC,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dictionary_getdictionary(
+    d: *const max_sys::t_dictionary,
+    key: Key,
+    value: *mut *mut max_sys::t_object,
+) -> max_sys::t_max_err {
+    match entry(d, key) {
+        Some(Entry::Dict(v)) => {
+            *value = *v as _;
+            NONE
+        }
+        _ => GENERIC,
+    }
+}
@@ -0,0 +1,37 @@
+use std::{
+    ffi::c_void,
+    os::raw::{c_long, c_short},
+};
+
+//tests dispatch inlets by hand, these are only linked in through the builder
+#[no_mangle]
+pub unsafe extern "C" fn proxy_new(
+    _x: *mut c_void,
+    _id: c_long,
+    _stuffloc: *mut c_long,
+) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn proxy_getinlet(_master: *mut max_sys::t_object) -> c_long {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn intin(_x: *mut c_void, _n: c_short) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn floatin(_x: *mut c_void, _n: c_short) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn inlet_nth(_x: *mut max_sys::t_object, _n: c_long) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn inlet_delete(_x: *mut c_void) {}
@@ -0,0 +1,122 @@
+use std::{cell::RefCell, ffi::c_void};
+
+//the only objects tests create are mock dictionaries and clocks
+#[no_mangle]
+pub unsafe extern "C" fn object_free(x: *mut c_void) -> max_sys::t_max_err {
+    if !super::dictionary::free(x) {
+        super::clock::free(x);
+    }
+    0
+}
+
+//test objects aren't in a patcher
+#[no_mangle]
+pub unsafe extern "C" fn object_obex_lookup(
+    _x: *mut c_void,
+    _key: *mut max_sys::t_symbol,
+    _val: *mut *mut max_sys::t_object,
+) -> max_sys::t_max_err {
+    max_sys::e_max_errorcodes::MAX_ERR_GENERIC as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_obex_storeflags(
+    _x: *mut c_void,
+    _key: *mut max_sys::t_symbol,
+    _val: *mut max_sys::t_object,
+    _flags: std::os::raw::c_long,
+) -> max_sys::t_max_err {
+    0
+}
+
+thread_local! {
+    static NOTIFIED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Take the notifications objects broadcast on the current thread.
+pub fn take_notified() -> Vec<String> {
+    NOTIFIED.with(|n| std::mem::take(&mut *n.borrow_mut()))
+}
+
+//tests have no clients to notify, the messages are recorded instead
+#[no_mangle]
+pub unsafe extern "C" fn object_notify(
+    _x: *mut c_void,
+    s: *mut max_sys::t_symbol,
+    _data: *mut c_void,
+) -> max_sys::t_max_err {
+    let msg = std::ffi::CStr::from_ptr((*s).s_name).to_string_lossy();
+    NOTIFIED.with(|n| n.borrow_mut().push(msg.into_owned()));
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_obex_store(
+    _x: *mut c_void,
+    _key: *mut max_sys::t_symbol,
+    _val: *mut max_sys::t_object,
+) -> max_sys::t_max_err {
+    0
+}
+
+//the variadic arguments are ignored, nothing is registered so there is nothing to call
+#[no_mangle]
+pub unsafe extern "C" fn object_method(_x: *mut c_void, _s: *mut max_sys::t_symbol) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_register(
+    _name_space: *mut max_sys::t_symbol,
+    _s: *mut max_sys::t_symbol,
+    x: *mut c_void,
+) -> *mut c_void {
+    x
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_findregistered(
+    _name_space: *mut max_sys::t_symbol,
+    _s: *mut max_sys::t_symbol,
+) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_attach(
+    _name_space: *mut max_sys::t_symbol,
+    _s: *mut max_sys::t_symbol,
+    _x: *mut c_void,
+) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_subscribe(
+    _name_space: *mut max_sys::t_symbol,
+    _s: *mut max_sys::t_symbol,
+    _classname: *mut max_sys::t_symbol,
+    _x: *mut c_void,
+) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn defer_low(
+    _ob: *mut c_void,
+    _fn: max_sys::method,
+    _sym: *mut max_sys::t_symbol,
+    _argc: std::os::raw::c_short,
+    _argv: *mut max_sys::t_atom,
+) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn binbuf_insert(
+    _x: *mut max_sys::t_binbuf,
+    _s: *mut max_sys::t_symbol,
+    _argc: std::os::raw::c_short,
+    _argv: *mut max_sys::t_atom,
+) {
+}
@@ -0,0 +1,94 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::c_void,
+    os::raw::c_short,
+};
+
+thread_local! {
+    static SENT: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    static OVERFLOW: Cell<bool> = Cell::new(false);
+    static ON_SEND: RefCell<Option<Box<dyn FnMut()>>> = RefCell::new(None);
+}
+
+/// Call `func` after each message an outlet sends on the current thread, like an object
+/// connected to the outlet would.
+pub fn set_on_send(func: Option<Box<dyn FnMut()>>) {
+    ON_SEND.with(|o| *o.borrow_mut() = func);
+}
+
+/// Make outlets on the current thread fail with a stack overflow, without recording anything.
+pub fn set_overflow(overflow: bool) {
+    OVERFLOW.with(|o| o.set(overflow));
+}
+
+/// Take the messages sent through outlets by the current thread.
+pub fn take_sent() -> Vec<String> {
+    SENT.with(|s| std::mem::take(&mut *s.borrow_mut()))
+}
+
+//outlets return non null on success, null for stack overflow
+fn record(msg: String) -> *mut c_void {
+    if OVERFLOW.with(|o| o.get()) {
+        return std::ptr::null_mut();
+    }
+    SENT.with(|s| s.borrow_mut().push(msg));
+    //taken while it runs, so sends from it don't call it again
+    if let Some(mut func) = ON_SEND.with(|o| o.borrow_mut().take()) {
+        func();
+        ON_SEND.with(|o| {
+            o.borrow_mut().get_or_insert(func);
+        });
+    }
+    1usize as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn outlet_bang(_o: *mut c_void) -> *mut c_void {
+    record("bang".into())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn outlet_int(_o: *mut c_void, v: max_sys::t_atom_long) -> *mut c_void {
+    record(format!("int {}", v))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn outlet_float(_o: *mut c_void, v: f64) -> *mut c_void {
+    record(format!("float {}", v))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn outlet_list(
+    _o: *mut c_void,
+    _s: *mut max_sys::t_symbol,
+    ac: c_short,
+    _av: *mut max_sys::t_atom,
+) -> *mut c_void {
+    record(format!("list {}", ac))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn outlet_anything(
+    _o: *mut c_void,
+    s: *mut max_sys::t_symbol,
+    ac: c_short,
+    _av: *mut max_sys::t_atom,
+) -> *mut c_void {
+    let sel = std::ffi::CStr::from_ptr((*s).s_name).to_string_lossy();
+    record(format!("{} {}", sel, ac))
+}
+
+//tests only ever run on the "main" thread, see post::set_main_thread
+#[no_mangle]
+pub unsafe extern "C" fn systhread_istimerthread() -> c_short {
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn outlet_append(
+    _op: *mut max_sys::t_object,
+    _s1: *mut max_sys::t_symbol,
+    _s2: *mut max_sys::t_symbol,
+) -> *mut c_void {
+    1usize as _
+}
@@ -0,0 +1,68 @@
+use std::{cell::RefCell, ffi::CStr, os::raw::c_char};
+
+thread_local! {
+    static MAIN_THREAD: RefCell<bool> = RefCell::new(true);
+    static POSTED: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+/// Pretend the current thread is, or isn't, Max's main thread.
+pub fn set_main_thread(main: bool) {
+    MAIN_THREAD.with(|m| *m.borrow_mut() = main);
+}
+
+/// Take the messages written to the console by the current thread.
+pub fn take_posted() -> Vec<String> {
+    POSTED.with(|p| std::mem::take(&mut *p.borrow_mut()))
+}
+
+fn record(kind: &str, msg: String) {
+    eprintln!("{}: {}", kind, msg);
+    POSTED.with(|p| p.borrow_mut().push(format!("{}: {}", kind, msg)));
+}
+
+//the real functions are variadic, we only ever pass a preformatted message or "%s" and a message
+unsafe fn message(s: *const c_char, arg: *const c_char) -> String {
+    let s = CStr::from_ptr(s).to_string_lossy();
+    if s == "%s" {
+        CStr::from_ptr(arg).to_string_lossy().into_owned()
+    } else {
+        s.into_owned()
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn error(s: *const c_char, arg: *const c_char) {
+    record("error", message(s, arg));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_error(
+    _x: *mut max_sys::t_object,
+    s: *const c_char,
+    arg: *const c_char,
+) {
+    record("object error", message(s, arg));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_warn(
+    _x: *mut max_sys::t_object,
+    s: *const c_char,
+    arg: *const c_char,
+) {
+    record("object warn", message(s, arg));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn object_post(
+    _x: *mut max_sys::t_object,
+    s: *const c_char,
+    arg: *const c_char,
+) {
+    record("object post", message(s, arg));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn systhread_ismainthread() -> std::os::raw::c_short {
+    MAIN_THREAD.with(|m| *m.borrow()) as _
+}
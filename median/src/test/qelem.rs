@@ -0,0 +1,61 @@
+use std::{
+    cell::RefCell,
+    ffi::c_void,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+//tests don't have a main thread loop, they flush by hand
+struct Qelem {
+    set: AtomicBool,
+    obj: *mut c_void,
+    func: max_sys::method,
+}
+
+thread_local! {
+    static LIVE: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Call the qelems created by the current thread that are set, like max's main thread loop would.
+pub unsafe fn run() {
+    let live = LIVE.with(|l| l.borrow().clone());
+    for x in live {
+        let q = &*(x as *const Qelem);
+        if q.set.swap(false, Ordering::Relaxed) {
+            if let Some(func) = q.func {
+                func(q.obj);
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qelem_new(obj: *mut c_void, func: max_sys::method) -> *mut c_void {
+    let x = Box::into_raw(Box::new(Qelem {
+        set: AtomicBool::new(false),
+        obj,
+        func,
+    }));
+    LIVE.with(|l| l.borrow_mut().push(x as usize));
+    x as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qelem_set(x: *mut max_sys::t_qelem) {
+    (*(x as *const Qelem)).set.store(true, Ordering::Relaxed);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qelem_front(x: *mut max_sys::t_qelem) {
+    qelem_set(x);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qelem_unset(x: *mut max_sys::t_qelem) {
+    (*(x as *const Qelem)).set.store(false, Ordering::Relaxed);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qelem_free(x: *mut max_sys::t_qelem) {
+    LIVE.with(|l| l.borrow_mut().retain(|q| *q != x as usize));
+    let _ = Box::from_raw(x as *mut Qelem);
+}
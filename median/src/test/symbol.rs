@@ -0,0 +1,33 @@
+use std::{collections::HashMap, ffi::CStr, os::raw::c_char, sync::Mutex};
+
+lazy_static::lazy_static! {
+    //symbols live forever in max, so we leak them here too
+    static ref SYMBOLS: Mutex<HashMap<Vec<u8>, usize>> = Mutex::new(HashMap::new());
+    static ref COMMON: usize = unsafe {
+        let mut table: Box<max_sys::_common_symbols_table> = Box::new(std::mem::zeroed());
+        table.s_nothing = gensym(b"\0".as_ptr() as _);
+        table.s_dictionary = gensym(b"dictionary\0".as_ptr() as _);
+        table.s_jit_matrix = gensym(b"jit_matrix\0".as_ptr() as _);
+        table.s_symbol = gensym(b"symbol\0".as_ptr() as _);
+        Box::into_raw(table) as usize
+    };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn common_symbols_gettable() -> *mut max_sys::_common_symbols_table {
+    *COMMON as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gensym(s: *const c_char) -> *mut max_sys::t_symbol {
+    let name = CStr::from_ptr(s);
+    let mut symbols = SYMBOLS.lock().unwrap();
+    let ptr = symbols.entry(name.to_bytes().to_vec()).or_insert_with(|| {
+        let s = Box::new(max_sys::t_symbol {
+            s_name: name.to_owned().into_raw(),
+            s_thing: std::ptr::null_mut(),
+        });
+        Box::into_raw(s) as usize
+    });
+    *ptr as _
+}
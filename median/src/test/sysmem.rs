@@ -1,16 +1,80 @@
 use core::ffi::c_void;
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::os::raw::c_long;
 
+//the size is stored in a header before the returned pointer so free and resize can find it
+const HEADER: usize = 16;
+
+unsafe fn layout(size: usize) -> Layout {
+    Layout::from_size_align_unchecked(size + HEADER, HEADER)
+}
+
+unsafe fn alloc(size: usize, zeroed: bool) -> *mut u8 {
+    let ptr = if zeroed {
+        System.alloc_zeroed(layout(size))
+    } else {
+        System.alloc(layout(size))
+    };
+    if ptr.is_null() {
+        return ptr;
+    }
+    *(ptr as *mut usize) = size;
+    ptr.add(HEADER)
+}
+
+unsafe fn size(ptr: *mut c_void) -> usize {
+    *((ptr as *mut u8).sub(HEADER) as *mut usize)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn sysmem_newptr(size: c_long) -> max_sys::t_ptr {
-    let vec: Vec<i8> = vec![0; size as _];
-    let mut slice = vec.into_boxed_slice();
-    let ptr = slice.as_mut_ptr();
-    std::mem::forget(slice);
-    ptr
+    alloc(size as _, false) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sysmem_newptrclear(size: c_long) -> max_sys::t_ptr {
+    alloc(size as _, true) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sysmem_resizeptr(ptr: *mut c_void, newsize: c_long) -> max_sys::t_ptr {
+    if ptr.is_null() {
+        return sysmem_newptr(newsize);
+    }
+    let old = size(ptr);
+    let ptr = System.realloc(
+        (ptr as *mut u8).sub(HEADER),
+        layout(old),
+        newsize as usize + HEADER,
+    );
+    if ptr.is_null() {
+        return ptr as _;
+    }
+    *(ptr as *mut usize) = newsize as _;
+    ptr.add(HEADER) as _
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn sysmem_freeptr(ptr: *mut c_void) {
-    Box::from_raw(ptr);
+    if !ptr.is_null() {
+        System.dealloc((ptr as *mut u8).sub(HEADER), layout(size(ptr)));
+    }
+}
+
+//copies at most size - 1 bytes and always null terminates
+#[no_mangle]
+pub unsafe extern "C" fn strncpy_zero(
+    dst: *mut std::os::raw::c_char,
+    src: *const std::os::raw::c_char,
+    size: c_long,
+) -> *mut std::os::raw::c_char {
+    if size > 0 {
+        let len = std::ffi::CStr::from_ptr(src)
+            .to_bytes()
+            .len()
+            .min(size as usize - 1);
+        std::ptr::copy_nonoverlapping(src, dst, len);
+        *dst.add(len) = 0;
+    }
+    dst
 }
@@ -6,27 +6,39 @@ use crate::{
     buffer::BufferRef,
     builder::{MSPWrappedBuilder, ManagedBufferRefInternal, MaxWrappedBuilder, WrappedBuilder},
     class::{Class, ClassType},
-    inlet::{FloatCB, IntCB},
-    method::{MaxFree, MaxMethod},
+    dictionary::Dictionary,
+    inlet::{ClassFallbacks, DefaultInlet, FloatCB, InletHandlers, InletMessage, IntCB, Proxy},
+    method::{MaxFree, MaxMethod, SelList, B},
     notify::Notification,
     object::{MSPObj, MaxObj, ObjBox},
+    outlet::MessageCount,
+    panic::{PanicPolicy, PanicState},
+    pattr::Modified,
     symbol::SymbolRef,
+    thread::SysThread,
 };
 
 use std::{
+    any::Any,
+    cell::RefCell,
     collections::HashMap,
     ffi::{c_char, c_void, CStr, CString},
     marker::PhantomData,
     mem::MaybeUninit,
     os::raw::c_long,
-    sync::Mutex,
+    sync::{atomic::AtomicBool, Arc, OnceLock},
 };
 
+use arc_swap::ArcSwap;
 use lazy_static::lazy_static;
+use parking_lot::ReentrantMutex;
 
 lazy_static! {
-    //type name -> ClassMaxObjWrapper
-    static ref CLASSES: Mutex<HashMap<&'static str, ClassMaxObjWrapper>> = Mutex::new(HashMap::new());
+    //type name -> ClassEntry, copied on write so lookups never lock
+    static ref CLASSES: ArcSwap<HashMap<&'static str, ClassEntry>> = ArcSwap::from_pointee(HashMap::new());
+    //serializes registration, reentrant so class_setup can register other classes. holds the
+    //classes that are part way through registering
+    static ref REGISTERING: ReentrantMutex<RefCell<Vec<&'static str>>> = ReentrantMutex::new(RefCell::new(Vec::new()));
 }
 
 const ASSIST_MAX: i64 = 512;
@@ -36,6 +48,9 @@ pub type MSPObjWrapper<T> = Wrapper<max_sys::t_pxobject, MSPWrapperInternal<T>,
 
 pub type FloatCBHash<T> = HashMap<usize, FloatCB<T>>;
 pub type IntCBHash<T> = HashMap<usize, IntCB<T>>;
+pub type InletHandlersHash<T> = HashMap<usize, InletHandlers<T>>;
+
+type FallbacksMethod = extern "C" fn(*mut c_void) -> *const ClassFallbacks;
 
 pub type DeferMethodWrapped<T> = extern "C" fn(
     wrapper: &T,
@@ -57,6 +72,8 @@ pub use median_macros::wrapped_attr_get_tramp as attr_get_tramp;
 pub use median_macros::wrapped_attr_set_tramp as attr_set_tramp;
 ///trampoline for deffered calls
 pub use median_macros::wrapped_defer_tramp as defer_tramp;
+///trampoline for methods that give a value back to `object_method_typed` callers
+pub use median_macros::wrapped_gimmeback_tramp as gimmeback_tramp;
 ///trampoline for list methods (ditch selector)
 pub use median_macros::wrapped_list_tramp as list_tramp;
 ///trampoline for seletor list methods
@@ -64,11 +81,28 @@ pub use median_macros::wrapped_sel_list_tramp as sel_list_tramp;
 ///general use trampoline
 pub use median_macros::wrapped_tramp as tramp;
 
-//we only use ClassMaxObjWrapper in CLASSES after we've registered the class, for max's usage this is
-//Send
-#[repr(transparent)]
-struct ClassMaxObjWrapper(*mut max_sys::t_class);
-unsafe impl Send for ClassMaxObjWrapper {}
+//we only use ClassEntry in CLASSES after we've registered the class, for max's usage this is
+//Send and Sync
+#[derive(Copy, Clone)]
+struct ClassEntry {
+    class: *mut max_sys::t_class,
+    //a ClassShared<T>, leaked because max classes are never unregistered
+    shared: &'static (dyn Any + Send + Sync),
+}
+unsafe impl Send for ClassEntry {}
+unsafe impl Sync for ClassEntry {}
+
+/// State shared by every instance of a wrapped class: its [`ClassData`] and the live instances.
+pub struct ClassShared<T: ObjWrapped<T>> {
+    //a T::Data for classes that implement ClassData, created on first use
+    data: OnceLock<Box<dyn Any + Send + Sync>>,
+    //addresses of the wrapped values of live instances, they don't own the instances. reentrant
+    //so for_each_instance callbacks can create instances and count them
+    instances: ReentrantMutex<RefCell<Vec<usize>>>,
+    //the class' own methods for messages that inlet handlers don't handle
+    fallbacks: ClassFallbacks,
+    _phantom: PhantomData<fn() -> T>,
+}
 
 /// A trait used by both Max and MSP objects.
 ///
@@ -79,10 +113,22 @@ pub trait ObjWrapped<T>: Sized + Sync + 'static {
     /// The name of your class, this is what you'll type into a box in Max if your class is a
     /// `ClassType::Box`.
     ///
-    /// You can add additional aliases in the `class_setup` method.
+    /// You can add additional names with [`class_aliases`](#method.class_aliases).
     fn class_name() -> &'static str;
 
+    /// Additional names for your class, registered with `class_alias`. Defaults to none.
+    ///
+    /// # Remarks
+    /// * The `external!` macro implements this from `#[alias("name")]` attributes on your struct.
+    fn class_aliases() -> &'static [&'static str] {
+        &[]
+    }
+
     /// The type of your class. Defaults to 'box' which creates visual objects in Max.
+    ///
+    /// # Remarks
+    /// * `ClassType::NoBox` classes are hidden helpers, create them from Rust with `new` on the
+    /// wrapper or from any external with [`Object::new_typed`](../object/struct.Object.html#method.new_typed).
     fn class_type() -> ClassType {
         ClassType::Box
     }
@@ -99,6 +145,218 @@ pub trait ObjWrapped<T>: Sized + Sync + 'static {
         .unwrap();
         render(v.as_c_str());
     }
+
+    /// What to do with an instance of your class after one of its methods panics. Defaults to
+    /// `PanicPolicy::Continue`.
+    ///
+    /// # Remarks
+    /// * Panics are always caught and reported to the Max console for the instance, they never
+    /// unwind into Max.
+    fn panic_policy() -> PanicPolicy {
+        PanicPolicy::Continue
+    }
+
+    /// Should `@attr value` creation arguments be applied to the attributes of new instances,
+    /// with `attr_args_process`. Defaults to `true`.
+    ///
+    /// # Remarks
+    /// * Return `false` to handle them yourself, [`creation_args`](../builder/trait.ObjBuilder.html#tymethod.creation_args)
+    /// has all of the arguments.
+    fn process_attr_args() -> bool {
+        true
+    }
+
+    /// Do instances of your class add inlets that route their own messages: `Handlers` inlets,
+    /// cold inlets, `Float` and `Int` inlets past the ninth, or a signal only default inlet.
+    /// Defaults to `false`.
+    ///
+    /// # Remarks
+    /// * When `true`, median handles `bang`, `int`, `float`, `list`, `anything` and your variable
+    /// argument selectors, routing them to the inlet they arrived at. Your methods for them get
+    /// the messages that the inlet doesn't handle.
+    /// * When `false`, those messages go straight to your methods and Max spreads lists sent to
+    /// the default inlet over `Float` and `Int` inlets.
+    /// * Building an instance with such inlets panics if this is `false`.
+    fn inlet_dispatch() -> bool {
+        false
+    }
+
+    /// The lifecycle hooks below that your class implements, only these are registered with Max.
+    /// Defaults to none.
+    ///
+    /// # Remarks
+    /// * The `external!` macro implements this from the hook methods you implement.
+    fn hooks() -> Hooks {
+        Hooks::default()
+    }
+
+    /// Called when the patcher that contains the object has finished loading, and whenever
+    /// `loadbang` is sent to the patcher.
+    fn loadbang(&self) {}
+
+    /// Called when the object's box is double clicked in a locked patcher.
+    fn dblclick(&self) {}
+
+    /// Is inlet `index` cold, `None` uses the hint from the inlet's
+    /// [`InletHandlers`](../inlet/struct.InletHandlers.html).
+    fn inletinfo(&self, _index: usize) -> Option<bool> {
+        None
+    }
+
+    /// Called when DSP is turned on or off. MSP objects only.
+    fn dspstate(&self, _on: bool) {}
+
+    /// Called when the patcher that contains the object is renamed.
+    fn patcherrename(&self) {}
+
+    /// Called just before the object is freed, while its outlets and clocks still work. Always
+    /// called, it doesn't need to be listed in [`hooks`](#method.hooks).
+    fn pre_free(&self) {}
+
+    /// State that isn't in attributes, to save with the patcher, `None` saves nothing.
+    ///
+    /// # Remarks
+    /// * Called when the patcher is saved, the atoms are stored in the object's box dictionary.
+    /// * Use [`to_atoms`](../atom/fn.to_atoms.html) and [`from_atoms`](../atom/fn.from_atoms.html)
+    /// to save serde types.
+    fn save_state(&self) -> Option<Vec<Atom>> {
+        None
+    }
+
+    /// Restore the state from [`save_state`](#method.save_state), called after `new` when the
+    /// object is loaded from a saved patcher. Only called if `save_state` is listed in
+    /// [`hooks`](#method.hooks).
+    fn restore_state(&self, _state: &[Atom]) {}
+
+    /// The value of your object for `pattr` and `pattrstorage`, `None` if it has no value.
+    ///
+    /// # Remarks
+    /// * Implementing this makes attribute changes notify `pattr`, use a
+    /// [`Notifier`](../pattr/struct.Notifier.html) to notify it of other changes.
+    /// * Use [`to_atoms`](../atom/fn.to_atoms.html) and [`from_atoms`](../atom/fn.from_atoms.html)
+    /// to map a serde state type to atoms.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// #[derive(Serialize, Deserialize)]
+    /// struct State {
+    ///     steps: Vec<t_atom_long>,
+    ///     rate: f64,
+    /// }
+    ///
+    /// fn getvalueof(&self) -> Option<Vec<Atom>> {
+    ///     to_atoms(&*self.state.lock()).ok()
+    /// }
+    ///
+    /// fn setvalueof(&self, value: &[Atom]) {
+    ///     if let Ok(state) = from_atoms(value) {
+    ///         *self.state.lock() = state;
+    ///     }
+    /// }
+    /// ```
+    fn getvalueof(&self) -> Option<Vec<Atom>> {
+        None
+    }
+
+    /// Set the value of your object, recalled by `pattr`, `pattrstorage` or `preset`. Always
+    /// called on the main thread.
+    ///
+    /// # Remarks
+    /// * Doesn't notify `pattr`, since it is the source of the value.
+    fn setvalueof(&self, _value: &[Atom]) {}
+
+    /// The value to store in a `preset` snapshot, `None` stores nothing. Recalling the snapshot
+    /// calls [`setvalueof`](#method.setvalueof).
+    ///
+    /// # Remarks
+    /// * Usually the same as [`getvalueof`](#method.getvalueof).
+    fn preset(&self) -> Option<Vec<Atom>> {
+        None
+    }
+}
+
+/// Access to the state shared by all instances of a wrapped class, implemented for every
+/// [`ObjWrapped`] type.
+///
+/// # Examples
+///
+/// ```ignore
+/// use median::wrapper::WrappedClass;
+///
+/// //from any of your methods
+/// let count = Self::instance_count();
+/// Self::for_each_instance(|i| i.refresh());
+/// ```
+pub trait WrappedClass: ObjWrapped<Self> {
+    /// Call `func` for every live instance of your class.
+    ///
+    /// # Remarks
+    /// * The instance registry is locked while `func` runs, so instances can't be freed out from
+    /// under it by other threads, but it also means `func` must not free instances of your class.
+    /// * `func` can create instances, they aren't visited, and it can count them.
+    /// * Instances are added once their constructor returns, so an instance doesn't see itself
+    /// from its own constructor.
+    fn for_each_instance<F: FnMut(&Self)>(func: F) {
+        class_shared::<Self>().for_each(func)
+    }
+
+    /// Get the number of live instances of your class.
+    fn instance_count() -> usize {
+        class_shared::<Self>().len()
+    }
+}
+
+impl<T: ObjWrapped<T>> WrappedClass for T {}
+
+/// Data shared by all instances of your class, implement this if your class needs any.
+///
+/// # Examples
+///
+/// ```ignore
+/// use median::wrapper::ClassData;
+///
+/// impl ClassData for MyExtern {
+///     type Data = Mutex<Vec<SymbolRef>>;
+///
+///     fn new_class_data() -> Self::Data {
+///         Mutex::new(Vec::new())
+///     }
+/// }
+///
+/// //from any of your methods
+/// Self::class_data().lock().push(name);
+/// ```
+pub trait ClassData: ObjWrapped<Self> {
+    /// The type of the shared data.
+    type Data: Send + Sync + 'static;
+
+    /// Create the data, called once, the first time [`class_data`](#method.class_data) is called.
+    fn new_class_data() -> Self::Data;
+
+    /// Get the data shared by all instances of your class.
+    ///
+    /// # Panics
+    /// * If your class hasn't been registered.
+    ///
+    /// # Remarks
+    /// * This looks up the class, if you need the data in `perform` get it in your constructor
+    /// and keep the reference.
+    fn class_data() -> &'static Self::Data {
+        class_shared::<Self>().data()
+    }
+}
+
+/// The [`ObjWrapped`] lifecycle hooks that a class implements.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hooks {
+    pub loadbang: bool,
+    pub dblclick: bool,
+    pub inletinfo: bool,
+    pub dspstate: bool,
+    pub patcherrename: bool,
+    pub save_state: bool,
+    pub getvalueof: bool,
+    pub preset: bool,
 }
 
 /// The trait to implement for your object to be wrapped as a Max object.
@@ -154,6 +412,28 @@ pub trait MSPObjWrapped<T>: ObjWrapped<T> {
 pub trait WrapperWrapped<T> {
     /// Retrieve a reference to your wrapped class.
     fn wrapped(&self) -> &T;
+
+    /// Call `func` with a reference to your wrapped class, catching any panic.
+    ///
+    /// # Arguments
+    /// * `what` - the name of the method being called, used when reporting a panic.
+    /// * `func` - the code to run.
+    ///
+    /// # Remarks
+    /// * A panic is reported with `object_error` and then the class' `panic_policy` is applied.
+    /// * Returns `None` if `func` panicked or wasn't called because the object is disabled.
+    /// * Counts as a message to the object, see
+    /// [`MessageCount`](../outlet/struct.MessageCount.html).
+    fn with_wrapped<R, F: FnOnce(&T) -> R>(&self, what: &str, func: F) -> Option<R>;
+
+    /// Tell `pattr` that the value of your object has changed, on the main thread. Does nothing
+    /// unless your class implements [`getvalueof`](trait.ObjWrapped.html#method.getvalueof) or
+    /// asked for a [`Notifier`](../pattr/struct.Notifier.html).
+    ///
+    /// # Remarks
+    /// * Called by the attribute setter trampolines after a successful set.
+    /// * Does nothing by default.
+    fn notify_modified(&self) {}
 }
 
 /// Defer methods for wrapped objects.
@@ -169,6 +449,8 @@ pub trait WrappedDefer<T> {
 pub struct Wrapper<O, I, T> {
     s_obj: O,
     wrapped: MaybeUninit<I>,
+    //false until wrapped is written, so a failed construction doesn't drop uninitialized memory
+    initialized: bool,
     _phantom: PhantomData<T>,
 }
 
@@ -177,9 +459,14 @@ pub struct MaxWrapperInternal<T> {
     wrapped: T,
     callbacks_float: FloatCBHash<T>,
     callbacks_int: IntCBHash<T>,
+    inlet_handlers: InletHandlersHash<T>,
+    default_inlet: DefaultInlet,
+    messages: Arc<MessageCount>,
+    pattr: Option<Modified>,
     buffer_refs: Vec<ManagedBufferRefInternal>,
     assist_ins: HashMap<usize, CString>,
     assist_outs: HashMap<usize, CString>,
+    panic_state: PanicState,
     //we just hold onto these so they don't get deallocated until later
     _proxy_inlets: Vec<crate::inlet::Proxy>,
 }
@@ -191,9 +478,16 @@ pub struct MSPWrapperInternal<T> {
     outs: Vec<MaybeUninit<&'static mut [f64]>>,
     callbacks_float: FloatCBHash<T>,
     callbacks_int: IntCBHash<T>,
+    inlet_handlers: InletHandlersHash<T>,
+    default_inlet: DefaultInlet,
+    messages: Arc<MessageCount>,
+    pattr: Option<Modified>,
     buffer_refs: Vec<ManagedBufferRefInternal>,
     assist_ins: HashMap<usize, CString>,
     assist_outs: HashMap<usize, CString>,
+    panic_state: PanicState,
+    //set once a real-time violation has been reported, see rt::check
+    rt_reported: AtomicBool,
     //we just hold onto these so they don't get deallocated until later
     _proxy_inlets: Vec<crate::inlet::Proxy>,
 }
@@ -206,9 +500,16 @@ pub trait WrapperInternal<O, T>: Sized {
 
     fn call_float(&self, index: usize, value: f64);
     fn call_int(&self, index: usize, value: max_sys::t_atom_long);
+    fn inlet_handlers(&self, index: usize) -> Option<&InletHandlers<T>>;
+    fn has_inlet_handlers(&self) -> bool;
+    fn default_inlet(&self) -> DefaultInlet;
 
     fn handle_notification(&self, notification: &Notification);
     fn assist(&self, io: c_long, index: c_long, s: *mut c_char);
+
+    fn panic_state(&self) -> &PanicState;
+    fn messages(&self) -> &MessageCount;
+    fn pattr(&self) -> Option<&Modified>;
 }
 
 unsafe impl<I, T> MaxObj for Wrapper<max_sys::t_object, I, T> {}
@@ -233,10 +534,15 @@ where
             wrapped,
             callbacks_float: std::mem::take(&mut f.callbacks_float),
             callbacks_int: std::mem::take(&mut f.callbacks_int),
+            inlet_handlers: std::mem::take(&mut f.inlet_handlers),
+            default_inlet: f.default_inlet,
+            messages: f.messages,
+            pattr: f.pattr.take(),
             buffer_refs: std::mem::take(&mut f.buffer_refs),
             _proxy_inlets: std::mem::take(&mut f.proxy_inlets),
             assist_ins: std::mem::take(&mut f.assist_ins),
             assist_outs: std::mem::take(&mut f.assist_outs),
+            panic_state: PanicState::default(),
         }
     }
     fn class_setup(class: &mut Class<Wrapper<max_sys::t_object, Self, T>>) {
@@ -252,6 +558,15 @@ where
             f(self.wrapped(), value);
         }
     }
+    fn inlet_handlers(&self, index: usize) -> Option<&InletHandlers<T>> {
+        self.inlet_handlers.get(&index)
+    }
+    fn has_inlet_handlers(&self) -> bool {
+        !self.inlet_handlers.is_empty()
+    }
+    fn default_inlet(&self) -> DefaultInlet {
+        self.default_inlet
+    }
     fn handle_notification(&self, notification: &Notification) {
         handle_buffer_ref_notifications(&self.buffer_refs, notification);
         self.wrapped().handle_notification(notification);
@@ -288,6 +603,15 @@ where
 
         self.wrapped().assist(iolet, render);
     }
+    fn panic_state(&self) -> &PanicState {
+        &self.panic_state
+    }
+    fn messages(&self) -> &MessageCount {
+        &self.messages
+    }
+    fn pattr(&self) -> Option<&Modified> {
+        self.pattr.as_ref()
+    }
 }
 
 impl<T> WrapperInternal<max_sys::t_pxobject, T> for MSPWrapperInternal<T>
@@ -321,10 +645,16 @@ where
             outs,
             callbacks_float: std::mem::take(&mut f.callbacks_float),
             callbacks_int: std::mem::take(&mut f.callbacks_int),
+            inlet_handlers: std::mem::take(&mut f.inlet_handlers),
+            default_inlet: f.default_inlet,
+            messages: f.messages,
+            pattr: f.pattr.take(),
             buffer_refs: std::mem::take(&mut f.buffer_refs),
             _proxy_inlets: std::mem::take(&mut f.proxy_inlets),
             assist_ins: std::mem::take(&mut f.assist_ins),
             assist_outs: std::mem::take(&mut f.assist_outs),
+            panic_state: PanicState::default(),
+            rt_reported: AtomicBool::new(false),
         }
     }
     fn class_setup(class: &mut Class<Wrapper<max_sys::t_pxobject, Self, T>>) {
@@ -340,6 +670,15 @@ where
             f(self.wrapped(), value);
         }
     }
+    fn inlet_handlers(&self, index: usize) -> Option<&InletHandlers<T>> {
+        self.inlet_handlers.get(&index)
+    }
+    fn has_inlet_handlers(&self) -> bool {
+        !self.inlet_handlers.is_empty()
+    }
+    fn default_inlet(&self) -> DefaultInlet {
+        self.default_inlet
+    }
     fn handle_notification(&self, notification: &Notification) {
         handle_buffer_ref_notifications(&self.buffer_refs, notification);
         self.wrapped().handle_notification(notification);
@@ -376,6 +715,15 @@ where
 
         self.wrapped().assist(iolet, render);
     }
+    fn panic_state(&self) -> &PanicState {
+        &self.panic_state
+    }
+    fn messages(&self) -> &MessageCount {
+        &self.messages
+    }
+    fn pattr(&self) -> Option<&Modified> {
+        self.pattr.as_ref()
+    }
 }
 
 fn handle_buffer_ref_notifications(
@@ -395,16 +743,14 @@ impl<T> MSPWrapperInternal<T>
 where
     T: MSPObjWrapped<T> + Sync + 'static,
 {
-    extern "C" fn perform64(
+    fn perform64(
         &mut self,
-        _dsp64: *mut max_sys::t_object,
+        obj: *mut max_sys::t_object,
         ins: *const *const f64,
         numins: c_long,
         outs: *mut *mut f64,
         numouts: c_long,
         sampleframes: c_long,
-        _flags: c_long,
-        _userparam: *mut c_void,
     ) {
         assert!(self.ins.len() >= numins as _);
         assert!(self.outs.len() >= numouts as _);
@@ -428,31 +774,122 @@ where
         //do a dance so we can access an immutable and a mutable at the same time
         let mut ins = std::mem::take(&mut self.ins);
         let mut outs = std::mem::take(&mut self.outs);
-        unsafe {
-            self.wrapped().perform(
+        let (ins_s, outs_s) = unsafe {
+            (
                 std::mem::transmute::<_, &[&[f64]]>(ins.as_slice()),
                 std::mem::transmute::<_, &mut [&mut [f64]]>(outs.as_mut_slice()),
-                nframes,
-            );
+            )
+        };
+        let wrapped = &self.wrapped;
+        let state = &self.panic_state;
+        if !crate::logging::with_object(obj, || {
+            state.call_perform(obj, T::panic_policy(), || {
+                crate::rt::check::run(obj, &self.rt_reported, || {
+                    wrapped.perform(ins_s, outs_s, nframes)
+                })
+            })
+        }) {
+            //silence after a panic or once disabled
+            for o in outs_s.iter_mut() {
+                o.fill(0.0);
+            }
         }
         std::mem::swap(&mut self.ins, &mut ins);
         std::mem::swap(&mut self.outs, &mut outs);
     }
 }
 
-fn new_common<F, O>(key: &'static str, func: F) -> O
+fn new_common<T, F, O>(func: F) -> O
 where
-    F: Fn(*mut max_sys::t_class) -> O,
+    T: ObjWrapped<T>,
+    F: FnOnce(*mut max_sys::t_class, &'static ClassShared<T>) -> O,
 {
-    //unlock the mutex so we can register in the object init
-    let max_class = {
-        let g = CLASSES.lock().expect("couldn't lock CLASSES mutex");
-        match g.get(key) {
-            Some(class) => class.0,
-            None => panic!("class {} not registered", key),
-        }
+    let key = key::<T>();
+    let (max_class, shared) = match class_entry(key) {
+        Some(entry) => (entry.class, downcast_shared(key, entry.shared)),
+        None => panic!("class {} not registered", key),
     };
-    func(max_class)
+    func(max_class, shared)
+}
+
+//lock free once registration is done
+fn class_entry(key: &str) -> Option<ClassEntry> {
+    CLASSES.load().get(key).copied()
+}
+
+fn downcast_shared<T: ObjWrapped<T>>(
+    key: &str,
+    shared: &'static (dyn Any + Send + Sync),
+) -> &'static ClassShared<T> {
+    shared
+        .downcast_ref()
+        .unwrap_or_else(|| panic!("class {} registered with another type", key))
+}
+
+/// Get the state shared by every instance of `T`.
+///
+/// # Panics
+/// * If `T` hasn't been registered.
+pub fn class_shared<T: ObjWrapped<T>>() -> &'static ClassShared<T> {
+    let key = key::<T>();
+    match class_entry(key) {
+        Some(entry) => downcast_shared(key, entry.shared),
+        None => panic!("class {} not registered", key),
+    }
+}
+
+impl<T: ObjWrapped<T>> ClassShared<T> {
+    fn new(fallbacks: ClassFallbacks) -> Self {
+        Self {
+            data: OnceLock::new(),
+            instances: ReentrantMutex::new(RefCell::new(Vec::new())),
+            fallbacks,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the class data, creating it if this is the first use.
+    pub fn data(&self) -> &T::Data
+    where
+        T: ClassData,
+    {
+        self.data
+            .get_or_init(|| Box::new(T::new_class_data()))
+            .downcast_ref()
+            .expect("class data has another type")
+    }
+
+    /// Call `func` for every live instance, see [`ObjWrapped::for_each_instance`].
+    pub fn for_each<F: FnMut(&T)>(&self, mut func: F) {
+        let g = self.instances.lock();
+        //iterate a copy so func can add instances, the lock is held until we're done
+        let instances = g.borrow().clone();
+        for p in instances {
+            func(unsafe { &*(p as *const T) });
+        }
+    }
+
+    /// Get the number of live instances.
+    pub fn len(&self) -> usize {
+        self.instances.lock().borrow().len()
+    }
+
+    /// Are there no live instances.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn add(&self, wrapped: &T) {
+        self.instances
+            .lock()
+            .borrow_mut()
+            .push(wrapped as *const T as usize);
+    }
+
+    fn remove(&self, wrapped: &T) {
+        let p = wrapped as *const T as usize;
+        self.instances.lock().borrow_mut().retain(|i| *i != p);
+    }
 }
 
 impl<O, I, T> WrapperWrapped<T> for Wrapper<O, I, T>
@@ -463,6 +900,16 @@ where
     fn wrapped(&self) -> &T {
         self.internal().wrapped()
     }
+
+    fn with_wrapped<R, F: FnOnce(&T) -> R>(&self, what: &str, func: F) -> Option<R> {
+        self.guard_message(what, || func(self.wrapped()))
+    }
+
+    fn notify_modified(&self) {
+        if let Some(m) = self.internal().pattr() {
+            m.notify();
+        }
+    }
 }
 
 //build up our float and int input trampolines, and the register fn
@@ -471,11 +918,11 @@ macro_rules! int_float_tramps {
         $(
             paste::paste! {
                 pub extern "C" fn [<call_in $i>](&self, value: max_sys::t_atom_long) {
-                    self.internal().call_int($i, value);
+                    self.guard_message(concat!("in", $i), || self.internal().call_int($i, value));
                 }
 
                 pub extern "C" fn [<call_ft $i>](&self, value: f64) {
-                    self.internal().call_float($i, value);
+                    self.guard_message(concat!("ft", $i), || self.internal().call_float($i, value));
                 }
             }
         )*
@@ -484,17 +931,15 @@ macro_rules! int_float_tramps {
             unsafe {
             $(
                 paste::paste! {
-                    let name = std::ffi::CString::new(concat!("ft", $i)).unwrap();
                     max_sys::class_addmethod(class,
                         Some(std::mem::transmute::<extern "C" fn(&Self, f64), crate::method::MaxMethod>(Self::[<call_ft $i>])),
-                        name.as_ptr(),
+                        crate::sym!(concat!("ft", $i)).as_cstr().as_ptr(),
                         max_sys::e_max_atomtypes::A_FLOAT, 0
                     );
 
-                    let name = std::ffi::CString::new(concat!("in", $i)).unwrap();
                     max_sys::class_addmethod(class,
                         Some(std::mem::transmute::<extern "C" fn(&Self, max_sys::t_atom_long), crate::method::MaxMethod>(Self::[<call_in $i>])),
-                        name.as_ptr(),
+                        crate::sym!(concat!("in", $i)).as_cstr().as_ptr(),
                         max_sys::e_max_atomtypes::A_LONG, 0
                     );
                 })*
@@ -512,18 +957,539 @@ where
         unsafe { &*self.wrapped.as_ptr() }
     }
 
+    fn obj_ptr(&self) -> *mut max_sys::t_object {
+        //the max object is always the first member
+        &self.s_obj as *const O as *mut max_sys::t_object
+    }
+
+    //call func, catching any panic and applying the class' policy
+    fn guard<R, F: FnOnce() -> R>(&self, what: &str, func: F) -> Option<R> {
+        let obj = self.obj_ptr();
+        crate::logging::with_object(obj, || {
+            self.internal()
+                .panic_state()
+                .call(obj, T::panic_policy(), what, func)
+        })
+    }
+
+    //guard a message sent to an inlet or method, counted so outlets disabled after an overflow
+    //are enabled again
+    fn guard_message<R, F: FnOnce() -> R>(&self, what: &str, func: F) -> Option<R> {
+        let _message = self.internal().messages().enter();
+        self.guard(what, func)
+    }
+
+    //route a message to the handlers of the inlet it arrived at, then to the class' methods
+    fn dispatch_inlet(&self, msg: InletMessage) {
+        let internal = self.internal();
+        let _message = internal.messages().enter();
+        let signal_only = internal.default_inlet() == DefaultInlet::SignalOnly;
+        //the inlet number is only meaningful if there are proxies with handlers
+        let inlet = if internal.has_inlet_handlers() || signal_only {
+            Proxy::get_inlet(self.obj_ptr())
+        } else {
+            0
+        };
+        if inlet == 0 && signal_only {
+            crate::object::error(
+                self.obj_ptr(),
+                format!(
+                    "doesn't understand \"{}\" in a signal inlet",
+                    msg.selector()
+                ),
+            );
+            return;
+        }
+        if let Some(handlers) = internal.inlet_handlers(inlet) {
+            let m = msg.clone();
+            //None means the handler panicked, which the guard has already reported
+            if self.guard("inlet", || handlers.dispatch(self.wrapped(), m)) != Some(false) {
+                return;
+            }
+        }
+        let fallbacks = &class_shared::<T>().fallbacks;
+        //the class' methods are trampolines, they guard themselves
+        unsafe {
+            match msg {
+                InletMessage::Bang => match fallbacks.bang {
+                    Some(f) => std::mem::transmute::<MaxMethod, B<Self>>(f)(self),
+                    None => self.dispatch_fallback(fallbacks, crate::sym!("bang"), &[]),
+                },
+                InletMessage::Int(v) => match (fallbacks.int, fallbacks.float) {
+                    (Some(f), _) => {
+                        std::mem::transmute::<MaxMethod, crate::method::I<Self>>(f)(self, v)
+                    }
+                    (None, Some(f)) => {
+                        std::mem::transmute::<MaxMethod, crate::method::F<Self>>(f)(self, v as _)
+                    }
+                    (None, None) => {
+                        self.dispatch_fallback(fallbacks, crate::sym!("int"), &[Atom::from(v)])
+                    }
+                },
+                InletMessage::Float(v) => match (fallbacks.float, fallbacks.int) {
+                    (Some(f), _) => {
+                        std::mem::transmute::<MaxMethod, crate::method::F<Self>>(f)(self, v)
+                    }
+                    (None, Some(f)) => {
+                        std::mem::transmute::<MaxMethod, crate::method::I<Self>>(f)(self, v as _)
+                    }
+                    (None, None) => {
+                        self.dispatch_fallback(fallbacks, crate::sym!("float"), &[Atom::from(v)])
+                    }
+                },
+                InletMessage::List(atoms) => match fallbacks.list {
+                    Some(f) => std::mem::transmute::<MaxMethod, SelList<Self>>(f)(
+                        self,
+                        crate::sym!("list").inner(),
+                        atoms.len() as _,
+                        atoms.as_ptr() as _,
+                    ),
+                    None => self.dispatch_fallback(fallbacks, crate::sym!("list"), atoms),
+                },
+                InletMessage::Anything(sel, atoms) => self.dispatch_fallback(fallbacks, sel, atoms),
+            }
+        }
+    }
+
+    unsafe fn dispatch_fallback(&self, fallbacks: &ClassFallbacks, sel: SymbolRef, atoms: &[Atom]) {
+        match fallbacks
+            .selectors
+            .get(&(sel.inner() as usize))
+            .or(fallbacks.anything.as_ref())
+        {
+            Some(f) => std::mem::transmute::<MaxMethod, SelList<Self>>(*f)(
+                self,
+                sel.inner(),
+                atoms.len() as _,
+                atoms.as_ptr() as _,
+            ),
+            None => crate::object::error(self.obj_ptr(), format!("doesn't understand \"{}\"", sel)),
+        }
+    }
+
+    extern "C" fn inlet_bang(&self) {
+        self.dispatch_inlet(InletMessage::Bang);
+    }
+
+    extern "C" fn inlet_int(&self, v: max_sys::t_atom_long) {
+        self.dispatch_inlet(InletMessage::Int(v));
+    }
+
+    extern "C" fn inlet_float(&self, v: f64) {
+        self.dispatch_inlet(InletMessage::Float(v));
+    }
+
+    extern "C" fn inlet_list(
+        &self,
+        sel: *mut max_sys::t_symbol,
+        ac: c_long,
+        av: *const max_sys::t_atom,
+    ) {
+        crate::method::sel_list(sel, ac, av, |_sel, atoms| {
+            self.dispatch_inlet(InletMessage::List(atoms))
+        });
+    }
+
+    extern "C" fn inlet_anything(
+        &self,
+        sel: *mut max_sys::t_symbol,
+        ac: c_long,
+        av: *const max_sys::t_atom,
+    ) {
+        crate::method::sel_list(sel, ac, av, |sel, atoms| {
+            self.dispatch_inlet(InletMessage::Anything(sel, atoms))
+        });
+    }
+
+    extern "C" fn inlet_info(&self, _b: *mut c_void, index: c_long, t: *mut c_char) {
+        let hook = if T::hooks().inletinfo {
+            self.guard("inletinfo", || self.wrapped().inletinfo(index as _))
+                .flatten()
+        } else {
+            None
+        };
+        let cold = hook.unwrap_or_else(|| {
+            self.internal()
+                .inlet_handlers(index as _)
+                .map(|h| h.is_cold())
+                .unwrap_or(false)
+        });
+        if cold {
+            unsafe {
+                *t = 1;
+            }
+        }
+    }
+
+    extern "C" fn loadbang_tramp(&self) {
+        self.guard("loadbang", || self.wrapped().loadbang());
+    }
+
+    extern "C" fn dblclick_tramp(&self) {
+        self.guard("dblclick", || self.wrapped().dblclick());
+    }
+
+    extern "C" fn dspstate_tramp(&self, on: c_long) {
+        self.guard("dspstate", || self.wrapped().dspstate(on != 0));
+    }
+
+    extern "C" fn patcherrename_tramp(&self) {
+        self.guard("patcherrename", || self.wrapped().patcherrename());
+    }
+
+    extern "C" fn appendtodictionary_tramp(&self, d: *mut max_sys::t_dictionary) {
+        if d.is_null() {
+            return;
+        }
+        if let Some(state) = self
+            .guard("save_state", || self.wrapped().save_state())
+            .flatten()
+        {
+            let mut dict = unsafe { Dictionary::from_raw_borrowed(d) };
+            if let Err(e) = dict.set_atoms(&crate::sym!("median_state"), &state) {
+                crate::object::error(self.obj_ptr(), format!("failed to save state: {}", e));
+            }
+        }
+    }
+
+    //restore the state saved with the patcher, the box dictionary is only available in new, and
+    //only for boxes
+    fn restore_saved_state(&self) {
+        if !matches!(T::class_type(), ClassType::Box) {
+            return;
+        }
+        let d = unsafe { (*crate::sym!("#D").inner()).s_thing } as *mut max_sys::t_dictionary;
+        if d.is_null() {
+            return;
+        }
+        let dict = unsafe { Dictionary::from_raw_borrowed(d) };
+        if let Some(state) = dict.get_atoms(&crate::sym!("median_state")) {
+            self.guard("restore_state", || self.wrapped().restore_state(&state));
+        }
+    }
+
+    extern "C" fn getvalueof_tramp(
+        &self,
+        ac: *mut c_long,
+        av: *mut *mut max_sys::t_atom,
+    ) -> max_sys::t_max_err {
+        let value = match self
+            .guard("getvalueof", || self.wrapped().getvalueof())
+            .flatten()
+        {
+            Some(value) if !ac.is_null() && !av.is_null() => value,
+            _ => return max_sys::e_max_errorcodes::MAX_ERR_GENERIC as _,
+        };
+        unsafe {
+            //reuses the caller's atoms if there are enough of them
+            let mut alloc: c_char = 0;
+            if max_sys::atom_alloc_array(value.len().max(1) as _, ac, av, &mut alloc)
+                != max_sys::e_max_errorcodes::MAX_ERR_NONE as max_sys::t_max_err
+            {
+                return max_sys::e_max_errorcodes::MAX_ERR_OUT_OF_MEM as _;
+            }
+            for (i, a) in value.iter().enumerate() {
+                *(*av).add(i) = a.value;
+            }
+            *ac = value.len() as _;
+        }
+        max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+    }
+
+    extern "C" fn setvalueof_tramp(
+        &self,
+        ac: c_long,
+        av: *mut max_sys::t_atom,
+    ) -> max_sys::t_max_err {
+        let value = atoms(ac, av);
+        self.recall(&value);
+        max_sys::e_max_errorcodes::MAX_ERR_NONE as _
+    }
+
+    //pattrstorage can recall from other threads, setvalueof is only called on the main thread
+    fn recall(&self, value: &[Atom]) {
+        if SysThread::is_main() {
+            self.guard("setvalueof", || self.wrapped().setvalueof(value));
+        } else {
+            crate::thread::defer_low(
+                unsafe {
+                    std::mem::transmute::<DeferMethodWrapped<Self>, crate::thread::DeferMethod>(
+                        Self::recall_tramp,
+                    )
+                },
+                self.obj_ptr(),
+                crate::sym!("setvalueof"),
+                value,
+            );
+        }
+    }
+
+    extern "C" fn recall_tramp(
+        &self,
+        _sym: *mut max_sys::t_symbol,
+        ac: c_long,
+        av: *const max_sys::t_atom,
+    ) {
+        let value = atoms(ac, av);
+        self.guard("setvalueof", || self.wrapped().setvalueof(&value));
+    }
+
+    //add the value to the snapshot that preset is storing, preset sends it back to us as a
+    //median_preset message
+    extern "C" fn preset_tramp(&self) {
+        let z = unsafe { (*crate::sym!("_preset").inner()).s_thing };
+        if z.is_null() {
+            return;
+        }
+        if let Some(value) = self.guard("preset", || self.wrapped().preset()).flatten() {
+            let obj = self.obj_ptr();
+            let mut message = vec![
+                Atom::from(obj as *mut c_void),
+                Atom::from(SymbolRef::from(unsafe {
+                    max_sys::object_classname(obj as _)
+                })),
+                Atom::from(crate::sym!("median_preset")),
+            ];
+            message.extend(value);
+            unsafe {
+                max_sys::binbuf_insert(
+                    z as _,
+                    std::ptr::null_mut(),
+                    message.len() as _,
+                    message.as_mut_ptr() as _,
+                );
+            }
+        }
+    }
+
+    extern "C" fn median_preset_tramp(
+        &self,
+        _sym: *mut max_sys::t_symbol,
+        ac: c_long,
+        av: *const max_sys::t_atom,
+    ) {
+        self.recall(&atoms(ac, av));
+    }
+
+    //register the lifecycle hooks that the class implements
+    fn register_hooks(c: &mut Class<Self>) {
+        let hooks = T::hooks();
+        let class = c.inner();
+        let add = |m: MaxMethod, sel: &str| unsafe {
+            let sel = CString::new(sel).expect("couldn't convert selector to CString");
+            max_sys::class_addmethod(
+                class,
+                Some(m),
+                sel.as_ptr(),
+                max_sys::e_max_atomtypes::A_CANT,
+                0,
+            );
+        };
+        type Hook<W> = extern "C" fn(&W);
+        unsafe {
+            if hooks.loadbang {
+                add(
+                    std::mem::transmute::<Hook<Self>, MaxMethod>(Self::loadbang_tramp),
+                    "loadbang",
+                );
+            }
+            if hooks.dblclick {
+                add(
+                    std::mem::transmute::<Hook<Self>, MaxMethod>(Self::dblclick_tramp),
+                    "dblclick",
+                );
+            }
+            if hooks.dspstate {
+                add(
+                    std::mem::transmute::<extern "C" fn(&Self, c_long), MaxMethod>(
+                        Self::dspstate_tramp,
+                    ),
+                    "dspstate",
+                );
+            }
+            //the hint comes from the inlet handlers if the class doesn't implement the hook, and
+            //don't replace an inletinfo the class registered itself
+            if (hooks.inletinfo || T::inlet_dispatch())
+                && max_sys::class_method(class, crate::sym!("inletinfo").inner()).is_none()
+            {
+                add(
+                    std::mem::transmute::<
+                        extern "C" fn(&Self, *mut c_void, c_long, *mut c_char),
+                        MaxMethod,
+                    >(Self::inlet_info),
+                    "inletinfo",
+                );
+            }
+            if hooks.patcherrename {
+                add(
+                    std::mem::transmute::<Hook<Self>, MaxMethod>(Self::patcherrename_tramp),
+                    "patcherrename",
+                );
+            }
+            if hooks.save_state {
+                add(
+                    std::mem::transmute::<
+                        extern "C" fn(&Self, *mut max_sys::t_dictionary),
+                        MaxMethod,
+                    >(Self::appendtodictionary_tramp),
+                    "appendtodictionary",
+                );
+            }
+            if hooks.getvalueof {
+                add(
+                    std::mem::transmute::<
+                        extern "C" fn(
+                            &Self,
+                            *mut c_long,
+                            *mut *mut max_sys::t_atom,
+                        ) -> max_sys::t_max_err,
+                        MaxMethod,
+                    >(Self::getvalueof_tramp),
+                    "getvalueof",
+                );
+            }
+            if hooks.getvalueof || hooks.preset {
+                add(
+                    std::mem::transmute::<
+                        extern "C" fn(&Self, c_long, *mut max_sys::t_atom) -> max_sys::t_max_err,
+                        MaxMethod,
+                    >(Self::setvalueof_tramp),
+                    "setvalueof",
+                );
+            }
+            if hooks.preset {
+                add(
+                    std::mem::transmute::<Hook<Self>, MaxMethod>(Self::preset_tramp),
+                    "preset",
+                );
+                let sel = CString::new("median_preset").unwrap();
+                max_sys::class_addmethod(
+                    class,
+                    Some(std::mem::transmute::<DeferMethodWrapped<Self>, MaxMethod>(
+                        Self::median_preset_tramp,
+                    )),
+                    sel.as_ptr(),
+                    max_sys::e_max_atomtypes::A_GIMME,
+                    0,
+                );
+            }
+        }
+    }
+
+    //register the methods that route messages to inlet handlers, taking over the methods that the
+    //class registered for the same messages
+    fn register_dispatch(c: &mut Class<Self>) -> ClassFallbacks {
+        let fallbacks = c.take_fallbacks();
+        let class = c.inner();
+        let add = |m: MaxMethod, sel: &str, t: max_sys::e_max_atomtypes::Type| unsafe {
+            let sel = CString::new(sel).expect("couldn't convert selector to CString");
+            max_sys::class_addmethod(class, Some(m), sel.as_ptr(), t, 0);
+        };
+        unsafe {
+            use max_sys::e_max_atomtypes::{A_CANT, A_FLOAT, A_GIMME, A_LONG, A_NOTHING};
+            add(
+                std::mem::transmute::<B<Self>, MaxMethod>(Self::inlet_bang),
+                "bang",
+                A_NOTHING,
+            );
+            add(
+                std::mem::transmute::<crate::method::I<Self>, MaxMethod>(Self::inlet_int),
+                "int",
+                A_LONG,
+            );
+            add(
+                std::mem::transmute::<crate::method::F<Self>, MaxMethod>(Self::inlet_float),
+                "float",
+                A_FLOAT,
+            );
+            add(
+                std::mem::transmute::<SelList<Self>, MaxMethod>(Self::inlet_list),
+                "list",
+                A_GIMME,
+            );
+            add(
+                std::mem::transmute::<SelList<Self>, MaxMethod>(Self::inlet_anything),
+                "anything",
+                A_GIMME,
+            );
+            for sel in fallbacks.selectors.keys() {
+                let sel = *sel as *mut max_sys::t_symbol;
+                max_sys::class_addmethod(
+                    class,
+                    Some(std::mem::transmute::<SelList<Self>, MaxMethod>(
+                        Self::inlet_anything,
+                    )),
+                    (*sel).s_name,
+                    A_GIMME,
+                    0,
+                );
+            }
+            //so a later registration that finds this class in max can get the fallbacks
+            add(
+                std::mem::transmute::<FallbacksMethod, MaxMethod>(Self::fallbacks_tramp),
+                "median_fallbacks",
+                A_CANT,
+            );
+        }
+        fallbacks
+    }
+
+    extern "C" fn fallbacks_tramp(_x: *mut c_void) -> *const ClassFallbacks {
+        //null if the class isn't registered, so this never panics
+        match class_entry(key::<T>()) {
+            Some(entry) => &downcast_shared::<T>(key::<T>(), entry.shared).fallbacks,
+            None => std::ptr::null(),
+        }
+    }
+
+    //the fallbacks of a class that was already registered with max, by another copy of this code
+    fn existing_fallbacks(class: *mut max_sys::t_class) -> ClassFallbacks {
+        unsafe {
+            match max_sys::class_method(class, crate::sym!("median_fallbacks").inner()) {
+                Some(f) => {
+                    std::mem::transmute::<MaxMethod, FallbacksMethod>(f)(std::ptr::null_mut())
+                        .as_ref()
+                        .cloned()
+                        .unwrap_or_default()
+                }
+                None => ClassFallbacks::default(),
+            }
+        }
+    }
+
     /// Retrieve a mutable reference to your wrapped class.
     pub fn wrapped_mut(&mut self) -> &mut T {
         unsafe { (&mut *self.wrapped.as_mut_ptr()).wrapped_mut() }
     }
 
     extern "C" fn free_wrapped(&mut self) {
+        //construction failed, nothing to free
+        if !self.initialized {
+            return;
+        }
+        //remove from the registry before dropping, so for_each_instance never sees a freed instance
+        if let Some(entry) = class_entry(key::<T>()) {
+            let shared = downcast_shared::<T>(key::<T>(), entry.shared);
+            shared.remove(self.wrapped());
+        }
+        self.guard("pre_free", || self.wrapped().pre_free());
+        //a notifier can outlive the object, stop it from notifying before the object is gone
+        if let Some(m) = self.internal().pattr() {
+            m.free();
+        }
+        self.initialized = false;
         //free wrapped
         let mut wrapped = MaybeUninit::uninit();
         std::mem::swap(&mut self.wrapped, &mut wrapped);
-        unsafe {
-            std::mem::drop(wrapped.assume_init());
-        }
+        let obj = self.obj_ptr();
+        crate::logging::with_object(obj, || {
+            crate::panic::catch(obj, "free", || unsafe {
+                std::mem::drop(wrapped.assume_init());
+            })
+        });
+        //queued log messages can refer to this object, write them while it still exists
+        crate::logging::flush();
+        crate::rt::flush();
     }
 
     fn register_common<F>(
@@ -547,54 +1513,114 @@ where
         F: Fn() -> Class<Self>,
     {
         let key = key::<T>();
-        let mut h = CLASSES.lock().expect("couldn't lock CLASSES mutex");
-        if !h.contains_key(key) {
-            //don't lookup class unless we want to, because max might try to register it which
-            //could cause a loop
-            let existing = if lookup_class {
-                Class::<T>::find_in_max(T::class_name(), T::class_type())
+        //so the audio thread never has to create the real-time console
+        crate::rt::init();
+        if class_entry(key).is_some() {
+            return;
+        }
+        let registering = REGISTERING.lock();
+        //already registered by another thread while we waited for the lock
+        if class_entry(key).is_some() {
+            return;
+        }
+        //further up this thread's stack, the class isn't usable until that registration is done
+        if registering.borrow().contains(&key) {
+            crate::error(format!(
+                "{} registered from its own class setup, it can't be used until setup is done",
+                T::class_name()
+            ));
+            return;
+        }
+        registering.borrow_mut().push(key);
+        //remove the key even if class setup panics, so a later attempt can register
+        struct Done<'a>(&'a RefCell<Vec<&'static str>>, &'static str);
+        impl Drop for Done<'_> {
+            fn drop(&mut self) {
+                self.0.borrow_mut().retain(|k| *k != self.1);
+            }
+        }
+        let _done = Done(&registering, key);
+        //don't lookup class unless we want to, because max might try to register it which
+        //could cause a loop
+        let existing = if lookup_class {
+            Class::<T>::find_in_max(T::class_name(), T::class_type())
+        } else {
+            std::ptr::null_mut()
+        };
+        let (max_class, fallbacks) = if existing.is_null() {
+            let mut c = creator();
+            let fallbacks = if T::inlet_dispatch() {
+                Self::register_dispatch(&mut c)
             } else {
-                std::ptr::null_mut()
+                ClassFallbacks::default()
             };
-            let max_class = if existing.is_null() {
-                let mut c = creator();
-                let notify = std::ffi::CString::new("notify").unwrap();
-                let assist = std::ffi::CString::new("assist").unwrap();
-                unsafe {
-                    //register notifications
-                    max_sys::class_addmethod(
-                        c.inner(),
-                        Some(std::mem::transmute::<_, MaxMethod>(notification_handler)),
-                        notify.as_ptr(),
-                        max_sys::e_max_atomtypes::A_CANT,
-                        0,
-                    );
-                    //assist
-                    max_sys::class_addmethod(
-                        c.inner(),
-                        Some(std::mem::transmute::<_, MaxMethod>(assist_tramp)),
-                        assist.as_ptr(),
-                        max_sys::e_max_atomtypes::A_CANT,
-                        0,
-                    );
+            Self::register_hooks(&mut c);
+            unsafe {
+                //register notifications
+                max_sys::class_addmethod(
+                    c.inner(),
+                    Some(std::mem::transmute::<_, MaxMethod>(notification_handler)),
+                    crate::sym!("notify").as_cstr().as_ptr(),
+                    max_sys::e_max_atomtypes::A_CANT,
+                    0,
+                );
+                //assist
+                max_sys::class_addmethod(
+                    c.inner(),
+                    Some(std::mem::transmute::<_, MaxMethod>(assist_tramp)),
+                    crate::sym!("assist").as_cstr().as_ptr(),
+                    max_sys::e_max_atomtypes::A_CANT,
+                    0,
+                );
+            }
+            c.register(T::class_type())
+                .expect(format!("failed to register {}", key).as_str());
+
+            //aliases can only be added to a registered class
+            for alias in T::class_aliases() {
+                if let Err(e) = c.add_alias(alias) {
+                    crate::error(format!(
+                        "failed to add alias {} for {}: {}",
+                        alias,
+                        T::class_name(),
+                        e
+                    ));
                 }
-                c.register(T::class_type())
-                    .expect(format!("failed to register {}", key).as_str());
+            }
 
-                //register our ft1, ft2.. in1, in2.. tramps
-                Self::register_ft_in(c.inner());
-                c.inner()
-            } else {
-                existing
-            };
+            //register our ft1, ft2.. in1, in2.. tramps
+            Self::register_ft_in(c.inner());
+            (c.inner(), fallbacks)
+        } else {
+            (existing, Self::existing_fallbacks(existing))
+        };
 
-            h.insert(key, ClassMaxObjWrapper(max_class));
-        }
+        let shared: &'static ClassShared<T> = Box::leak(Box::new(ClassShared::new(fallbacks)));
+        let entry = ClassEntry {
+            class: max_class,
+            shared,
+        };
+        CLASSES.rcu(|classes| {
+            let mut classes = HashMap::clone(classes);
+            classes.insert(key, entry);
+            classes
+        });
     }
 
     int_float_tramps!(1, 2, 3, 4, 5, 6, 7, 8, 9);
 }
 
+//copy atoms that max owns
+fn atoms(ac: c_long, av: *const max_sys::t_atom) -> Vec<Atom> {
+    if av.is_null() || ac < 1 {
+        return Vec::new();
+    }
+    unsafe { std::slice::from_raw_parts(av, ac as _) }
+        .iter()
+        .map(|a| Atom { value: *a })
+        .collect()
+}
+
 fn key<T>() -> &'static str {
     std::any::type_name::<T>()
 }
@@ -607,11 +1633,13 @@ where
     ///
     /// # Remarks
     ///
-    /// This method expects to only be called from the main thread. Internally, it locks a mutex
-    /// and looks up your class by type name. If your class has alrady been registered it won't
-    /// re-register.
+    /// This method expects to only be called from the main thread. It looks up your class by type
+    /// name, if your class has already been registered it won't re-register.
     ///
-    /// This will deadlock if you call `register()` again inside your `T::class_setup()`.
+    /// Your `T::class_setup()` can register other classes that your class depends on, like helper
+    /// `ClassType::NoBox` classes.
+    /// Registering a class from its own `class_setup`, directly or through the classes it
+    /// registers, reports an error and does nothing.
     pub unsafe fn register(lookup_class: bool) {
         Self::register_common(
             lookup_class,
@@ -627,7 +1655,9 @@ where
                         ),
                     ),
                 );
-                //TODO somehow pass the lock so that classes can register additional classes
+                if T::inlet_dispatch() {
+                    c.capture_fallbacks();
+                }
                 MaxWrapperInternal::<T>::class_setup(&mut c);
                 c
             },
@@ -642,9 +1672,13 @@ where
     ) -> *mut c_void {
         let sym: SymbolRef = sym.into();
         let args = std::slice::from_raw_parts(std::mem::transmute::<_, _>(argv), argc as usize);
-        let o = ObjBox::into_raw(Self::new(sym, &args));
-        assert_eq!((&*o).max_obj(), (&*o).wrapped().max_obj());
-        std::mem::transmute::<_, _>(o)
+        //a panic drops the partially constructed object and reports, max gets null
+        crate::panic::catch(std::ptr::null_mut(), T::class_name(), || {
+            let o = ObjBox::into_raw(Self::new(sym, &args));
+            assert_eq!((&*o).max_obj(), (&*o).wrapped().max_obj());
+            std::mem::transmute::<_, _>(o)
+        })
+        .unwrap_or(std::ptr::null_mut())
     }
 
     /// Create an instance of the wrapper, on the heap, with no arguments.
@@ -654,17 +1688,23 @@ where
 
     /// Create an instance of the wrapper, on the heap.
     pub fn new(sym: SymbolRef, args: &[Atom]) -> ObjBox<Self> {
-        new_common(key::<T>(), |max_class| unsafe {
+        new_common(|max_class, shared: &'static ClassShared<T>| unsafe {
             let mut o: ObjBox<Self> = ObjBox::alloc(max_class);
+            o.initialized = false;
             let internal = MaxWrapperInternal::<T>::new(o.max_obj(), sym.clone(), args);
             o.wrapped = MaybeUninit::new(internal);
-            //process attribute arguments
-            //TODO optionally don't process?
-            max_sys::attr_args_process(
-                o.max_obj() as _,
-                args.len() as _,
-                std::mem::transmute::<_, _>(args.as_ptr()), //casts to mutable but max doesn't mutate
-            );
+            o.initialized = true;
+            shared.add(o.wrapped());
+            if T::hooks().save_state {
+                o.restore_saved_state();
+            }
+            if T::process_attr_args() {
+                max_sys::attr_args_process(
+                    o.max_obj() as _,
+                    args.len() as _,
+                    std::mem::transmute::<_, _>(args.as_ptr()), //casts to mutable but max doesn't mutate
+                );
+            }
             o
         })
     }
@@ -677,11 +1717,13 @@ where
         data: *mut c_void,
     ) {
         let notification = Notification::new(sender_name, message, sender, data);
-        self.internal().handle_notification(&notification);
+        self.guard("notify", || {
+            self.internal().handle_notification(&notification)
+        });
     }
 
     extern "C" fn assist_tramp(&self, _b: *mut c_void, io: c_long, index: c_long, s: *mut c_char) {
-        self.internal().assist(io, index, s);
+        self.guard("assist", || self.internal().assist(io, index, s));
     }
 }
 
@@ -693,11 +1735,13 @@ where
     ///
     /// # Remarks
     ///
-    /// This method expects to only be called from the main thread. Internally, it locks a mutex
-    /// and looks up your class by type name. If your class has alrady been registered it won't
-    /// re-register.
+    /// This method expects to only be called from the main thread. It looks up your class by type
+    /// name, if your class has already been registered it won't re-register.
     ///
-    /// This will deadlock if you call `register()` again inside your `T::class_setup()`.
+    /// Your `T::class_setup()` can register other classes that your class depends on, like helper
+    /// `ClassType::NoBox` classes.
+    /// Registering a class from its own `class_setup`, directly or through the classes it
+    /// registers, reports an error and does nothing.
     pub unsafe fn register(lookup_class: bool) {
         Self::register_common(
             lookup_class,
@@ -713,9 +1757,10 @@ where
                         ),
                     ),
                 );
-                //TODO somehow pass the lock so that classes can register additional classes
+                if T::inlet_dispatch() {
+                    c.capture_fallbacks();
+                }
                 MSPWrapperInternal::<T>::class_setup(&mut c);
-                let dsp64 = CString::new("dsp64").unwrap();
                 max_sys::class_addmethod(
                     c.inner(),
                     Some(std::mem::transmute::<
@@ -729,7 +1774,7 @@ where
                         ),
                         MaxMethod,
                     >(Self::dsp64)),
-                    dsp64.as_ptr(),
+                    crate::sym!("dsp64").as_cstr().as_ptr(),
                     max_sys::e_max_atomtypes::A_CANT,
                     0,
                 );
@@ -747,59 +1792,70 @@ where
     ) -> *mut c_void {
         let sym: SymbolRef = sym.into();
         let args = std::slice::from_raw_parts(std::mem::transmute::<_, _>(argv), argc as usize);
-        let o = ObjBox::into_raw(Self::new(sym, &args));
-        assert_eq!((&*o).msp_obj(), (&*o).wrapped().msp_obj());
-        std::mem::transmute::<_, _>(o)
+        //a panic drops the partially constructed object and reports, max gets null
+        crate::panic::catch(std::ptr::null_mut(), T::class_name(), || {
+            let o = ObjBox::into_raw(Self::new(sym, &args));
+            assert_eq!((&*o).msp_obj(), (&*o).wrapped().msp_obj());
+            std::mem::transmute::<_, _>(o)
+        })
+        .unwrap_or(std::ptr::null_mut())
     }
 
     /// Create an instance of the wrapper, on the heap.
     pub fn new(sym: SymbolRef, args: &[Atom]) -> ObjBox<Self> {
         unsafe {
-            new_common(key::<T>(), |max_class| {
+            new_common(|max_class, shared: &'static ClassShared<T>| {
                 let mut o: ObjBox<Self> = ObjBox::alloc(max_class);
+                o.initialized = false;
                 let internal = MSPWrapperInternal::<T>::new(o.msp_obj(), sym.clone(), args);
                 o.wrapped = MaybeUninit::new(internal);
-                //process attribute arguments
-                //TODO optionally don't process?
-                max_sys::attr_args_process(
-                    o.max_obj() as _,
-                    args.len() as _,
-                    std::mem::transmute::<_, _>(args.as_ptr()), //casts to mutable but max doesn't mutate
-                );
+                o.initialized = true;
+                shared.add(o.wrapped());
+                if T::hooks().save_state {
+                    o.restore_saved_state();
+                }
+                if T::process_attr_args() {
+                    max_sys::attr_args_process(
+                        o.max_obj() as _,
+                        args.len() as _,
+                        std::mem::transmute::<_, _>(args.as_ptr()), //casts to mutable but max doesn't mutate
+                    );
+                }
                 o
             })
         }
     }
 
     extern "C" fn free_msp(&mut self) {
-        //free dsp first
-        unsafe {
-            max_sys::z_dsp_free(self.msp_obj());
+        //free dsp first, dsp is only set up once construction succeeds
+        if self.initialized {
+            unsafe {
+                max_sys::z_dsp_free(self.msp_obj());
+            }
         }
         self.free_wrapped();
     }
 
     extern "C" fn perform64(
         &mut self,
-        dsp64: *mut max_sys::t_object,
+        _dsp64: *mut max_sys::t_object,
         ins: *const *const f64,
         numins: c_long,
         outs: *mut *mut f64,
         numouts: c_long,
         sampleframes: c_long,
-        flags: c_long,
-        userparam: *mut c_void,
+        _flags: c_long,
+        _userparam: *mut c_void,
     ) {
+        let obj = self.obj_ptr();
         unsafe {
             (&mut *self.wrapped.as_mut_ptr()).perform64(
-                dsp64,
+                obj,
                 ins,
                 numins,
                 outs,
                 numouts,
                 sampleframes,
-                flags,
-                userparam,
             );
         }
     }
@@ -813,7 +1869,8 @@ where
         _flags: i64,
     ) {
         unsafe {
-            self.wrapped().dsp_setup(samplerate);
+            //still add perform after a panic, it outputs silence if it can't run
+            self.with_wrapped("dsp_setup", |w| w.dsp_setup(samplerate));
             max_sys::dsp_add64(
                 dsp64,
                 self.max_obj(),
@@ -855,11 +1912,13 @@ where
         data: *mut c_void,
     ) {
         let notification = Notification::new(sender_name, message, sender, data);
-        self.internal().handle_notification(&notification);
+        self.guard("notify", || {
+            self.internal().handle_notification(&notification)
+        });
     }
 
     extern "C" fn assist_tramp(&self, _b: *mut c_void, io: c_long, index: c_long, s: *mut c_char) {
-        self.internal().assist(io, index, s);
+        self.guard("assist", || self.internal().assist(io, index, s));
     }
 }
 
@@ -968,3 +2027,229 @@ where
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Counted(usize);
+
+    impl ObjWrapped<Counted> for Counted {
+        fn class_name() -> &'static str {
+            "counted"
+        }
+    }
+
+    impl ClassData for Counted {
+        type Data = Vec<usize>;
+
+        fn new_class_data() -> Self::Data {
+            vec![1, 2, 3]
+        }
+    }
+
+    //records the hooks that were called
+    #[derive(Default)]
+    struct Hooked {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl Hooked {
+        fn call(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.calls.lock().unwrap())
+        }
+    }
+
+    impl ObjWrapped<Hooked> for Hooked {
+        fn class_name() -> &'static str {
+            "hooked"
+        }
+
+        fn hooks() -> Hooks {
+            Hooks {
+                loadbang: true,
+                dblclick: true,
+                inletinfo: true,
+                dspstate: true,
+                patcherrename: true,
+                ..Default::default()
+            }
+        }
+
+        fn loadbang(&self) {
+            self.call("loadbang".into());
+        }
+
+        fn dblclick(&self) {
+            self.call("dblclick".into());
+        }
+
+        fn inletinfo(&self, index: usize) -> Option<bool> {
+            Some(index == 1)
+        }
+
+        fn dspstate(&self, on: bool) {
+            self.call(format!("dspstate {}", on));
+        }
+
+        fn patcherrename(&self) {
+            self.call("patcherrename".into());
+        }
+
+        fn pre_free(&self) {
+            self.call("pre_free".into());
+        }
+    }
+
+    impl MaxObjWrapped<Hooked> for Hooked {
+        fn new(_builder: &mut dyn MaxWrappedBuilder<Self>) -> Self {
+            Self::default()
+        }
+    }
+
+    //saves its values with the patcher
+    #[derive(Default)]
+    struct Stateful {
+        state: Mutex<Vec<f64>>,
+    }
+
+    impl ObjWrapped<Stateful> for Stateful {
+        fn class_name() -> &'static str {
+            "stateful"
+        }
+
+        fn hooks() -> Hooks {
+            Hooks {
+                save_state: true,
+                ..Default::default()
+            }
+        }
+
+        fn save_state(&self) -> Option<Vec<Atom>> {
+            Some(self.state.lock().unwrap().iter().map(Atom::from).collect())
+        }
+
+        fn restore_state(&self, state: &[Atom]) {
+            *self.state.lock().unwrap() = state.iter().map(Atom::get_float).collect();
+        }
+    }
+
+    impl MaxObjWrapped<Stateful> for Stateful {
+        fn new(_builder: &mut dyn MaxWrappedBuilder<Self>) -> Self {
+            Self::default()
+        }
+    }
+
+    //a wrapper that max didn't allocate, for calling the trampolines
+    fn wrap<T: MaxObjWrapped<T>>() -> Box<MaxObjWrapper<T>> {
+        let mut w = Box::new(MaxObjWrapper::<T> {
+            s_obj: unsafe { std::mem::zeroed() },
+            wrapped: MaybeUninit::uninit(),
+            initialized: false,
+            _phantom: PhantomData,
+        });
+        let internal = MaxWrapperInternal::new(w.obj_ptr(), crate::sym!("x"), &[]);
+        w.wrapped = MaybeUninit::new(internal);
+        w.initialized = true;
+        w
+    }
+
+    #[test]
+    fn hook_tramps() {
+        let mut w = wrap::<Hooked>();
+        w.loadbang_tramp();
+        w.dblclick_tramp();
+        w.dspstate_tramp(1);
+        w.patcherrename_tramp();
+        assert_eq!(
+            w.wrapped().take(),
+            vec![
+                "loadbang".to_string(),
+                "dblclick".to_string(),
+                "dspstate true".to_string(),
+                "patcherrename".to_string()
+            ]
+        );
+
+        let info = |w: &MaxObjWrapper<Hooked>, index| {
+            let mut cold: c_char = 0;
+            w.inlet_info(std::ptr::null_mut(), index, &mut cold);
+            cold
+        };
+        assert_eq!(info(&w, 0), 0);
+        assert_eq!(info(&w, 1), 1);
+
+        w.free_wrapped();
+        assert!(!w.initialized);
+    }
+
+    #[test]
+    fn saved_state() {
+        let saved = wrap::<Stateful>();
+        *saved.wrapped().state.lock().unwrap() = vec![2.0, 0.5];
+        let d = unsafe { crate::test::dictionary::dictionary_new() };
+        saved.appendtodictionary_tramp(d);
+
+        //max makes the box dictionary available through #D while the object is created
+        let restored = wrap::<Stateful>();
+        unsafe {
+            (*crate::sym!("#D").inner()).s_thing = d as _;
+        }
+        restored.restore_saved_state();
+        unsafe {
+            (*crate::sym!("#D").inner()).s_thing = std::ptr::null_mut();
+            crate::test::object::object_free(d as _);
+        }
+
+        assert_eq!(*restored.wrapped().state.lock().unwrap(), vec![2.0, 0.5]);
+    }
+
+    #[test]
+    fn instance_registry() {
+        let shared = ClassShared::<Counted>::new(ClassFallbacks::default());
+        assert_eq!(shared.data(), &vec![1, 2, 3]);
+        assert!(shared.is_empty());
+
+        let a = Counted(1);
+        let b = Counted(2);
+        shared.add(&a);
+        shared.add(&b);
+        assert_eq!(shared.len(), 2);
+        let mut sum = 0;
+        shared.for_each(|i| sum += i.0);
+        assert_eq!(sum, 3);
+
+        shared.remove(&a);
+        let mut seen = Vec::new();
+        shared.for_each(|i| seen.push(i.0));
+        assert_eq!(seen, vec![2]);
+    }
+
+    #[test]
+    fn instance_registry_panic() {
+        let shared = ClassShared::<Counted>::new(ClassFallbacks::default());
+        let a = Counted(1);
+        let b = Counted(2);
+        shared.add(&a);
+
+        //the registry can be used from the callback
+        shared.for_each(|_| {
+            shared.add(&b);
+            assert_eq!(shared.len(), 2);
+        });
+
+        //a panic in the callback doesn't leave the registry unusable
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            shared.for_each(|_| panic!("callback"));
+        }));
+        assert!(r.is_err());
+        shared.remove(&a);
+        shared.remove(&b);
+        assert!(shared.is_empty());
+    }
+}
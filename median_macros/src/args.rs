@@ -0,0 +1,136 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Fields, Ident, Token, Type,
+};
+
+//how a field is filled when its argument is missing
+enum FieldKind {
+    Required,
+    Optional,
+    Default(Option<Box<Expr>>),
+    Rest,
+}
+
+pub fn derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    crate::error::wrap(derive_creation_args(input).map(|r| r.into()))
+}
+
+fn derive_creation_args(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => &f.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "CreationArgs needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "CreationArgs can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut parse = Vec::new();
+    let mut idents = Vec::new();
+    let mut rest: Option<&Ident> = None;
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        if let Some(r) = rest {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!("`{}` comes after the #[arg(rest)] field `{}`", ident, r),
+            ));
+        }
+        let label = ident.to_string();
+        parse.push(match field_kind(field)? {
+            FieldKind::Required => quote! {
+                let #ident = ::median::args::required(__args, &mut __index, #label)?;
+            },
+            FieldKind::Optional => quote! {
+                let #ident = ::median::args::optional(__args, &mut __index, #label)?;
+            },
+            FieldKind::Default(None) => quote! {
+                let #ident = ::median::args::optional(__args, &mut __index, #label)?
+                    .unwrap_or_default();
+            },
+            FieldKind::Default(Some(expr)) => quote! {
+                let #ident = ::median::args::optional(__args, &mut __index, #label)?
+                    .unwrap_or_else(|| #expr);
+            },
+            FieldKind::Rest => {
+                rest = Some(ident);
+                quote! {
+                    let #ident = ::median::args::rest(__args, &mut __index);
+                }
+            }
+        });
+        idents.push(ident);
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::median::args::CreationArgs for #name #ty_generics #where_clause {
+            fn parse(
+                __args: &[::median::atom::Atom],
+            ) -> ::std::result::Result<Self, ::median::args::ArgsError> {
+                #[allow(unused_mut, unused_variables)]
+                let mut __index = 0usize;
+                #(#parse)*
+                Ok(Self { #(#idents),* })
+            }
+        }
+    })
+}
+
+fn field_kind(field: &syn::Field) -> syn::Result<FieldKind> {
+    let mut kind = None;
+    for attr in field.attrs.iter().filter(|a| a.path.is_ident("arg")) {
+        if kind.is_some() {
+            return Err(syn::Error::new(attr.span(), "only one #[arg] is allowed"));
+        }
+        kind = Some(attr.parse_args_with(|input: syn::parse::ParseStream| {
+            let ident: Ident = input.parse()?;
+            if ident == "rest" {
+                Ok(FieldKind::Rest)
+            } else if ident == "default" {
+                if input.peek(Token![=]) {
+                    input.parse::<Token![=]>()?;
+                    Ok(FieldKind::Default(Some(Box::new(input.parse()?))))
+                } else {
+                    Ok(FieldKind::Default(None))
+                }
+            } else {
+                Err(syn::Error::new(
+                    ident.span(),
+                    "expected `default`, `default = value` or `rest`",
+                ))
+            }
+        })?);
+    }
+    Ok(kind.unwrap_or_else(|| {
+        if is_option(&field.ty) {
+            FieldKind::Optional
+        } else {
+            FieldKind::Required
+        }
+    }))
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(p) => p
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
@@ -24,9 +24,9 @@ impl Parse for Parsed {
 pub fn parse_and_build(input: proc_macro::TokenStream, with_main: bool) -> proc_macro::TokenStream {
     let Parsed { items } = parse_macro_input!(input as Parsed);
     crate::error::wrap(match process(items) {
-        Ok((ts, class_name)) => {
+        Ok((ts, class_names)) => {
             if with_main {
-                match ext_main_classes(&[class_name]) {
+                match ext_main_classes(&class_names) {
                     Ok(m) => Ok(quote! {
                         #ts
                         #m
@@ -46,10 +46,11 @@ pub fn ext_main(tokens: proc_macro2::TokenStream) -> syn::Result<proc_macro2::To
     Ok(quote! {
         #[no_mangle]
         pub unsafe extern "C" fn ext_main(_r: *mut ::std::ffi::c_void) {
-            if std::panic::catch_unwind(|| {
+            //report rather than unwinding into max
+            if let Err(e) = std::panic::catch_unwind(|| {
                 #tokens
-            }).is_err() {
-                std::process::exit(1);
+            }) {
+                ::median::panic::report(std::ptr::null_mut(), "ext_main", e.as_ref());
             }
         }
     }
@@ -59,15 +60,16 @@ pub fn ext_main(tokens: proc_macro2::TokenStream) -> syn::Result<proc_macro2::To
 pub fn ext_main_classes(class_names: &[Ident]) -> syn::Result<proc_macro2::TokenStream> {
     let register: Vec<_> = class_names
         .iter()
-        .map(|n| quote! { #n::register() })
+        .map(|n| quote! { #n::register(); })
         .collect();
     Ok(quote! {
         #[no_mangle]
         pub unsafe extern "C" fn ext_main(_r: *mut ::std::ffi::c_void) {
-            if std::panic::catch_unwind(|| {
+            //report rather than unwinding into max
+            if let Err(e) = std::panic::catch_unwind(|| {
                 #(#register)*
-            }).is_err() {
-                std::process::exit(1);
+            }) {
+                ::median::panic::report(std::ptr::null_mut(), "ext_main", e.as_ref());
             }
         }
     }
@@ -78,6 +80,8 @@ struct StructDetails {
     the_struct: ItemStruct,
     class_name: Ident,
     class_alias: String,
+    //additional names, registered with class_alias
+    aliases: Vec<LitStr>,
 }
 
 //an attribute to specify the name of the class
@@ -111,16 +115,78 @@ fn process_struct(mut s: ItemStruct) -> syn::Result<StructDetails> {
         class_alias = n.name.value();
     }
 
+    //find alias attributes and remove them, #[alias("a")] or #[alias("a", "b")]
+    let mut aliases = Vec::new();
+    let mut attrs = Vec::new();
+    for a in s.attrs.drain(..) {
+        if a.path.is_ident("alias") {
+            let names = a.parse_args_with(
+                syn::punctuated::Punctuated::<LitStr, Token![,]>::parse_terminated,
+            )?;
+            aliases.extend(names);
+        } else {
+            attrs.push(a);
+        }
+    }
+    s.attrs = attrs;
+
     Ok(StructDetails {
         the_struct: s,
         class_name,
         class_alias,
+        aliases,
     })
 }
 
 struct ImplDetails {
     wrapper_type: Ident,
     processed_impls: Vec<ItemImpl>,
+    //lifecycle hooks written in the MaxObjWrapped/MSPObjWrapped impl, they belong to ObjWrapped
+    hook_methods: Vec<syn::ImplItem>,
+}
+
+//ObjWrapped lifecycle hooks that are registered with max, and the Hooks field for each
+const HOOKS: &[&str] = &[
+    "loadbang",
+    "dblclick",
+    "inletinfo",
+    "dspstate",
+    "patcherrename",
+    "save_state",
+    "getvalueof",
+    "preset",
+];
+
+fn is_hook_method(item: &syn::ImplItem) -> bool {
+    match item {
+        syn::ImplItem::Method(m) => {
+            m.sig.ident == "pre_free"
+                || m.sig.ident == "restore_state"
+                || m.sig.ident == "setvalueof"
+                || HOOKS.iter().any(|h| m.sig.ident == h)
+        }
+        _ => false,
+    }
+}
+
+//implement ObjWrapped::hooks for the hooks implemented in items
+fn hooks_method(items: &[syn::ImplItem]) -> syn::ImplItem {
+    let implemented = HOOKS.iter().filter(|h| {
+        items.iter().any(|item| match item {
+            syn::ImplItem::Method(m) => m.sig.ident == h,
+            _ => false,
+        })
+    });
+    let fields = implemented.map(|h| Ident::new(h, proc_macro2::Span::call_site()));
+    syn::parse2(quote! {
+        fn hooks() -> ::median::wrapper::Hooks {
+            ::median::wrapper::Hooks {
+                #(#fields: true,)*
+                ..::std::default::Default::default()
+            }
+        }
+    })
+    .expect("to parse as method")
 }
 
 fn process_impls(
@@ -156,6 +222,10 @@ fn process_impls(
     //find class_setup, if it exists
     let mut the_impl = the_impl.unwrap();
 
+    let (hook_methods, items): (Vec<_>, Vec<_>) =
+        the_impl.items.drain(..).partition(is_hook_method);
+    the_impl.items = items;
+
     let mut class_setup = None;
     if let Some(pos) = the_impl.items.iter().position(|item| {
         if let syn::ImplItem::Method(m) = item {
@@ -173,13 +243,10 @@ fn process_impls(
     };
 
     let mut class_setup: syn::ImplItemMethod = class_setup.unwrap_or_else(|| {
-        syn::parse(
-            quote! {
-                fn class_setup(c: &mut ::median::class::Class<::median::wrapper::#wrapper_type<Self>>) {
-                }
+        syn::parse2(quote! {
+            fn class_setup(c: &mut ::median::class::Class<::median::wrapper::#wrapper_type<Self>>) {
             }
-            .into(),
-        )
+        })
         .expect("to parse as method")
     });
 
@@ -265,18 +332,40 @@ fn process_impls(
                                 let var_name = Ident::new(var_name, a.span());
 
                                 class_setup.block.stmts.push(
-                                    syn::parse(
-                                        quote! { #class_setup_class_var.add_method(median::method::Method::#var_name(Self::#tramp_name)).unwrap(); }.into()
+                                    syn::parse2(
+                                        quote! { #class_setup_class_var.add_method(median::method::Method::#var_name(Self::#tramp_name)).unwrap(); }
                                     ).expect("to create a statement"));
                                 m.attrs.push(a);
                             };
                         }
 
-                        syn::ImplItem::Method(m)
+                        //methods that give a value back to object_method_typed callers,
+                        //#[gimmeback] uses the method name as the selector, #[gimmeback("sel")]
+                        //overrides it
+                        if let Some(pos) = m.attrs.iter().position(|a| a.path.is_ident("gimmeback")) {
+                            let mut a = m.attrs.remove(pos).clone();
+                            let sel = if a.tokens.is_empty() {
+                                LitStr::new(&m.sig.ident.to_string(), m.sig.ident.span())
+                            } else {
+                                a.parse_args::<LitStr>()?
+                            };
+                            a.path = syn::parse_str("::median::wrapper::gimmeback_tramp").expect("to make tramp");
+                            attr_add_type(&mut a);
+
+                            let tramp_name = std::format!("{}_tramp", m.sig.ident);
+                            let tramp_name = Ident::new(tramp_name.as_str(), m.span());
+                            class_setup.block.stmts.push(
+                                syn::parse2(
+                                    quote! { #class_setup_class_var.add_method(median::method::Method::SelGimmeBack(#sel, Self::#tramp_name)).unwrap(); }
+                                ).expect("to create a statement"));
+                            m.attrs.push(a);
+                        }
+
+                        Ok(syn::ImplItem::Method(m))
                     }
-                    _ => item.clone(),
+                    _ => Ok(item.clone()),
                 })
-                .collect();
+                .collect::<syn::Result<Vec<_>>>()?;
         }
     }
 
@@ -287,54 +376,113 @@ fn process_impls(
     Ok(ImplDetails {
         wrapper_type,
         processed_impls,
+        hook_methods,
     })
 }
 
-fn process(items: Vec<Item>) -> syn::Result<(proc_macro2::TokenStream, Ident)> {
+//the type an impl is for, if it is a plain path
+fn impl_type_ident(i: &ItemImpl) -> Option<&Ident> {
+    match i.self_ty.as_ref() {
+        syn::Type::Path(p) => p.path.segments.last().map(|s| &s.ident),
+        _ => None,
+    }
+}
+
+//is the impl a hand written ObjWrapped impl
+fn is_obj_wrapped_impl(i: &ItemImpl) -> bool {
+    match &i.trait_ {
+        Some((_, path, _)) => path
+            .segments
+            .last()
+            .is_some_and(|l| l.ident == "ObjWrapped"),
+        None => false,
+    }
+}
+
+fn process(items: Vec<Item>) -> syn::Result<(proc_macro2::TokenStream, Vec<Ident>)> {
+    let mut structs = Vec::new();
     let mut impls = Vec::new();
-    let mut the_struct = None;
     let mut remain = Vec::new();
-    let mut has_obj_wrapped = false;
 
-    for item in items.iter() {
+    for item in items.into_iter() {
         match item {
-            Item::Struct(i) => {
-                if the_struct.is_some() {
-                    return Err(syn::Error::new(i.span(), "only one wrapped struct allowed"));
-                }
-                the_struct = Some(i);
-            }
-            Item::Impl(i) => {
-                //see if ObjWrapped is already implemented so we don't double impl
-                match &i.trait_ {
-                    Some((_, path, _)) => {
-                        if let Some(l) = path.segments.last() {
-                            if l.ident == "ObjWrapped" {
-                                has_obj_wrapped = true;
-                            }
-                        }
-                    }
-                    _ => (),
-                }
-                impls.push(i.clone());
-            }
+            Item::Struct(i) => structs.push(i),
+            Item::Impl(i) => impls.push(i),
             _ => remain.push(item),
         }
     }
 
+    if structs.is_empty() {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "expected at least one wrapped struct",
+        ));
+    }
+
+    let mut out = quote! {};
+    let mut class_names = Vec::new();
+    for s in structs {
+        //take the impls for this struct
+        let (mine, rest): (Vec<_>, Vec<_>) = impls
+            .into_iter()
+            .partition(|i| impl_type_ident(i) == Some(&s.ident));
+        impls = rest;
+        let (ts, class_name) = process_class(s, mine)?;
+        out = quote! {
+            #out
+            #ts
+        };
+        class_names.push(class_name);
+    }
+
+    //impls for types that aren't wrapped are passed through untouched
+    Ok((
+        quote! {
+            #out
+
+            #(#impls)*
+
+            #(#remain)*
+        },
+        class_names,
+    ))
+}
+
+fn process_class(
+    the_struct: ItemStruct,
+    impls: Vec<ItemImpl>,
+) -> syn::Result<(proc_macro2::TokenStream, Ident)> {
+    //see if ObjWrapped is already implemented so we don't double impl
+    let has_obj_wrapped = impls.iter().any(is_obj_wrapped_impl);
+
     //process the struct, getting the names
     let StructDetails {
         the_struct,
         class_name,
         class_alias,
-    } = process_struct(the_struct.unwrap().clone())?;
+        aliases,
+    } = process_struct(the_struct)?;
 
     //process the impls, getting the wrapper type
     let ImplDetails {
         wrapper_type,
-        processed_impls: impls,
+        processed_impls: mut impls,
+        mut hook_methods,
     } = process_impls(&the_struct, &class_name, impls)?;
 
+    //move the hooks into a hand written ObjWrapped impl, and implement hooks unless it does
+    if let Some(obj_wrapped) = impls.iter_mut().find(|i| is_obj_wrapped_impl(i)) {
+        obj_wrapped.items.append(&mut hook_methods);
+        let has_hooks = obj_wrapped.items.iter().any(|item| match item {
+            syn::ImplItem::Method(m) => m.sig.ident == "hooks",
+            _ => false,
+        });
+        if !has_hooks {
+            let hooks = hooks_method(&obj_wrapped.items);
+            obj_wrapped.items.push(hooks);
+        }
+    }
+
     let mut out = quote! {
         #the_struct
 
@@ -346,21 +494,207 @@ fn process(items: Vec<Item>) -> syn::Result<(proc_macro2::TokenStream, Ident)> {
         }
 
         #(#impls)*
-
-        #(#remain)*
     };
 
     if !has_obj_wrapped {
         let max_class_name = LitStr::new(&class_alias, the_struct.span());
+        let hooks = hooks_method(&hook_methods);
         out = quote! {
             #out
             impl ::median::wrapper::ObjWrapped<#class_name> for #class_name {
                 fn class_name() -> &'static str {
                     &#max_class_name
                 }
+
+                fn class_aliases() -> &'static [&'static str] {
+                    &[#(#aliases),*]
+                }
+
+                #hooks
+
+                #(#hook_methods)*
             }
         };
+    } else if let Some(a) = aliases.first() {
+        return Err(syn::Error::new(
+            a.span(),
+            "#[alias] can't be used when ObjWrapped is implemented by hand, implement class_aliases instead",
+        ));
+    }
+
+    Ok((out, class_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method(src: &str) -> syn::ImplItem {
+        syn::parse_str(src).expect("to parse as method")
+    }
+
+    //the fields set to true by a generated hooks method
+    fn hook_fields(hooks: &syn::ImplItem) -> Vec<String> {
+        let m = match hooks {
+            syn::ImplItem::Method(m) => m,
+            _ => panic!("expected a method"),
+        };
+        match m.block.stmts.first() {
+            Some(syn::Stmt::Expr(syn::Expr::Struct(s))) => s
+                .fields
+                .iter()
+                .map(|f| match &f.member {
+                    syn::Member::Named(i) => i.to_string(),
+                    _ => panic!("expected a named field"),
+                })
+                .collect(),
+            _ => panic!("expected a Hooks literal"),
+        }
+    }
+
+    //expand the items and find the impl of the trait `name`
+    fn expand_impl(src: &str, name: &str) -> ItemImpl {
+        let Parsed { items } = syn::parse_str(src).expect("to parse items");
+        let (ts, _) = process(items).expect("to process");
+        let file: syn::File = syn::parse2(ts).expect("to parse the expansion");
+        file.items
+            .into_iter()
+            .find_map(|item| match item {
+                Item::Impl(i) => match &i.trait_ {
+                    Some((_, path, _)) if path.segments.last().unwrap().ident == name => Some(i),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .expect("to find the impl")
+    }
+
+    fn method_names(i: &ItemImpl) -> Vec<String> {
+        i.items
+            .iter()
+            .filter_map(|item| match item {
+                syn::ImplItem::Method(m) => Some(m.sig.ident.to_string()),
+                _ => None,
+            })
+            .collect()
     }
 
-    Ok((out.into(), class_name))
+    fn find_method<'a>(i: &'a ItemImpl, name: &str) -> &'a syn::ImplItem {
+        i.items
+            .iter()
+            .find(|item| matches!(item, syn::ImplItem::Method(m) if m.sig.ident == name))
+            .expect("to find the method")
+    }
+
+    #[test]
+    fn hook_methods() {
+        for name in HOOKS
+            .iter()
+            .chain(&["pre_free", "restore_state", "setvalueof"])
+        {
+            assert!(is_hook_method(&method(&format!("fn {}(&self) {{}}", name))));
+        }
+        assert!(!is_hook_method(&method(
+            "fn new(builder: &mut dyn MaxWrappedBuilder<Self>) -> Self { Self }"
+        )));
+        assert!(!is_hook_method(&method("fn bang(&self) {}")));
+        assert!(!is_hook_method(&method("const LOADBANG: bool = true;")));
+
+        let items = vec![
+            method("fn loadbang(&self) {}"),
+            method("fn save_state(&self) -> Option<Vec<Atom>> { None }"),
+            //restored state and pre_free don't need registering
+            method("fn restore_state(&self, _state: &[Atom]) {}"),
+            method("fn pre_free(&self) {}"),
+            method("fn bang(&self) {}"),
+        ];
+        assert_eq!(
+            hook_fields(&hooks_method(&items)),
+            vec!["loadbang".to_string(), "save_state".to_string()]
+        );
+        assert!(hook_fields(&hooks_method(&[])).is_empty());
+    }
+
+    #[test]
+    fn hooks_moved() {
+        let max = "
+            impl MaxObjWrapped<Hooked> for Hooked {
+                fn new(_builder: &mut dyn MaxWrappedBuilder<Self>) -> Self {
+                    Self
+                }
+                fn loadbang(&self) {}
+                fn getvalueof(&self) -> Option<Vec<Atom>> {
+                    None
+                }
+                fn pre_free(&self) {}
+            }
+        ";
+
+        //the generated ObjWrapped impl gets the hooks
+        let src = format!("pub struct Hooked; {}", max);
+        let obj = expand_impl(&src, "ObjWrapped");
+        let names = method_names(&obj);
+        for name in &["loadbang", "getvalueof", "pre_free", "hooks"] {
+            assert!(names.iter().any(|n| n == name), "{} not moved", name);
+        }
+        assert_eq!(
+            hook_fields(find_method(&obj, "hooks")),
+            vec!["loadbang".to_string(), "getvalueof".to_string()]
+        );
+        let wrapped = expand_impl(&src, "MaxObjWrapped");
+        assert_eq!(
+            method_names(&wrapped),
+            vec!["new".to_string(), "class_setup".to_string()]
+        );
+
+        //so does a hand written one
+        let src = format!(
+            "pub struct Hooked;
+            impl ObjWrapped<Hooked> for Hooked {{
+                fn class_name() -> &'static str {{
+                    \"hooked\"
+                }}
+            }}
+            {}",
+            max
+        );
+        let obj = expand_impl(&src, "ObjWrapped");
+        assert_eq!(
+            method_names(&obj),
+            vec![
+                "class_name".to_string(),
+                "loadbang".to_string(),
+                "getvalueof".to_string(),
+                "pre_free".to_string(),
+                "hooks".to_string()
+            ]
+        );
+        assert_eq!(
+            hook_fields(find_method(&obj, "hooks")),
+            vec!["loadbang".to_string(), "getvalueof".to_string()]
+        );
+
+        //unless it implements hooks itself
+        let src = format!(
+            "pub struct Hooked;
+            impl ObjWrapped<Hooked> for Hooked {{
+                fn class_name() -> &'static str {{
+                    \"hooked\"
+                }}
+                fn hooks() -> Hooks {{
+                    Hooks::default()
+                }}
+            }}
+            {}",
+            max
+        );
+        let obj = expand_impl(&src, "ObjWrapped");
+        assert_eq!(
+            method_names(&obj)
+                .iter()
+                .filter(|n| n.as_str() == "hooks")
+                .count(),
+            1
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use proc_macro::TokenStream;
+mod args;
 mod error;
 mod external;
 mod tramp;
@@ -33,6 +34,11 @@ pub fn wrapped_sel_list_tramp(attr: TokenStream, item: TokenStream) -> TokenStre
     tramp::wrapped_sel_list_tramp(attr, item)
 }
 
+#[proc_macro_attribute]
+pub fn wrapped_gimmeback_tramp(attr: TokenStream, item: TokenStream) -> TokenStream {
+    tramp::wrapped_gimmeback_tramp(attr, item)
+}
+
 #[proc_macro_attribute]
 pub fn wrapped_list_tramp(attr: TokenStream, item: TokenStream) -> TokenStream {
     tramp::wrapped_list_tramp(attr, item)
@@ -47,3 +53,8 @@ pub fn wrapped_attr_get_tramp(attr: TokenStream, item: TokenStream) -> TokenStre
 pub fn wrapped_attr_set_tramp(attr: TokenStream, item: TokenStream) -> TokenStream {
     tramp::wrapped_attr_set_tramp(attr, item)
 }
+
+#[proc_macro_derive(CreationArgs, attributes(arg))]
+pub fn derive_creation_args(input: TokenStream) -> TokenStream {
+    args::derive(input)
+}
@@ -27,6 +27,12 @@ pub fn wrapped_sel_list_tramp(attr: TokenStream, item: TokenStream) -> TokenStre
     crate::error::wrap(wrapped_sel_list_tramp_with_type(wrapper, meth))
 }
 
+pub fn wrapped_gimmeback_tramp(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let TrampArgs { wrapper } = parse_macro_input!(attr as TrampArgs);
+    let meth: ImplItemMethod = parse_macro_input!(item as ImplItemMethod);
+    crate::error::wrap(wrapped_gimmeback_tramp_with_type(wrapper, meth))
+}
+
 // tramp still has selector, but it isn't passed on to wrapped method
 pub fn wrapped_list_tramp(attr: TokenStream, item: TokenStream) -> TokenStream {
     let TrampArgs { wrapper } = parse_macro_input!(attr as TrampArgs);
@@ -82,7 +88,10 @@ pub fn wrapped_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<TokenStream
         .collect::<Result<Vec<Box<Pat>>, _>>()?;
     let expanded = quote! {
         pub extern "C" fn #tramp_name(wrapper: &#t, #(#args),*) {
-            ::median::wrapper::WrapperWrapped::wrapped(wrapper).#meth_name(#(#vars),*)
+            let obj = ::median::object::MaxObj::max_obj(wrapper);
+            ::median::wrapper::WrapperWrapped::with_wrapped(wrapper, stringify!(#meth_name), |w| {
+                ::median::method::HandlerResult::report(w.#meth_name(#(#vars),*), obj)
+            });
         }
         #meth
     };
@@ -104,8 +113,11 @@ pub fn wrapped_defer_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<Token
             ac: ::std::os::raw::c_long,
             av: *const ::median::max_sys::t_atom,
         ) {
-            median::method::sel_list(sel, ac, av, |sym, atoms| {
-                ::median::wrapper::WrapperWrapped::wrapped(wrapper).#meth_name(&sym, atoms);
+            let obj = ::median::object::MaxObj::max_obj(wrapper);
+            ::median::wrapper::WrapperWrapped::with_wrapped(wrapper, stringify!(#meth_name), |w| {
+                median::method::sel_list(sel, ac, av, |sym, atoms| {
+                    ::median::method::HandlerResult::report(w.#meth_name(&sym, atoms), obj)
+                })
             });
         }
         #meth
@@ -126,8 +138,11 @@ pub fn wrapped_sel_list_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<To
             ac: ::std::os::raw::c_long,
             av: *const ::median::max_sys::t_atom,
         ) {
-            median::method::sel_list(sel, ac, av, |sym, atoms| {
-                ::median::wrapper::WrapperWrapped::wrapped(wrapper).#meth_name(&sym, atoms);
+            let obj = ::median::object::MaxObj::max_obj(wrapper);
+            ::median::wrapper::WrapperWrapped::with_wrapped(wrapper, stringify!(#meth_name), |w| {
+                median::method::sel_list(sel, ac, av, |sym, atoms| {
+                    ::median::method::HandlerResult::report(w.#meth_name(&sym, atoms), obj)
+                })
             });
         }
         #meth
@@ -135,6 +150,35 @@ pub fn wrapped_sel_list_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<To
     Ok(expanded.into())
 }
 
+pub fn wrapped_gimmeback_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<TokenStream> {
+    let Names {
+        meth_name,
+        tramp_name,
+    } = get_names(&meth);
+    //TODO check signature
+    let expanded = quote! {
+        pub extern "C" fn #tramp_name(
+            wrapper: &#t,
+            sel: *mut ::median::max_sys::t_symbol,
+            ac: ::std::os::raw::c_long,
+            av: *const ::median::max_sys::t_atom,
+            rv: *mut ::median::max_sys::t_atom,
+        ) -> ::median::max_sys::t_max_err {
+            let obj = ::median::object::MaxObj::max_obj(wrapper);
+            ::median::wrapper::WrapperWrapped::with_wrapped(wrapper, stringify!(#meth_name), |w| {
+                unsafe {
+                    ::median::method::sel_list_back(obj, sel, ac, av, rv, |sym, atoms| {
+                        w.#meth_name(&sym, atoms)
+                    })
+                }
+            })
+            .unwrap_or(::median::max_sys::e_max_errorcodes::MAX_ERR_GENERIC as _)
+        }
+        #meth
+    };
+    Ok(expanded.into())
+}
+
 pub fn wrapped_list_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<TokenStream> {
     let Names {
         meth_name,
@@ -148,8 +192,11 @@ pub fn wrapped_list_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<TokenS
             ac: ::std::os::raw::c_long,
             av: *const ::median::max_sys::t_atom,
         ) {
-            median::method::sel_list(sel, ac, av, |_sym, atoms| {
-                ::median::wrapper::WrapperWrapped::wrapped(wrapper).#meth_name(atoms);
+            let obj = ::median::object::MaxObj::max_obj(wrapper);
+            ::median::wrapper::WrapperWrapped::with_wrapped(wrapper, stringify!(#meth_name), |w| {
+                median::method::sel_list(sel, ac, av, |_sym, atoms| {
+                    ::median::method::HandlerResult::report(w.#meth_name(atoms), obj)
+                })
             });
         }
         #meth
@@ -170,7 +217,9 @@ pub fn wrapped_attr_get_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<To
             ac: *mut ::std::os::raw::c_long,
             av: *mut *mut ::median::max_sys::t_atom,
         ) {
-            ::median::attr::get(ac, av, || ::median::wrapper::WrapperWrapped::wrapped(wrapper).#meth_name());
+            ::median::wrapper::WrapperWrapped::with_wrapped(wrapper, stringify!(#meth_name), |w| {
+                ::median::attr::get(ac, av, || w.#meth_name())
+            });
         }
         #meth
     };
@@ -202,8 +251,27 @@ pub fn wrapped_attr_set_tramp_with_type(t: Type, meth: ImplItemMethod) -> Res<To
             _attr: ::std::ffi::c_void,
             ac: ::std::os::raw::c_long,
             av: *mut ::median::max_sys::t_atom,
-        ) {
-            ::median::attr::set(ac, av, |#(#args),*| ::median::wrapper::WrapperWrapped::wrapped(wrapper).#meth_name(#(#vars),*));
+        ) -> ::median::max_sys::t_max_err {
+            //report against the object and reject the value on error
+            let obj = ::median::object::MaxObj::max_obj(wrapper);
+            let err = ::median::wrapper::WrapperWrapped::with_wrapped(wrapper, stringify!(#meth_name), |w| {
+                ::median::attr::set(ac, av, |#(#args),*| {
+                    //already reported against the object
+                    ::median::method::MaxErr(::median::method::HandlerResult::report(
+                        w.#meth_name(#(#vars),*),
+                        obj,
+                    ))
+                })
+            })
+            .unwrap_or(::median::max_sys::e_max_errorcodes::MAX_ERR_GENERIC as _);
+            //let pattr know the attribute changed, set doesn't call the setter without a value
+            if ac > 0
+                && !av.is_null()
+                && err == ::median::max_sys::e_max_errorcodes::MAX_ERR_NONE as ::median::max_sys::t_max_err
+            {
+                ::median::wrapper::WrapperWrapped::notify_modified(wrapper);
+            }
+            err
         }
         #meth
     };